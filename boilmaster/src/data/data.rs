@@ -0,0 +1,132 @@
+use std::{
+	collections::HashMap,
+	fs,
+	sync::{Arc, RwLock},
+};
+
+use anyhow::Context;
+use figment::value::magic::RelativePathBuf;
+use ironworks::zipatch::{PatchRepository, ZiPatch};
+use serde::Deserialize;
+
+use super::{error::Error, version::Version};
+
+/// Version alias that resolves to the newest version available.
+pub const LATEST: &str = "latest";
+
+#[derive(Debug, Deserialize)]
+pub struct Config {
+	patch: PatchConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct PatchConfig {
+	directory: RelativePathBuf,
+	cache: Option<RelativePathBuf>,
+}
+
+pub struct Data {
+	zipatch: ZiPatch,
+
+	// Versions are built on first request, and kept for the lifetime of the service.
+	versions: RwLock<HashMap<String, Arc<Version>>>,
+}
+
+impl Data {
+	pub fn new(config: Config) -> Result<Self, Error> {
+		let directory = config.patch.directory.relative();
+
+		let mut zipatch = ZiPatch::new();
+		if let Some(cache) = config.patch.cache {
+			zipatch = zipatch.with_cache_directory(cache.relative());
+		}
+
+		// The patch directory contains a folder for each repository, named as in
+		// the game's sqpack directory - `ffxiv` for the base game, `exN` for expansions.
+		let entries = fs::read_dir(&directory)
+			.with_context(|| format!("failed to read patch directory {directory:?}"))?;
+		for entry in entries {
+			let path = entry
+				.context("failed to read patch directory entry")?
+				.path();
+			let id = match path.file_name().and_then(|name| name.to_str()) {
+				Some("ffxiv") => 0,
+				Some(name) => match name.strip_prefix("ex").and_then(|id| id.parse().ok()) {
+					Some(id) => id,
+					None => continue,
+				},
+				None => continue,
+			};
+
+			if !path.is_dir() {
+				continue;
+			}
+
+			let repository = PatchRepository::at(&path)
+				.with_context(|| format!("failed to read patch repository {path:?}"))?;
+			zipatch.add_repository(id, repository);
+		}
+
+		Ok(Data {
+			zipatch,
+			versions: Default::default(),
+		})
+	}
+
+	/// List the game versions available, in ascending order.
+	pub fn versions(&self) -> Vec<String> {
+		self.zipatch.versions()
+	}
+
+	/// The key of the newest version available, if any.
+	pub fn latest(&self) -> Option<String> {
+		self.versions().pop()
+	}
+
+	/// Get the specified game version. Omitting the version, or specifying
+	/// [`LATEST`], will resolve to the newest version available.
+	pub fn version(&self, version: Option<&str>) -> Result<Arc<Version>, Error> {
+		let key = self.resolve_key(version.unwrap_or(LATEST))?;
+
+		if let Some(version) = self.versions.read().unwrap().get(&key) {
+			return Ok(version.clone());
+		}
+
+		// Build the version without holding the lock, so requests for other versions
+		// aren't blocked. If another request built it in the meantime, use theirs.
+		let version = Arc::new(Version::new(&self.zipatch, key.clone())?);
+		let version = self
+			.versions
+			.write()
+			.unwrap()
+			.entry(key)
+			.or_insert(version)
+			.clone();
+
+		Ok(version)
+	}
+
+	/// Get the specified game version without retaining it for later requests.
+	/// Versions that have already been built will be reused.
+	pub fn version_uncached(&self, version: &str) -> Result<Arc<Version>, Error> {
+		let key = self.resolve_key(version)?;
+
+		if let Some(version) = self.versions.read().unwrap().get(&key) {
+			return Ok(version.clone());
+		}
+
+		Ok(Arc::new(Version::new(&self.zipatch, key)?))
+	}
+
+	fn resolve_key(&self, version: &str) -> Result<String, Error> {
+		// Only versions that exist in the repositories are accepted, rather than
+		// anything that resolves to a patch - otherwise arbitrary strings would
+		// each build their own version instance.
+		let key = match version {
+			LATEST => self.latest(),
+			version => self.versions().into_iter().find(|key| key == version),
+		};
+
+		key.ok_or_else(|| Error::UnknownVersion(version.into()))
+	}
+}
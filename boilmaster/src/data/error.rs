@@ -0,0 +1,8 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("unknown game version \"{0}\"")]
+	UnknownVersion(String),
+
+	#[error(transparent)]
+	Failure(#[from] anyhow::Error),
+}
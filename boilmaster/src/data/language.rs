@@ -0,0 +1,78 @@
+use std::{fmt, str::FromStr};
+
+use ironworks::excel::Language;
+use serde::{de, Deserialize, Serialize};
+
+/// Game language, represented by its short code, i.e. `en`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LanguageString(Language);
+
+impl From<LanguageString> for Language {
+	fn from(language: LanguageString) -> Self {
+		language.0
+	}
+}
+
+impl From<Language> for LanguageString {
+	fn from(language: Language) -> Self {
+		Self(language)
+	}
+}
+
+impl FromStr for LanguageString {
+	type Err = String;
+
+	fn from_str(string: &str) -> Result<Self, Self::Err> {
+		use Language as L;
+		let language = match string {
+			"none" => L::None,
+			"ja" => L::Japanese,
+			"en" => L::English,
+			"de" => L::German,
+			"fr" => L::French,
+			"chs" => L::ChineseSimplified,
+			"cht" => L::ChineseTraditional,
+			"kr" => L::Korean,
+			other => return Err(format!("unknown language \"{other}\"")),
+		};
+
+		Ok(Self(language))
+	}
+}
+
+impl fmt::Display for LanguageString {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		use Language as L;
+		let string = match self.0 {
+			L::None => "none",
+			L::Japanese => "ja",
+			L::English => "en",
+			L::German => "de",
+			L::French => "fr",
+			L::ChineseSimplified => "chs",
+			L::ChineseTraditional => "cht",
+			L::Korean => "kr",
+		};
+
+		formatter.write_str(string)
+	}
+}
+
+impl<'de> Deserialize<'de> for LanguageString {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		let raw = String::deserialize(deserializer)?;
+		raw.parse().map_err(de::Error::custom)
+	}
+}
+
+impl Serialize for LanguageString {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		serializer.collect_str(self)
+	}
+}
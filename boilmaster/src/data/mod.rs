@@ -0,0 +1,11 @@
+mod data;
+mod error;
+mod language;
+mod version;
+
+pub use {
+	data::{Config, Data, LATEST},
+	error::Error,
+	language::LanguageString,
+	version::Version,
+};
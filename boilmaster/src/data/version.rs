@@ -0,0 +1,80 @@
+use std::{
+	collections::{hash_map::Entry, BTreeMap, HashMap},
+	sync::{Arc, RwLock},
+};
+
+use ironworks::{
+	excel::{Excel, Language},
+	sqpack::SqPack,
+	zipatch::ZiPatch,
+	Ironworks,
+};
+
+use super::error::Error;
+
+pub struct Version {
+	key: String,
+	patches: BTreeMap<u8, String>,
+	ironworks: Arc<Ironworks>,
+
+	// Excel instances are built per default language on first request.
+	excel: RwLock<HashMap<Language, Arc<Excel<'static>>>>,
+}
+
+impl Version {
+	pub(super) fn new(zipatch: &ZiPatch, key: String) -> Result<Self, Error> {
+		let specifier = zipatch.resolve_version(&key).map_err(|error| match error {
+			ironworks::Error::NotFound(_) => Error::UnknownVersion(key.clone()),
+			other => Error::Failure(other.into()),
+		})?;
+
+		let patches = specifier
+			.patches()
+			.iter()
+			.map(|(id, patch)| (*id, patch.clone()))
+			.collect();
+
+		let ironworks = Ironworks::new().with_resource(SqPack::new(zipatch.version(specifier)));
+
+		Ok(Version {
+			key,
+			patches,
+			ironworks: Arc::new(ironworks),
+			excel: Default::default(),
+		})
+	}
+
+	/// The game version this instance represents, i.e. `2022.10.13.0000.0000`.
+	pub fn key(&self) -> &str {
+		&self.key
+	}
+
+	/// The patch each repository is read from, keyed by repository ID.
+	pub fn patches(&self) -> &BTreeMap<u8, String> {
+		&self.patches
+	}
+
+	// TODO: the default language should probably be configurable
+	pub fn excel(&self) -> Arc<Excel<'static>> {
+		self.excel_language(Language::English)
+	}
+
+	/// Excel database for this version, reading rows in the specified language
+	/// by default.
+	pub fn excel_language(&self, language: Language) -> Arc<Excel<'static>> {
+		if let Some(excel) = self.excel.read().unwrap().get(&language) {
+			return Arc::clone(excel);
+		}
+
+		let mut excel = self.excel.write().unwrap();
+		match excel.entry(language) {
+			Entry::Occupied(entry) => Arc::clone(entry.get()),
+			Entry::Vacant(entry) => {
+				let excel = Excel::with()
+					.language(language)
+					.build(Arc::clone(&self.ironworks));
+				Arc::clone(entry.insert(Arc::new(excel)))
+			}
+		}
+	}
+}
@@ -11,7 +11,7 @@ use nom::{
 };
 use serde::{de, Deserialize, Deserializer};
 
-use crate::utility::warnings::{SoftDeserialize, Warnings};
+use crate::utility::warnings::{SoftDeserialize, Warning, WarningCode, Warnings};
 
 // TODO: should this be in a top level filter module? will depend if there's other types of filters i guess. also semantics...
 //       might make sense as read::Filter to go alongside i.e. search::Filter
@@ -63,22 +63,26 @@ impl<'de> SoftDeserialize<'de> for Option<FieldFilter> {
 		D: Deserializer<'de>,
 	{
 		let raw = String::deserialize(deserializer)?;
+		FieldFilter::parse(&raw).map_err(de::Error::custom)
+	}
+}
 
-		let (remaining, filter) = group(&raw)
+impl FieldFilter {
+	/// Parse a field filter string. A `None` filter selects all fields.
+	pub fn parse(input: &str) -> Result<Warnings<Option<Self>>, String> {
+		let (remaining, filter) = group(input)
 			.finish()
-			.map_err(|error| de::Error::custom(format!("field filter parse error: {error}")))?;
+			.map_err(|error| format!("field filter parse error: {error}"))?;
 
 		if !remaining.is_empty() {
-			return Err(de::Error::custom(format!(
+			return Err(format!(
 				"field filter parse error: trailing characters found {remaining:?}"
-			)));
+			));
 		}
 
 		Ok(filter)
 	}
-}
 
-impl FieldFilter {
 	fn merge(self, source: Self) -> Warnings<Option<Self>> {
 		match (self, source) {
 			(Self::Struct(target_struct), Self::Struct(source_struct)) => {
@@ -91,9 +95,12 @@ impl FieldFilter {
 					.map(|array_filter| Some(Self::Array(array_filter)))
 			}
 
-			// TODO: this will need a "path", i think?
-			(fallback_1, fallback_2) => Warnings::new(None).with_warning(format!(
-				"filters `{fallback_1}` and `{fallback_2}` cannot be merged, and have been ignored"
+			// Paths are attached by the enclosing merges as the warning propagates.
+			(fallback_1, fallback_2) => Warnings::new(None).with_warning(Warning::new(
+				WarningCode::FilterMergeConflict,
+				format!(
+					"filters `{fallback_1}` and `{fallback_2}` cannot be merged, and have been ignored"
+				),
 			)),
 		}
 	}
@@ -123,7 +130,8 @@ fn merge_struct_field(
 		None => Warnings::new(source_value),
 
 		// There's a collision, we might need to merge.
-		Some(target_maybe_filter) => merge_optional_filters(target_maybe_filter, source_value),
+		Some(target_maybe_filter) => merge_optional_filters(target_maybe_filter, source_value)
+			.map_warnings(|warning| warning.nested(&key)),
 	};
 
 	new_child.map(|child| {
@@ -133,7 +141,9 @@ fn merge_struct_field(
 }
 
 fn merge_array(left: ArrayFilter, right: ArrayFilter) -> Warnings<ArrayFilter> {
-	merge_optional_filters(left.map(|x| *x), right.map(|x| *x)).map(|output| output.map(Box::new))
+	merge_optional_filters(left.map(|x| *x), right.map(|x| *x))
+		.map(|output| output.map(Box::new))
+		.map_warnings(|warning| warning.nested("[]"))
 }
 
 fn group(input: &str) -> IResult<&str, Warnings<Option<FieldFilter>>> {
@@ -164,9 +174,12 @@ fn merge_optional_filters(
 		// If both sides have an active filter, merge them and lift any warnings.
 		(Some(filter_left), Some(filter_right)) => filter_left.merge(filter_right),
 		// Otherwise, a None filter in a group should clear the group.
-		(other_left, other_right) => Warnings::new(None).with_warning(format!(
-			"filter `{}` ignored as another branch selected all values",
-			other_left.or(other_right).unwrap()
+		(other_left, other_right) => Warnings::new(None).with_warning(Warning::new(
+			WarningCode::FilterIgnored,
+			format!(
+				"filter `{}` ignored as another branch selected all values",
+				other_left.or(other_right).unwrap()
+			),
 		)),
 	}
 }
@@ -273,7 +286,8 @@ mod test {
 		assert_eq!(value, None);
 		assert_eq!(
 			warnings,
-			vec![String::from(
+			vec![Warning::new(
+				WarningCode::FilterMergeConflict,
 				"filters `{a}` and `[]` cannot be merged, and have been ignored"
 			)]
 		);
@@ -287,9 +301,11 @@ mod test {
 		assert_eq!(value, expected);
 		assert_eq!(
 			warnings,
-			vec![String::from(
+			vec![Warning::new(
+				WarningCode::FilterMergeConflict,
 				"filters `[]` and `{b}` cannot be merged, and have been ignored"
-			)]
+			)
+			.with_path("a")]
 		);
 	}
 
@@ -309,9 +325,11 @@ mod test {
 		assert_eq!(value, expected);
 		assert_eq!(
 			warnings,
-			vec![String::from(
+			vec![Warning::new(
+				WarningCode::FilterIgnored,
 				"filter `{b}` ignored as another branch selected all values"
-			)]
+			)
+			.with_path("a")]
 		);
 	}
 
@@ -347,4 +365,20 @@ mod test {
 		]))));
 		assert_eq!(out, expected);
 	}
+
+	// [].a.b,[].a.[] -> [{a}]
+	#[test]
+	fn merge_warning_path() {
+		let (value, warnings) = test_warning_parse("[].a.b,[].a.[]").decompose();
+		let expected = Some(array_filter(Some(struct_filter([("a", None)]))));
+		assert_eq!(value, expected);
+		assert_eq!(
+			warnings,
+			vec![Warning::new(
+				WarningCode::FilterMergeConflict,
+				"filters `{b}` and `[]` cannot be merged, and have been ignored"
+			)
+			.with_path("[].a")]
+		);
+	}
 }
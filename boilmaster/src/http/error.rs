@@ -1,7 +1,7 @@
 use axum::{http::StatusCode, response::IntoResponse, Json};
 use serde::Serialize;
 
-use crate::{schema, search};
+use crate::{data, schema, search};
 
 #[derive(Serialize)]
 pub struct ErrorResponse {
@@ -18,10 +18,23 @@ pub enum Error {
 	#[error("Invalid request: {0}")]
 	Invalid(String),
 
+	#[error("Service unavailable: {0}")]
+	Unavailable(String),
+
 	#[error("Internal server error.")]
 	Other(#[from] anyhow::Error),
 }
 
+impl From<data::Error> for Error {
+	fn from(error: data::Error) -> Self {
+		use data::Error as DE;
+		match error {
+			DE::UnknownVersion(_) => Self::Invalid(error.to_string()),
+			DE::Failure(inner) => Self::Other(inner),
+		}
+	}
+}
+
 impl From<schema::Error> for Error {
 	fn from(error: schema::Error) -> Self {
 		use schema::Error as SE;
@@ -74,6 +87,7 @@ impl IntoResponse for Error {
 		let status_code = match self {
 			Self::NotFound(_) => StatusCode::NOT_FOUND,
 			Self::Invalid(_) => StatusCode::BAD_REQUEST,
+			Self::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
 			Self::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
 		};
 
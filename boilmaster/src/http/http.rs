@@ -11,7 +11,7 @@ use tower_http::trace::TraceLayer;
 
 use crate::{data::Data, schema, search::Search};
 
-use super::{search, sheets};
+use super::{search, sheets, versions};
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
@@ -45,9 +45,11 @@ pub async fn serve(
 fn router(data: Arc<Data>, schema: Arc<schema::Provider>, search: Arc<Search>) -> Router {
 	Router::new()
 		.nest("/sheets", sheets::router())
-		.nest("/search", search::router(search))
+		.nest("/search", search::router())
+		.nest("/versions", versions::router())
 		// TODO: I'm not convinced by setting up the extensions this high, seems a bit magic so to speak
 		.layer(Extension(data))
 		.layer(Extension(schema))
+		.layer(Extension(search))
 		.layer(TraceLayer::new_for_http())
 }
@@ -0,0 +1,202 @@
+use std::sync::Arc;
+
+use axum::{extract::Query, response::IntoResponse, Extension};
+use axum_macros::debug_handler;
+use ironworks::file::exh;
+use ironworks_schema as is;
+use serde::Serialize;
+
+use crate::{
+	data::{Data, LanguageString},
+	schema,
+	utility::warnings::{Warning, WarningCode},
+};
+
+use super::{
+	error::Result,
+	path::Path,
+	query::{SchemaQuery, VersionQuery},
+	response::Response,
+};
+
+#[derive(Serialize)]
+struct SheetMetaResponse {
+	version: String,
+	kind: exh::SheetKind,
+	columns: Vec<ColumnMeta>,
+	pages: Vec<PageMeta>,
+	languages: Vec<LanguageString>,
+	row_count: u32,
+	/// The schema for the sheet, if the requested schema defines one.
+	schema: Option<SchemaMeta>,
+}
+
+#[derive(Serialize)]
+struct ColumnMeta {
+	kind: exh::ColumnKind,
+	offset: u16,
+}
+
+#[derive(Serialize)]
+struct PageMeta {
+	start_id: u32,
+	row_count: u32,
+}
+
+// ironworks_schema types don't implement Serialize, the types below mirror
+// them for use in responses.
+
+#[derive(Serialize)]
+struct SchemaMeta {
+	name: String,
+	order: &'static str,
+	node: NodeMeta,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum NodeMeta {
+	Array { count: u32, node: Box<NodeMeta> },
+	Reference { targets: Vec<ReferenceTargetMeta> },
+	Scalar,
+	Struct { fields: Vec<StructFieldMeta> },
+}
+
+#[derive(Serialize)]
+struct ReferenceTargetMeta {
+	sheet: String,
+	selector: Option<String>,
+	condition: Option<ReferenceConditionMeta>,
+}
+
+#[derive(Serialize)]
+struct ReferenceConditionMeta {
+	selector: String,
+	value: u32,
+}
+
+#[derive(Serialize)]
+struct StructFieldMeta {
+	offset: u32,
+	name: String,
+	node: NodeMeta,
+}
+
+impl From<is::Sheet> for SchemaMeta {
+	fn from(sheet: is::Sheet) -> Self {
+		Self {
+			name: sheet.name,
+			order: match sheet.order {
+				is::Order::Index => "index",
+				is::Order::Offset => "offset",
+			},
+			node: sheet.node.into(),
+		}
+	}
+}
+
+impl From<is::Node> for NodeMeta {
+	fn from(node: is::Node) -> Self {
+		match node {
+			is::Node::Array { count, node } => Self::Array {
+				count,
+				node: Box::new((*node).into()),
+			},
+
+			is::Node::Reference(targets) => Self::Reference {
+				targets: targets
+					.into_iter()
+					.map(|target| ReferenceTargetMeta {
+						sheet: target.sheet,
+						selector: target.selector,
+						condition: target.condition.map(|condition| ReferenceConditionMeta {
+							selector: condition.selector,
+							value: condition.value,
+						}),
+					})
+					.collect(),
+			},
+
+			is::Node::Scalar => Self::Scalar,
+
+			is::Node::Struct(fields) => Self::Struct {
+				fields: fields
+					.into_iter()
+					.map(|field| StructFieldMeta {
+						offset: field.offset,
+						name: field.name,
+						node: field.node.into(),
+					})
+					.collect(),
+			},
+		}
+	}
+}
+
+#[debug_handler]
+pub(super) async fn sheet_meta(
+	Path(sheet_name): Path<String>,
+	Query(schema_query): Query<SchemaQuery>,
+	Query(version_query): Query<VersionQuery>,
+	Extension(data): Extension<Arc<Data>>,
+	Extension(schema_provider): Extension<Arc<schema::Provider>>,
+) -> Result<impl IntoResponse> {
+	let data_version = data.version(version_query.version.as_deref())?;
+	let excel = data_version.excel();
+
+	let sheet = excel.sheet(&sheet_name)?;
+
+	let columns = sheet
+		.columns()?
+		.iter()
+		.map(|column| ColumnMeta {
+			kind: column.kind(),
+			offset: column.offset(),
+		})
+		.collect();
+
+	let pages = sheet
+		.pages()?
+		.iter()
+		.map(|page| PageMeta {
+			start_id: page.start_id(),
+			row_count: page.row_count(),
+		})
+		.collect();
+
+	let languages = sheet
+		.languages()?
+		.into_iter()
+		.map(LanguageString::from)
+		.collect();
+
+	// Header metadata is still useful without a schema, so a missing sheet
+	// definition is only a warning.
+	let schema = schema_provider.schema(schema_query.schema.as_ref())?;
+	let mut warnings = vec![];
+	let sheet_schema = match schema.sheet(&sheet_name) {
+		Ok(sheet_schema) => Some(sheet_schema.into()),
+		Err(is::Error::NotFound(_)) => {
+			warnings.push(
+				Warning::new(
+					WarningCode::SchemaMissing,
+					format!("schema does not define sheet {sheet_name:?}"),
+				)
+				.with_sheet(&sheet_name),
+			);
+			None
+		}
+		Err(error) => return Err(error.into()),
+	};
+
+	Ok(Response::new(SheetMetaResponse {
+		version: data_version.key().to_string(),
+		kind: sheet.kind()?,
+		columns,
+		pages,
+		languages,
+		row_count: sheet.row_count()?,
+		schema: sheet_schema,
+	})
+	.with_warnings(warnings))
+}
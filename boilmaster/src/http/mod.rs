@@ -1,7 +1,12 @@
 mod error;
 mod http;
+mod meta;
 mod path;
+mod query;
+mod response;
+mod rows;
 mod search;
 mod sheets;
+mod versions;
 
 pub use http::{serve, Config};
@@ -0,0 +1,28 @@
+use serde::Deserialize;
+
+use crate::{field_filter::FieldFilter, schema, utility::warnings::Warnings};
+
+/// Query parameter selecting the game version to read data from. Omitting the
+/// parameter is equivalent to requesting the latest version.
+#[derive(Deserialize)]
+pub struct VersionQuery {
+	pub version: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct FieldFilterQuery {
+	// this is a bit jank with the double option, improve? is it even possible to improve?
+	pub fields: Option<Warnings<Option<FieldFilter>>>,
+}
+
+/// Query parameter controlling how many levels of references are expanded when
+/// reading rows.
+#[derive(Deserialize)]
+pub struct DepthQuery {
+	pub depth: Option<u8>,
+}
+
+#[derive(Deserialize)]
+pub struct SchemaQuery {
+	pub schema: Option<schema::Specifier>,
+}
@@ -0,0 +1,65 @@
+use axum::{response::IntoResponse, Json};
+use serde::Serialize;
+
+use crate::utility::warnings::Warning;
+
+/// Envelope for successful responses. The body is flattened into the top level
+/// of the response, alongside any warnings raised while handling the request.
+#[derive(Serialize)]
+pub struct Response<T> {
+	#[serde(flatten)]
+	body: T,
+	warnings: Vec<Warning>,
+}
+
+impl<T> Response<T> {
+	pub fn new(body: T) -> Self {
+		Self {
+			body,
+			warnings: vec![],
+		}
+	}
+
+	#[must_use]
+	pub fn with_warnings(mut self, warnings: impl IntoIterator<Item = Warning>) -> Self {
+		self.warnings.extend(warnings);
+		self
+	}
+}
+
+impl<T: Serialize> IntoResponse for Response<T> {
+	fn into_response(self) -> axum::response::Response {
+		Json(self).into_response()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use serde_json::json;
+
+	use crate::utility::warnings::WarningCode;
+
+	use super::*;
+
+	#[derive(Serialize)]
+	struct Body {
+		value: u32,
+	}
+
+	#[test]
+	fn flattens_body_alongside_warnings() {
+		let response = Response::new(Body { value: 1 }).with_warnings([Warning::new(
+			WarningCode::FilterIgnored,
+			"message",
+		)
+		.with_path("a.b")]);
+
+		assert_eq!(
+			serde_json::to_value(response).unwrap(),
+			json!({
+				"value": 1,
+				"warnings": [{"code": "filter_ignored", "message": "message", "path": "a.b"}],
+			})
+		);
+	}
+}
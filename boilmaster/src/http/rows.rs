@@ -0,0 +1,156 @@
+use std::{fmt, ops::RangeInclusive, str::FromStr};
+
+use serde::{de, Deserialize};
+
+/// Position of a (sub)row within a sheet, formatted as `row` or `row:subrow`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RowSpecifier {
+	pub row_id: u32,
+	pub subrow_id: u16,
+}
+
+impl FromStr for RowSpecifier {
+	type Err = String;
+
+	fn from_str(string: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid row specifier \"{string}\"");
+
+		let (row_id, subrow_id) = match string.split_once(':') {
+			Some((row_id, subrow_id)) => (row_id, Some(subrow_id)),
+			None => (string, None),
+		};
+
+		Ok(Self {
+			row_id: row_id.parse().map_err(|_| invalid())?,
+			subrow_id: subrow_id
+				.map(str::parse)
+				.transpose()
+				.map_err(|_| invalid())?
+				.unwrap_or(0),
+		})
+	}
+}
+
+impl fmt::Display for RowSpecifier {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{}:{}", self.row_id, self.subrow_id)
+	}
+}
+
+impl<'de> Deserialize<'de> for RowSpecifier {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		let raw = String::deserialize(deserializer)?;
+		raw.parse().map_err(de::Error::custom)
+	}
+}
+
+/// Selection of row IDs, formatted as a comma-separated list of IDs and
+/// inclusive ranges, i.e. `1,2,5-10`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RowSelection {
+	// Sorted and non-overlapping.
+	ranges: Vec<RangeInclusive<u32>>,
+}
+
+impl RowSelection {
+	/// Total number of row IDs selected.
+	pub fn count(&self) -> u64 {
+		self.ranges
+			.iter()
+			.map(|range| u64::from(range.end() - range.start()) + 1)
+			.sum()
+	}
+
+	/// Iterate over the selected row IDs, in ascending order.
+	pub fn row_ids(&self) -> impl Iterator<Item = u32> + '_ {
+		self.ranges.iter().flat_map(|range| range.clone())
+	}
+}
+
+impl FromStr for RowSelection {
+	type Err = String;
+
+	fn from_str(string: &str) -> Result<Self, Self::Err> {
+		let mut ranges = string
+			.split(',')
+			.map(|entry| {
+				let invalid = || format!("invalid row selection \"{entry}\"");
+				let parse = |id: &str| id.trim().parse::<u32>().map_err(|_| invalid());
+				match entry.split_once('-') {
+					Some((start, end)) => {
+						let (start, end) = (parse(start)?, parse(end)?);
+						match start <= end {
+							true => Ok(start..=end),
+							false => Err(invalid()),
+						}
+					}
+					None => parse(entry).map(|id| id..=id),
+				}
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		// Merge overlapping and adjacent ranges so each ID is only selected once.
+		ranges.sort_by_key(|range| *range.start());
+		let mut merged: Vec<RangeInclusive<u32>> = vec![];
+		for range in ranges {
+			match merged.last_mut() {
+				Some(last) if u64::from(*range.start()) <= u64::from(*last.end()) + 1 => {
+					*last = *last.start()..=*last.end().max(range.end());
+				}
+				_ => merged.push(range),
+			}
+		}
+
+		Ok(Self { ranges: merged })
+	}
+}
+
+impl<'de> Deserialize<'de> for RowSelection {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		let raw = String::deserialize(deserializer)?;
+		raw.parse().map_err(de::Error::custom)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn parse_selection() {
+		let selection = "10-12,1,2,11-14,3".parse::<RowSelection>().unwrap();
+		assert_eq!(
+			selection,
+			RowSelection {
+				ranges: vec![1..=3, 10..=14]
+			}
+		);
+		assert_eq!(selection.count(), 8);
+		assert!("5-1".parse::<RowSelection>().is_err());
+		assert!("1,,2".parse::<RowSelection>().is_err());
+	}
+
+	#[test]
+	fn parse_specifier() {
+		assert_eq!(
+			"12:3".parse::<RowSpecifier>().unwrap(),
+			RowSpecifier {
+				row_id: 12,
+				subrow_id: 3
+			}
+		);
+		assert_eq!(
+			"12".parse::<RowSpecifier>().unwrap(),
+			RowSpecifier {
+				row_id: 12,
+				subrow_id: 0
+			}
+		);
+	}
+}
@@ -1,80 +1,293 @@
 use std::{collections::HashSet, sync::Arc};
 
 use anyhow::Context;
-use axum::{extract::Query, response::IntoResponse, routing::get, Extension, Json, Router};
+use axum::{extract::Query, response::IntoResponse, routing::get, Extension, Router};
 use axum_macros::debug_handler;
+use ironworks::excel::Language;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-	data::Data,
-	schema,
+	data::{Data, LanguageString},
+	field_filter::FieldFilter,
+	read, schema,
 	search::{query, Search},
+	utility::warnings::{Warning, WarningCode, Warnings},
 };
 
-use super::error::Result;
+use super::{
+	error::{Error, Result},
+	query::{DepthQuery, SchemaQuery, VersionQuery},
+	response::Response,
+	sheets::{read_limits, read_row},
+};
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 500;
 
-pub fn router(search_service: Arc<Search>) -> Router {
-	Router::new()
-		.route("/", get(search))
-		.layer(Extension(search_service))
+// Every index is asked for `offset + limit` results, so paging needs to stop somewhere.
+const MAX_OFFSET: usize = 10_000;
+
+pub fn router() -> Router {
+	Router::new().route("/", get(search))
 }
 
 #[derive(Debug, Deserialize)]
 struct SearchQuery {
+	query: Option<String>,
 	sheets: Option<String>,
-	query: query::pre::Node,
+	language: Option<LanguageString>,
+	// Kept as a string, rather than parsed, so it can be stored in cursors.
+	fields: Option<String>,
+	limit: Option<usize>,
+	/// Continuation cursor from a previous response. When provided, the
+	/// remaining search parameters are ignored in favour of those in the cursor.
+	cursor: Option<String>,
 }
 
-// TODO: reuse this with sheets
-#[derive(Deserialize)]
-struct SchemaQuery {
+// The full set of parameters required to reproduce a page of search results.
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+	version: String,
+	query: String,
+	sheets: Option<String>,
+	language: LanguageString,
+	fields: Option<String>,
+	depth: Option<u8>,
 	schema: Option<schema::Specifier>,
+	offset: usize,
+	limit: usize,
+}
+
+impl Cursor {
+	fn encode(&self) -> Result<String> {
+		let json = serde_json::to_vec(self).context("failed to encode search cursor")?;
+		Ok(base64::encode_config(json, base64::URL_SAFE_NO_PAD))
+	}
+
+	fn decode(encoded: &str) -> Result<Self> {
+		let invalid = || Error::Invalid("Malformed search cursor.".into());
+		let json =
+			base64::decode_config(encoded, base64::URL_SAFE_NO_PAD).map_err(|_| invalid())?;
+		serde_json::from_slice(&json).map_err(|_| invalid())
+	}
+
+	// Cursors are provided by the client, and can't be trusted to contain the
+	// values they were encoded with.
+	fn validate(&self) -> Result<()> {
+		if !(1..=MAX_LIMIT).contains(&self.limit) {
+			return Err(Error::Invalid(format!(
+				"Limit must be between 1 and {MAX_LIMIT}."
+			)));
+		}
+
+		if self.offset > MAX_OFFSET {
+			return Err(Error::Invalid(format!(
+				"Results beyond the first {MAX_OFFSET} are not available."
+			)));
+		}
+
+		Ok(())
+	}
+}
+
+#[derive(Debug, Serialize)]
+struct SearchResponse {
+	version: String,
+	results: Vec<SearchResult>,
+	/// Cursor for the next page of results, if there are any.
+	next: Option<String>,
 }
 
-// TODO: flesh this out - at the moment it's just a 1:1 of searchresult, pending ideas on how to field filter for search results across multiple indices
 #[derive(Debug, Serialize)]
 struct SearchResult {
 	score: f32,
 	sheet: String,
 	row_id: u32,
 	subrow_id: u16,
+	/// Row data for the result. `None` if the row could not be read with the
+	/// requested filter, in which case a warning is raised.
+	fields: Option<read::Value>,
 }
 
 #[debug_handler]
 async fn search(
 	Query(search_query): Query<SearchQuery>,
+	Query(depth_query): Query<DepthQuery>,
 	Query(schema_query): Query<SchemaQuery>,
+	Query(version_query): Query<VersionQuery>,
 	Extension(data): Extension<Arc<Data>>,
 	Extension(schema_provider): Extension<Arc<schema::Provider>>,
 	Extension(search): Extension<Arc<Search>>,
 ) -> Result<impl IntoResponse> {
-	// TODO: this should expose a more useful error to the end user.
-	let search_version = search.version(None).context("search index not ready")?;
-	let excel = data.version(None).excel();
+	let cursor = match search_query.cursor {
+		Some(encoded) => Cursor::decode(&encoded)?,
+		None => {
+			// Resolve the version immediately, so that aliases such as `latest`
+			// continue to point to the same data on subsequent pages.
+			let version = data.version(version_query.version.as_deref())?;
+
+			Cursor {
+				version: version.key().to_string(),
+				query: search_query
+					.query
+					.ok_or_else(|| Error::Invalid("A query or cursor is required.".into()))?,
+				sheets: search_query.sheets,
+				fields: search_query.fields,
+				depth: depth_query.depth,
+				// TODO: the default language should probably be configurable
+				language: search_query
+					.language
+					.unwrap_or_else(|| Language::English.into()),
+				schema: schema_query.schema,
+				offset: 0,
+				limit: search_query.limit.unwrap_or(DEFAULT_LIMIT),
+			}
+		}
+	};
+
+	cursor.validate()?;
+
+	let data_version = data.version(Some(&cursor.version))?;
+	let search_version = search.version(data_version.key()).ok_or_else(|| {
+		Error::Unavailable(format!(
+			"search index for version {} has not been ingested",
+			data_version.key()
+		))
+	})?;
+	// Result rows, and any references they contain, are read in the cursor's language.
+	let excel = data_version.excel_language(cursor.language.into());
+
+	let query = cursor.query.parse::<query::pre::Node>()?;
 
 	// TODO: I imagine comma-seperated stuff might be relatively common; make a deser helper (probs can trait it up so any fromiter<string> can deser using this pattern)
-	let sheets = search_query.sheets.map(|encoded| {
+	let sheets = cursor.sheets.as_ref().map(|encoded| {
 		encoded
 			.split(',')
 			.map(|x| x.to_owned())
 			.collect::<HashSet<_>>()
 	});
 
-	let schema = schema_provider.schema(schema_query.schema.as_ref())?;
+	let schema = schema_provider.schema(cursor.schema.as_ref())?;
+	let limits = read_limits(cursor.depth)?;
+
+	let (field_filter, filter_warnings) = match &cursor.fields {
+		Some(fields) => FieldFilter::parse(fields).map_err(Error::Invalid)?,
+		None => Warnings::new(None),
+	}
+	.decompose();
 
-	let (results, warnings) = search_version
-		.search(&search_query.query, sheets, &excel, schema.as_ref())?
+	let (page, mut warnings) = search_version
+		.search(
+			&query,
+			sheets.as_ref(),
+			cursor.language.into(),
+			&excel,
+			schema.as_ref(),
+			cursor.offset,
+			cursor.limit,
+		)?
 		.decompose();
 
-	let http_results = results
+	let next_offset = cursor.offset + cursor.limit;
+	let next = match page.more && next_offset <= MAX_OFFSET {
+		true => Some(
+			Cursor {
+				offset: next_offset,
+				..cursor
+			}
+			.encode()?,
+		),
+		false => None,
+	};
+
+	warnings.extend(filter_warnings);
+
+	// Read the row data for each result. The same filter is used across every
+	// sheet, so fields it selects may not exist on all of them - failures are
+	// raised as warnings rather than failing the entire search.
+	let http_results = page
+		.results
 		.into_iter()
-		.map(|result| SearchResult {
-			score: result.score,
-			sheet: result.sheet,
-			row_id: result.row_id,
-			subrow_id: result.subrow_id,
+		.map(|result| {
+			let sheet = excel.sheet(&result.sheet)?;
+			let row = sheet.subrow(result.row_id, result.subrow_id)?;
+			let columns = sheet.columns()?;
+
+			let fields = match read_row(
+				&result.sheet,
+				&excel,
+				schema.as_ref(),
+				&row,
+				field_filter.as_ref(),
+				&columns,
+				&limits,
+			) {
+				Ok(fields) => Some(fields),
+				Err(error) => {
+					warnings.push(
+						Warning::new(WarningCode::ReadFailed, format!("{error:#}"))
+							.with_sheet(&result.sheet),
+					);
+					None
+				}
+			};
+
+			Ok(SearchResult {
+				score: result.score,
+				sheet: result.sheet,
+				row_id: result.row_id,
+				subrow_id: result.subrow_id,
+				fields,
+			})
 		})
-		.collect::<Vec<_>>();
+		.collect::<Result<Vec<_>>>()?;
+
+	Ok(Response::new(SearchResponse {
+		version: data_version.key().to_string(),
+		results: http_results,
+		next,
+	})
+	.with_warnings(warnings))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn cursor(offset: usize, limit: usize) -> Cursor {
+		Cursor {
+			version: "version".into(),
+			query: "query".into(),
+			sheets: None,
+			language: Language::English.into(),
+			fields: None,
+			depth: None,
+			schema: None,
+			offset,
+			limit,
+		}
+	}
+
+	#[test]
+	fn cursor_round_trip() {
+		let encoded = cursor(100, 50).encode().unwrap();
+		let decoded = Cursor::decode(&encoded).unwrap();
+		assert_eq!(decoded.offset, 100);
+		assert_eq!(decoded.limit, 50);
+		assert!(decoded.validate().is_ok());
+	}
 
-	Ok(Json((http_results, warnings)))
+	#[test]
+	fn cursor_rejects_out_of_bounds() {
+		for (offset, limit) in [
+			(0, 0),
+			(0, MAX_LIMIT + 1),
+			(MAX_OFFSET + 1, 1),
+			(usize::MAX, usize::MAX),
+		] {
+			let encoded = cursor(offset, limit).encode().unwrap();
+			let decoded = Cursor::decode(&encoded).unwrap();
+			assert!(decoded.validate().is_err(), "{offset}, {limit} accepted");
+		}
+	}
 }
@@ -1,21 +1,37 @@
 use std::sync::Arc;
 
-use axum::{extract::Query, response::IntoResponse, routing::get, Extension, Json, Router};
+use axum::{extract::Query, response::IntoResponse, routing::get, Extension, Router};
 use axum_macros::debug_handler;
 use ironworks::{
-	excel::{Excel, Row},
+	excel::{Excel, Row, Sheet},
 	file::exh,
 };
 use ironworks_schema::Schema;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{data::Data, field_filter::FieldFilter, read, schema, utility::warnings::Warnings};
 
 use super::{
 	error::{Anyhow, Error, Result},
+	meta::sheet_meta,
 	path::Path,
+	query::{DepthQuery, FieldFilterQuery, SchemaQuery, VersionQuery},
+	response::Response,
+	rows::{RowSelection, RowSpecifier},
 };
 
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+// Selected IDs are fetched individually, so the size of a selection needs to be bounded.
+const MAX_SELECTION: u64 = 100_000;
+
+const DEFAULT_DEPTH: u8 = 1;
+const MAX_DEPTH: u8 = 5;
+
+// Maximum number of referenced rows that will be read for a single request.
+const READ_BUDGET: usize = 10_000;
+
 pub fn router() -> Router {
 	let row_router = Router::new()
 		.route("/", get(row))
@@ -23,43 +39,201 @@ pub fn router() -> Router {
 
 	Router::new()
 		.route("/", get(sheets))
+		.route("/:sheet_name", get(sheet))
+		.route("/:sheet_name/meta", get(sheet_meta))
 		.nest("/:sheet_name/:row_id", row_router)
 }
 
+#[derive(Serialize)]
+struct SheetsResponse {
+	version: String,
+	sheets: Vec<String>,
+}
+
 #[debug_handler]
-async fn sheets(Extension(data): Extension<Arc<Data>>) -> Result<impl IntoResponse> {
-	let excel = data.version(None).excel();
+async fn sheets(
+	Query(version_query): Query<VersionQuery>,
+	Extension(data): Extension<Arc<Data>>,
+) -> Result<impl IntoResponse> {
+	let data_version = data.version(version_query.version.as_deref())?;
+	let excel = data_version.excel();
 
 	let list = excel.list().anyhow()?;
 
 	// This contains quite a lot of quest/ and custom/ - should I filter them out? Or support them better?
 	let names = list.iter().map(|x| x.into_owned()).collect::<Vec<_>>();
 
-	Ok(Json(names))
+	Ok(Response::new(SheetsResponse {
+		version: data_version.key().to_string(),
+		sheets: names,
+	}))
 }
 
-// TODO: this probably should be generally accessible across all sheet endpoints? and search?
 #[derive(Deserialize)]
-struct FieldFilterQuery {
-	// this is a bit jank with the double option, improve? is it even possible to improve?
-	fields: Option<Warnings<Option<FieldFilter>>>,
+struct SheetQuery {
+	/// Only return rows after the specified row.
+	after: Option<RowSpecifier>,
+	limit: Option<usize>,
+	/// Only return the selected rows.
+	rows: Option<RowSelection>,
 }
 
-// TODO: likewise with field filter, should be reuseable
-#[derive(Deserialize)]
-struct SchemaQuery {
-	schema: Option<schema::Specifier>,
+#[derive(Serialize)]
+struct SheetResponse {
+	version: String,
+	rows: Vec<SheetRow>,
+	/// Value for `after=` to fetch the next page of rows, if there are any.
+	next: Option<String>,
+}
+
+// Subrows are flattened into the list of rows, identified by their subrow ID.
+#[derive(Serialize)]
+struct SheetRow {
+	row_id: u32,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	subrow_id: Option<u16>,
+	fields: read::Value,
+}
+
+#[debug_handler]
+#[allow(clippy::too_many_arguments)]
+async fn sheet(
+	Path(sheet_name): Path<String>,
+	Query(sheet_query): Query<SheetQuery>,
+	Query(field_filter_query): Query<FieldFilterQuery>,
+	Query(depth_query): Query<DepthQuery>,
+	Query(schema_query): Query<SchemaQuery>,
+	Query(version_query): Query<VersionQuery>,
+	Extension(data): Extension<Arc<Data>>,
+	Extension(schema_provider): Extension<Arc<schema::Provider>>,
+) -> Result<impl IntoResponse> {
+	let data_version = data.version(version_query.version.as_deref())?;
+	let excel = data_version.excel();
+
+	let limit = sheet_query.limit.unwrap_or(DEFAULT_LIMIT);
+	if !(1..=MAX_LIMIT).contains(&limit) {
+		return Err(Error::Invalid(format!(
+			"Limit must be between 1 and {MAX_LIMIT}."
+		)));
+	}
+
+	let sheet = excel.sheet(&sheet_name)?;
+	let has_subrows = sheet.kind()? == exh::SheetKind::Subrows;
+
+	let schema = schema_provider.schema(schema_query.schema.as_ref())?;
+	let limits = read_limits(depth_query.depth)?;
+	let columns = sheet.columns()?;
+
+	let (field_filter, warnings) = field_filter_query
+		.fields
+		.unwrap_or_else(|| Warnings::new(None))
+		.decompose();
+
+	let sheet = &sheet;
+	let rows: Box<dyn Iterator<Item = Result<Row>> + '_> = match &sheet_query.rows {
+		Some(selection) => {
+			if selection.count() > MAX_SELECTION {
+				return Err(Error::Invalid(format!(
+					"Row selections may contain at most {MAX_SELECTION} IDs."
+				)));
+			}
+
+			// IDs that do not exist in the sheet are skipped.
+			Box::new(
+				selection
+					.row_ids()
+					.flat_map(move |row_id| subrows(sheet, row_id)),
+			)
+		}
+		None => {
+			// Start reading from the page containing the `after` row, rather than
+			// walking every row before it.
+			let start_id = sheet_query.after.map_or(0, |after| after.row_id);
+			Box::new(
+				sheet
+					.pages()?
+					.into_iter()
+					.filter(move |page| page.start_id() + page.row_count() > start_id)
+					.flat_map(move |page| {
+						let end_id = page.start_id() + page.row_count();
+						page.start_id().max(start_id)..end_id
+					})
+					.flat_map(move |row_id| subrows(sheet, row_id)),
+			)
+		}
+	};
+
+	let mut sheet_rows = vec![];
+	let mut more = false;
+	for row in rows {
+		let row = row?;
+		let specifier = RowSpecifier {
+			row_id: *row.row_id(),
+			subrow_id: *row.subrow_id(),
+		};
+		if sheet_query.after.is_some_and(|after| specifier <= after) {
+			continue;
+		}
+
+		if sheet_rows.len() == limit {
+			more = true;
+			break;
+		}
+
+		let fields = read_row(
+			&sheet_name,
+			&excel,
+			schema.as_ref(),
+			&row,
+			field_filter.as_ref(),
+			&columns,
+			&limits,
+		)?;
+
+		sheet_rows.push(SheetRow {
+			row_id: specifier.row_id,
+			subrow_id: has_subrows.then_some(specifier.subrow_id),
+			fields,
+		});
+	}
+
+	let next = match more {
+		true => sheet_rows.last().map(|row| {
+			RowSpecifier {
+				row_id: row.row_id,
+				subrow_id: row.subrow_id.unwrap_or(0),
+			}
+			.to_string()
+		}),
+		false => None,
+	};
+
+	Ok(Response::new(SheetResponse {
+		version: data_version.key().to_string(),
+		rows: sheet_rows,
+		next,
+	})
+	.with_warnings(warnings))
+}
+
+#[derive(Serialize)]
+struct RowResponse {
+	version: String,
+	fields: read::Value,
 }
 
 #[debug_handler]
 async fn row(
 	Path((sheet_name, row_id)): Path<(String, u32)>,
 	Query(field_filter_query): Query<FieldFilterQuery>,
+	Query(depth_query): Query<DepthQuery>,
 	Query(schema_query): Query<SchemaQuery>,
+	Query(version_query): Query<VersionQuery>,
 	Extension(data): Extension<Arc<Data>>,
 	Extension(schema_provider): Extension<Arc<schema::Provider>>,
 ) -> Result<impl IntoResponse> {
-	let excel = data.version(None).excel();
+	let data_version = data.version(version_query.version.as_deref())?;
+	let excel = data_version.excel();
 
 	let sheet = excel.sheet(&sheet_name)?;
 	if sheet.kind()? == exh::SheetKind::Subrows {
@@ -69,6 +243,7 @@ async fn row(
 	}
 
 	let schema = schema_provider.schema(schema_query.schema.as_ref())?;
+	let limits = read_limits(depth_query.depth)?;
 
 	let row = sheet.row(row_id)?;
 	let columns = sheet.columns()?;
@@ -77,9 +252,6 @@ async fn row(
 		.fields
 		.unwrap_or_else(|| Warnings::new(None))
 		.decompose();
-	if !warnings.is_empty() {
-		todo!("handle warnings in http layer");
-	}
 
 	let result = read_row(
 		&sheet_name,
@@ -88,20 +260,28 @@ async fn row(
 		&row,
 		field_filter.as_ref(),
 		&columns,
+		&limits,
 	)?;
 
-	Ok(Json(result))
+	Ok(Response::new(RowResponse {
+		version: data_version.key().to_string(),
+		fields: result,
+	})
+	.with_warnings(warnings))
 }
 
 #[debug_handler]
 async fn subrow(
 	Path((sheet_name, row_id, subrow_id)): Path<(String, u32, u16)>,
 	Query(field_filter_query): Query<FieldFilterQuery>,
+	Query(depth_query): Query<DepthQuery>,
 	Query(schema_query): Query<SchemaQuery>,
+	Query(version_query): Query<VersionQuery>,
 	Extension(data): Extension<Arc<Data>>,
 	Extension(schema_provider): Extension<Arc<schema::Provider>>,
 ) -> Result<impl IntoResponse> {
-	let excel = data.version(None).excel();
+	let data_version = data.version(version_query.version.as_deref())?;
+	let excel = data_version.excel();
 
 	let sheet = excel.sheet(&sheet_name)?;
 	if sheet.kind()? != exh::SheetKind::Subrows {
@@ -111,6 +291,7 @@ async fn subrow(
 	}
 
 	let schema = schema_provider.schema(schema_query.schema.as_ref())?;
+	let limits = read_limits(depth_query.depth)?;
 
 	let row = sheet.subrow(row_id, subrow_id)?;
 	let columns = sheet.columns()?;
@@ -119,9 +300,6 @@ async fn subrow(
 		.fields
 		.unwrap_or_else(|| Warnings::new(None))
 		.decompose();
-	if !warnings.is_empty() {
-		todo!("handle warnings in http layer");
-	}
 
 	let result = read_row(
 		&sheet_name,
@@ -130,19 +308,53 @@ async fn subrow(
 		&row,
 		field_filter.as_ref(),
 		&columns,
+		&limits,
 	)?;
 
-	Ok(Json(result))
+	Ok(Response::new(RowResponse {
+		version: data_version.key().to_string(),
+		fields: result,
+	})
+	.with_warnings(warnings))
+}
+
+// Read every subrow of a row, until one is not found. Rows that do not exist
+// in the sheet yield no subrows.
+fn subrows<'a>(sheet: &'a Sheet<&String>, row_id: u32) -> impl Iterator<Item = Result<Row>> + 'a {
+	(0..=u16::MAX)
+		.map(move |subrow_id| sheet.subrow(row_id, subrow_id))
+		.take_while(|row| {
+			!matches!(
+				row,
+				Err(ironworks::Error::NotFound(
+					ironworks::ErrorValue::Row { .. }
+				))
+			)
+		})
+		.map(|row| row.map_err(Error::from))
+}
+
+/// Build the reference expansion limits for a request.
+pub(super) fn read_limits(depth: Option<u8>) -> Result<read::Limits> {
+	let depth = depth.unwrap_or(DEFAULT_DEPTH);
+	if depth > MAX_DEPTH {
+		return Err(Error::Invalid(format!(
+			"Depth must be at most {MAX_DEPTH}."
+		)));
+	}
+
+	Ok(read::Limits::new(depth, READ_BUDGET))
 }
 
-fn read_row(
+pub(super) fn read_row(
 	sheet_name: &str,
 	excel: &Excel,
 	schema: &dyn Schema,
 	row: &Row,
 	filter: Option<&FieldFilter>,
 	columns: &[exh::ColumnDefinition],
-) -> Result<read::Value> {
+	limits: &read::Limits,
+) -> anyhow::Result<read::Value> {
 	let value = read::read_sheet(
 		sheet_name,
 		read::ReaderContext {
@@ -150,7 +362,9 @@ fn read_row(
 			schema,
 			filter,
 			row,
-			limit: 1,
+			depth: limits.depth,
+			budget: &limits.budget,
+			ancestors: &[],
 			columns,
 		},
 	)?;
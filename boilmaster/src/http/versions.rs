@@ -0,0 +1,84 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use axum::{response::IntoResponse, routing::get, Extension, Router};
+use axum_macros::debug_handler;
+use serde::Serialize;
+
+use crate::{data::Data, search::Search};
+
+use super::{error::Result, response::Response};
+
+pub fn router() -> Router {
+	Router::new().route("/", get(versions))
+}
+
+#[derive(Serialize)]
+struct VersionsResponse {
+	latest: Option<String>,
+	versions: Vec<VersionInfo>,
+}
+
+#[derive(Serialize)]
+struct VersionInfo {
+	key: String,
+	// Keyed by repository name, i.e. `ffxiv`, `ex1`.
+	patches: BTreeMap<String, String>,
+	// Failing to read the excel version of one game version shouldn't prevent
+	// listing the rest.
+	excel_version: Option<String>,
+	search_ready: bool,
+}
+
+#[debug_handler]
+async fn versions(
+	Extension(data): Extension<Arc<Data>>,
+	Extension(search): Extension<Arc<Search>>,
+) -> Result<impl IntoResponse> {
+	// Versions are read without being cached, so listing doesn't build and
+	// retain every version available.
+	let versions = data
+		.versions()
+		.into_iter()
+		.map(|key| {
+			let version = data.version_uncached(&key);
+
+			let patches = version
+				.as_ref()
+				.map(|version| {
+					version
+						.patches()
+						.iter()
+						.map(|(id, patch)| (repository_name(*id), patch.clone()))
+						.collect()
+				})
+				.unwrap_or_default();
+
+			let excel_version = match version {
+				Ok(version) => version.excel().version().ok(),
+				Err(error) => {
+					tracing::warn!("failed to read version {key}: {error:?}");
+					None
+				}
+			};
+
+			VersionInfo {
+				excel_version,
+				search_ready: search.version(&key).is_some(),
+				patches,
+				key,
+			}
+		})
+		.collect::<Vec<_>>();
+
+	Ok(Response::new(VersionsResponse {
+		latest: data.latest(),
+		versions,
+	}))
+}
+
+fn repository_name(id: u8) -> String {
+	match id {
+		0 => "ffxiv".into(),
+		other => format!("ex{other}"),
+	}
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use boilmaster::{data::Data, http, schema, search, tracing};
+use boilmaster::{data, http, schema, search, tracing};
 use figment::{
 	providers::{Env, Format, Toml},
 	Figment,
@@ -12,6 +12,7 @@ use tokio_util::sync::CancellationToken;
 #[derive(Debug, Deserialize)]
 struct Config {
 	tracing: tracing::Config,
+	data: data::Config,
 	http: http::Config,
 	schema: schema::Config,
 	search: search::Config,
@@ -30,7 +31,7 @@ async fn main() {
 	// Initialise tracing before getting too far into bootstrapping the rest of the application
 	tracing::init(config.tracing);
 
-	let data = Arc::new(Data::new());
+	let data = Arc::new(data::Data::new(config.data).expect("TODO: Error handling"));
 	let schema = Arc::new(schema::Provider::new(config.schema).expect("TODO: Error handling"));
 	let search = Arc::new(search::Search::new(config.search));
 
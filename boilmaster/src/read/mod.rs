@@ -1,5 +1,5 @@
 mod read;
 mod value;
 
-pub use read::{read_sheet, ReaderContext};
+pub use read::{read_sheet, Limits, ReaderContext};
 pub use value::{Reference, Value};
@@ -1,4 +1,7 @@
-use std::collections::{btree_map::Entry, BTreeMap};
+use std::{
+	cell::Cell,
+	collections::{btree_map::Entry, BTreeMap},
+};
 
 use anyhow::{anyhow, Context, Result};
 use ironworks::{excel, file::exh};
@@ -6,7 +9,27 @@ use ironworks_schema as schema;
 
 use crate::{field_filter::FieldFilter, utility::field};
 
-use super::value::{Reference, Value};
+use super::value::{Reference, Truncation, Value};
+
+/// Bounds on reference expansion over the course of a request.
+#[derive(Debug)]
+pub struct Limits {
+	/// Depth to expand references to by default. References explicitly selected
+	/// into by a field filter are expanded regardless of depth.
+	pub depth: u8,
+
+	/// Remaining number of referenced rows that may be read.
+	pub budget: Cell<usize>,
+}
+
+impl Limits {
+	pub fn new(depth: u8, budget: usize) -> Self {
+		Self {
+			depth,
+			budget: Cell::new(budget),
+		}
+	}
+}
 
 #[derive(Clone)]
 pub struct ReaderContext<'a> {
@@ -15,7 +38,10 @@ pub struct ReaderContext<'a> {
 	pub filter: Option<&'a FieldFilter>,
 
 	pub row: &'a excel::Row,
-	pub limit: u8,
+	pub depth: u8,
+	pub budget: &'a Cell<usize>,
+	/// Rows that are currently being read further up the reference chain.
+	pub ancestors: &'a [(&'a str, u32)],
 
 	pub columns: &'a [exh::ColumnDefinition],
 }
@@ -27,7 +53,16 @@ pub fn read_sheet(sheet_name: &str, context: ReaderContext) -> Result<Value> {
 		todo!("sheet schema {:?} order", sheet.order);
 	}
 
-	read_node(&sheet.node, context)
+	let mut ancestors = context.ancestors.to_vec();
+	ancestors.push((sheet_name, *context.row.row_id()));
+
+	read_node(
+		&sheet.node,
+		ReaderContext {
+			ancestors: &ancestors,
+			..context
+		},
+	)
 }
 
 fn read_node(node: &schema::Node, context: ReaderContext) -> Result<Value> {
@@ -84,9 +119,7 @@ fn read_reference(targets: &[schema::ReferenceTarget], context: ReaderContext) -
 
 	// TODO: is neg case always gonna be like this?
 	// A target < 0 (typically -1) signifies that no link is active on this row.
-	// Also ensure that we've not run out of recursion space.
-	// TODO: should we limit check only just before we run the recursion so the reference data at least includes the target values?
-	if target_value < 0 || context.limit == 0 {
+	if target_value < 0 {
 		return Ok(Value::Reference(reference));
 	}
 	let target_value = u32::try_from(target_value).unwrap();
@@ -98,6 +131,12 @@ fn read_reference(targets: &[schema::ReferenceTarget], context: ReaderContext) -
 			break;
 		}
 
+		// Looking up the target reads a row, even if it isn't expanded.
+		if let Some(truncation) = charge_budget(context.budget) {
+			reference.truncated = Some(truncation);
+			break;
+		}
+
 		// Get the target sheet's data and schema. Intentionally fail hard, as any
 		// mismatch here can cause incorrect joins.
 		let sheet_data = context.excel.sheet(&target.sheet)?;
@@ -122,12 +161,25 @@ fn read_reference(targets: &[schema::ReferenceTarget], context: ReaderContext) -
 		}?;
 
 		reference.sheet = Some(target.sheet.clone());
+		reference.key = Some(target_value.to_string());
+
+		let truncation = check_expansion(
+			context.ancestors,
+			(&target.sheet, target_value),
+			context.depth,
+			context.filter.is_some(),
+		);
+		if truncation.is_some() {
+			reference.truncated = truncation;
+			break;
+		}
+
 		reference.data = Some(
 			read_sheet(
 				&target.sheet,
 				ReaderContext {
 					row: &row_data,
-					limit: context.limit - 1,
+					depth: context.depth.saturating_sub(1),
 					columns: &sheet_data.columns()?,
 					..context
 				},
@@ -140,6 +192,38 @@ fn read_reference(targets: &[schema::ReferenceTarget], context: ReaderContext) -
 	Ok(Value::Reference(reference))
 }
 
+// Decide if a reference to the target row may be expanded. A reference is left
+// unexpanded if reading it would loop back on itself or exceed the requested
+// depth. A filter selecting fields within the target counts as an explicit
+// request for expansion, regardless of depth.
+fn check_expansion(
+	ancestors: &[(&str, u32)],
+	target: (&str, u32),
+	depth: u8,
+	filtered: bool,
+) -> Option<Truncation> {
+	if ancestors.contains(&target) {
+		return Some(Truncation::Cycle);
+	}
+
+	if depth == 0 && !filtered {
+		return Some(Truncation::Depth);
+	}
+
+	None
+}
+
+// Consume budget for reading a referenced row, if any remains.
+fn charge_budget(budget: &Cell<usize>) -> Option<Truncation> {
+	match budget.get() {
+		0 => Some(Truncation::Budget),
+		remaining => {
+			budget.set(remaining - 1);
+			None
+		}
+	}
+}
+
 fn field_to_index(field: excel::Field) -> Result<i32> {
 	use excel::Field as F;
 	let result = match field {
@@ -227,3 +311,46 @@ fn read_struct(fields: &[schema::StructField], context: ReaderContext) -> Result
 
 	Ok(Value::Struct(map))
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn expansion_cycle() {
+		let ancestors = [("Item", 1), ("ItemUICategory", 2)];
+
+		assert_eq!(
+			check_expansion(&ancestors, ("Item", 1), 3, false),
+			Some(Truncation::Cycle)
+		);
+		// Cycles are not expanded even if explicitly filtered.
+		assert_eq!(
+			check_expansion(&ancestors, ("ItemUICategory", 2), 3, true),
+			Some(Truncation::Cycle)
+		);
+		// The same sheet with a different row is not a cycle.
+		assert_eq!(check_expansion(&ancestors, ("Item", 2), 3, false), None);
+	}
+
+	#[test]
+	fn expansion_depth() {
+		assert_eq!(
+			check_expansion(&[], ("Item", 1), 0, false),
+			Some(Truncation::Depth)
+		);
+		assert_eq!(check_expansion(&[], ("Item", 1), 1, false), None);
+		// A filter selecting into the reference expands it at depth 0.
+		assert_eq!(check_expansion(&[], ("Item", 1), 0, true), None);
+	}
+
+	#[test]
+	fn expansion_budget() {
+		let budget = Cell::new(1);
+
+		assert_eq!(charge_budget(&budget), None);
+		assert_eq!(budget.get(), 0);
+		assert_eq!(charge_budget(&budget), Some(Truncation::Budget));
+		assert_eq!(budget.get(), 0);
+	}
+}
@@ -33,17 +33,23 @@ fn serialize_scalar<S: Serializer>(field: &excel::Field, s: S) -> Result<S::Ok,
 	}
 }
 
-// TODO: finalise this
+/// A reference to a row in another sheet. `sheet` and `key` are populated
+/// whenever the target row exists, even if its data was not read.
 #[derive(Debug, Serialize)]
 pub struct Reference {
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub sheet: Option<String>,
 
+	/// Key of the target row within `sheet`.
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub key: Option<String>,
 
 	pub value: i32,
 
+	/// Reason the target row was not expanded into `data`, if it was not.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub truncated: Option<Truncation>,
+
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub data: Option<Box<Value>>,
 }
@@ -54,7 +60,19 @@ impl Reference {
 			sheet: None,
 			key: None,
 			value,
+			truncated: None,
 			data: None,
 		}
 	}
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Truncation {
+	/// The reference is beyond the requested depth.
+	Depth,
+	/// The target row is already being read further up the reference chain.
+	Cycle,
+	/// The request has read the maximum number of referenced rows.
+	Budget,
+}
@@ -1,6 +1,6 @@
-use std::{convert::Infallible, str::FromStr};
+use std::{convert::Infallible, fmt, str::FromStr};
 
-use serde::{de, Deserialize};
+use serde::{de, Deserialize, Serialize};
 
 #[derive(Debug)]
 pub struct Specifier {
@@ -27,6 +27,16 @@ impl FromStr for Specifier {
 	}
 }
 
+impl fmt::Display for Specifier {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		formatter.write_str(&self.source)?;
+		if let Some(version) = &self.version {
+			write!(formatter, "@{version}")?;
+		}
+		Ok(())
+	}
+}
+
 impl<'de> Deserialize<'de> for Specifier {
 	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
 	where
@@ -36,3 +46,12 @@ impl<'de> Deserialize<'de> for Specifier {
 		raw.parse().map_err(de::Error::custom)
 	}
 }
+
+impl Serialize for Specifier {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		serializer.collect_str(self)
+	}
+}
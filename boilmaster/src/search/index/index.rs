@@ -1,19 +1,21 @@
 use std::path::PathBuf;
 
 use ironworks::excel::Sheet;
-use tantivy::{collector::TopDocs, directory::MmapDirectory, ReloadPolicy};
+use tantivy::{collector::TopDocs, directory::MmapDirectory, DocAddress, ReloadPolicy};
 
 use crate::search::{error::SearchError, query::post::Node, version::Executor};
 
 use super::{
 	ingest::Ingester,
 	resolve::QueryResolver,
-	schema::{ROW_ID, SUBROW_ID},
+	schema::{register_tokenizers, ROW_ID, SUBROW_ID},
 };
 
 #[derive(Debug)]
 pub struct IndexResult {
 	pub score: f32,
+	/// Address of the result's document. Results with equal scores are ordered by address.
+	pub address: DocAddress,
 	pub row_id: u32,
 	pub subrow_id: u16,
 }
@@ -47,6 +49,8 @@ impl Index {
 				.expect("TODO: error handling for ingestion failures"),
 		};
 
+		register_tokenizers(&index);
+
 		let reader = index
 			.reader_builder()
 			// TODO: this is set to manual 'cus technically an index is never updated in this setup. is that sane? i dunno?
@@ -63,6 +67,7 @@ impl Index {
 		executor: &Executor,
 		// query_string: &str,
 		query_node: &Node,
+		limit: usize,
 	) -> Result<impl Iterator<Item = IndexResult>, SearchError> {
 		let searcher = self.reader.searcher();
 
@@ -98,9 +103,8 @@ impl Index {
 		// TODO: in tantivy 0.19 i can throw a const scorer on this to make it worth nothing or something? i imagine the actual strings are the important bits
 		// let query = BooleanQuery::new(b.collect());
 
-		// TODO: this results in each individuial index having a limit, as opposed to the whole query itself - think about how to approach this.
 		let top_docs = searcher
-			.search(&query, &TopDocs::with_limit(100))
+			.search(&query, &TopDocs::with_limit(limit))
 			.map_err(anyhow::Error::from)?;
 
 		let todo_result = top_docs.into_iter().map(move |(score, doc_address)| {
@@ -125,6 +129,7 @@ impl Index {
 
 			IndexResult {
 				score,
+				address: doc_address,
 				row_id,
 				subrow_id,
 			}
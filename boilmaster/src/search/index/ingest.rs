@@ -1,13 +1,16 @@
 use anyhow::Result;
 use ironworks::{
-	excel::{Field, Row, Sheet},
+	excel::{Field, Language, Row, Sheet},
 	file::exh,
 };
 use serde::Deserialize;
 use tantivy::{directory::MmapDirectory, schema, Document, Index, IndexSettings};
 use tokio::sync::Semaphore;
 
-use super::schema::{build_sheet_schema, column_field_name, ROW_ID, SUBROW_ID};
+use super::schema::{
+	build_sheet_schema, column_field_name, language_field_name, register_tokenizers, ROW_ID,
+	SUBROW_ID,
+};
 
 #[derive(Debug, Deserialize)]
 pub struct IngestConfig {
@@ -47,19 +50,49 @@ impl Ingester {
 		let index = tokio::task::spawn_blocking(move || -> Result<_> {
 			// TODO: seperate building the index from ingesting into it
 			let columns = sheet.columns()?;
+			let languages = sheet.languages()?;
 
 			let index = Index::create(
 				directory,
-				build_sheet_schema(&columns),
+				build_sheet_schema(&columns, &languages),
 				IndexSettings::default(),
 			)?;
+			register_tokenizers(&index);
 
 			let mut writer = index.writer(writer_memory)?;
 			let schema = index.schema();
 
+			// Rows are iterated in the first language, with the remaining languages
+			// read by ID. Pages are cached, so this does not re-read files per row.
+			let options = languages
+				.iter()
+				.map(|&language| {
+					let mut options = sheet.with();
+					options.language(language);
+					(language, options)
+				})
+				.collect::<Vec<_>>();
+			let (first_language, first_options) = options
+				.first()
+				.ok_or_else(|| anyhow::anyhow!("sheet has no languages"))?;
+
 			// TODO: if there's any failures at all (i.e. iw read errors) during ingestion, the writer should be rolled back to ensure a theoretical retry is able to work on a clean deck.
-			for row in sheet.iter() {
-				let document = build_row_document(row, &columns, &schema)?;
+			for row in first_options.iter() {
+				let (row_id, subrow_id) = (*row.row_id(), *row.subrow_id());
+				let mut rows = vec![(*first_language, row)];
+				for (language, options) in &options[1..] {
+					// Localised data may be missing rows present in the first language.
+					// Index what is available rather than failing the whole sheet.
+					match options.subrow(row_id, subrow_id) {
+						Ok(row) => rows.push((*language, row)),
+						Err(ironworks::Error::NotFound(_)) => {
+							tracing::debug!(?language, row_id, subrow_id, "localised row missing")
+						}
+						Err(error) => return Err(error.into()),
+					}
+				}
+
+				let document = build_row_document(&rows, &columns, &schema)?;
 				writer.add_document(document)?;
 			}
 
@@ -76,27 +109,43 @@ impl Ingester {
 	}
 }
 
+// Rows are expected to be the same row in each language the sheet supports.
 fn build_row_document(
-	row: Row,
+	rows: &[(Language, Row)],
 	columns: &[exh::ColumnDefinition],
 	schema: &schema::Schema,
 ) -> Result<Document> {
 	let mut document = Document::new();
 
+	// Non-string fields are shared across languages, so can be read from any row.
+	let row = &rows[0].1;
+
 	document.add_u64(schema.get_field(ROW_ID).unwrap(), (*row.row_id()).into());
 	document.add_u64(
 		schema.get_field(SUBROW_ID).unwrap(),
 		(*row.subrow_id()).into(),
 	);
 
+	use Field as F;
 	for column in columns {
+		if column.kind() == exh::ColumnKind::String {
+			for (language, row) in rows {
+				let field = schema
+					.get_field(&language_field_name(column, *language))
+					.unwrap();
+				if let F::String(value) = row.field(column)? {
+					document.add_text(field, value);
+				}
+			}
+			continue;
+		}
+
 		let field = schema.get_field(&column_field_name(column)).unwrap();
 		let value = row.field(column)?;
 		// TODO: this feels pretty repetetive given the column kind schema build - is it avoidable or nah?
-		use Field as F;
 		match value {
-			// TODO: need to make sure the ingested strings don't contain non-string payloads
-			F::String(value) => document.add_text(field, value),
+			// Strings are handled per-language above.
+			F::String(_) => {}
 
 			F::I8(value) => document.add_i64(field, value.into()),
 			F::I16(value) => document.add_i64(field, value.into()),
@@ -1,16 +1,25 @@
+use ironworks::file::exh;
+use std::ops::Bound;
+
 use tantivy::{
-	query::{BooleanQuery, PhraseQuery, Query, TermQuery, TermSetQuery},
+	query::{
+		BooleanQuery, Occur as TantivyOccur, PhraseQuery, Query, RangeQuery, RegexQuery, TermQuery,
+		TermSetQuery,
+	},
 	schema::{Field, IndexRecordOption, Schema, Type},
 	Index, TantivyError, Term,
 };
 
 use crate::search::{
 	error::{FieldTypeError, MismatchError, SearchError},
-	query::post::{Group, Leaf, Node, Operation, Relation, Value},
+	query::post::{Group, Leaf, Node, Operation, Range, Relation, Value},
 	version::Executor,
 };
 
-use super::schema::column_field_name;
+use super::schema::{column_field_name, resolve_language_field_name};
+
+// TODO: relations are resolved to a set of terms, so an upper bound is required - this will miss rows with a large number of relation matches.
+const RELATION_LIMIT: usize = 100;
 
 pub struct QueryResolver<'a> {
 	pub index: &'a Index,
@@ -47,7 +56,13 @@ impl QueryResolver<'_> {
 	}
 
 	fn resolve_leaf(&self, leaf: &Leaf) -> Result<Box<dyn Query>, SearchError> {
-		let field_name = column_field_name(&leaf.field);
+		// String columns are indexed per-language.
+		let field_name = match leaf.field.kind() {
+			exh::ColumnKind::String => {
+				resolve_language_field_name(self.schema, &leaf.field, self.executor.language)
+			}
+			_ => column_field_name(&leaf.field),
+		};
 		let field = self.schema.get_field(&field_name).ok_or_else(|| {
 			SearchError::SchemaMismatch(MismatchError {
 				// TODO: this will be pretty cryptic to end-users, try to resolve to the schema column name?
@@ -59,6 +74,8 @@ impl QueryResolver<'_> {
 		match &leaf.operation {
 			Operation::Relation(relation) => self.resolve_relation(relation, field),
 			Operation::Match(string) => self.resolve_match(string, field),
+			Operation::Prefix(string) => self.resolve_prefix(string, field),
+			Operation::Range(range) => self.resolve_range(range, field),
 			Operation::Equal(value) => {
 				if matches!(value, Value::String(_)) {
 					// TODO: term/phrase queries inherently don't handle exact equality. I might be able to handle strings as a ^regex$? will need to test.
@@ -77,9 +94,9 @@ impl QueryResolver<'_> {
 		field: Field,
 	) -> Result<Box<dyn Query>, SearchError> {
 		// Run the inner query on the target index.
-		let results = self
-			.executor
-			.search(&relation.target.sheet, &relation.query)?;
+		let results =
+			self.executor
+				.search(&relation.target.sheet, &relation.query, RELATION_LIMIT)?;
 
 		// Map the results to terms for the query we're building.
 		// TODO: I'm ignoring the subrow here - is that sane? AFAIK subrow relations act as a pivot table, many:many - I don't _think_ it references the subrow anywhere?
@@ -96,6 +113,74 @@ impl QueryResolver<'_> {
 	}
 
 	fn resolve_match(&self, string: &str, field: Field) -> Result<Box<dyn Query>, SearchError> {
+		let mut terms = self.tokenize(string, field)?;
+
+		// If there's only one term, use a term query, otherwise a phrase query
+		match terms.len() {
+			0 => Err(SearchError::MalformedQuery(format!(
+				"match query {string:?} contains no terms"
+			))),
+			1 => Ok(term_query(terms.swap_remove(0).1)),
+			_ => Ok(Box::new(PhraseQuery::new_with_offset(terms))),
+		}
+	}
+
+	fn resolve_prefix(&self, string: &str, field: Field) -> Result<Box<dyn Query>, SearchError> {
+		let mut terms = self.tokenize(string, field)?;
+
+		// The final term is treated as a prefix, all preceding terms must match in full.
+		// NOTE: stemming tokenizers will stem the partial term as well - which may
+		// cause some prefixes that are not a full word to miss.
+		let (_, last) = terms.pop().ok_or_else(|| {
+			SearchError::MalformedQuery(format!("prefix query {string:?} contains no terms"))
+		})?;
+
+		let text = last.as_str().unwrap_or_default();
+		let prefix_query = RegexQuery::from_pattern(&format!("{}.*", escape_regex(text)), field)
+			.map_err(|error| SearchError::Failure(error.into()))?;
+
+		let mut clauses = terms
+			.into_iter()
+			.map(|(_, term)| (TantivyOccur::Must, term_query(term)))
+			.collect::<Vec<_>>();
+		clauses.push((TantivyOccur::Must, Box::new(prefix_query)));
+
+		Ok(Box::new(BooleanQuery::new(clauses)))
+	}
+
+	fn resolve_range(&self, range: &Range, field: Field) -> Result<Box<dyn Query>, SearchError> {
+		let field_type = self.schema.get_field_entry(field).field_type().value_type();
+
+		let query = (|| -> Option<RangeQuery> {
+			Some(match field_type {
+				Type::U64 => {
+					let (lower, upper) = map_range(range, |value| self.value_to_u64(value))?;
+					RangeQuery::new_u64_bounds(field, lower, upper)
+				}
+				Type::I64 => {
+					let (lower, upper) = map_range(range, |value| self.value_to_i64(value))?;
+					RangeQuery::new_i64_bounds(field, lower, upper)
+				}
+				Type::F64 => {
+					let (lower, upper) = map_range(range, |value| self.value_to_f64(value))?;
+					RangeQuery::new_f64_bounds(field, lower, upper)
+				}
+				_ => return None,
+			})
+		})()
+		.ok_or_else(|| {
+			SearchError::FieldType(FieldTypeError {
+				// TODO: this will be pretty cryptic to end-users, try to resolve to the schema column name?
+				field: format!("field {}", self.schema.get_field_name(field)),
+				expected: field_type.name().to_string(),
+				got: format!("{range:?}"),
+			})
+		})?;
+
+		Ok(Box::new(query))
+	}
+
+	fn tokenize(&self, string: &str, field: Field) -> Result<Vec<(usize, Term)>, SearchError> {
 		// Get the analyser for this field from the tokeniser manager.
 		let analyzer = self
 			.index
@@ -112,24 +197,14 @@ impl QueryResolver<'_> {
 				other => SearchError::Failure(other.into()),
 			})?;
 
-		// Use the analyser to break the match string up into terms
+		// Use the analyser to break the string up into terms
 		let mut terms = vec![];
 		analyzer.token_stream(string).process(&mut |token| {
 			let term = Term::from_field_text(field, &token.text);
 			terms.push((token.position, term))
 		});
 
-		// If there's only one term, use a term query, otherwise a phrase query
-		match terms.len() {
-			0 => {
-				todo!("i'm not sure what a 0-term phrase query would mean. input: {string}")
-			}
-			1 => Ok(Box::new(TermQuery::new(
-				terms.swap_remove(0).1,
-				IndexRecordOption::Basic,
-			))),
-			_ => Ok(Box::new(PhraseQuery::new_with_offset(terms))),
-		}
+		Ok(terms)
 	}
 
 	fn value_to_term(&self, value: &Value, field: Field) -> Result<Term, SearchError> {
@@ -202,3 +277,241 @@ impl QueryResolver<'_> {
 		}
 	}
 }
+
+fn term_query(term: Term) -> Box<dyn Query> {
+	Box::new(TermQuery::new(term, IndexRecordOption::Basic))
+}
+
+// Convert both bounds of a range, failing if either cannot be represented.
+fn map_range<T>(
+	range: &Range,
+	convert: impl Fn(&Value) -> Option<T>,
+) -> Option<(Bound<T>, Bound<T>)> {
+	Some((
+		map_bound(&range.lower, &convert)?,
+		map_bound(&range.upper, &convert)?,
+	))
+}
+
+fn map_bound<T>(bound: &Bound<Value>, convert: impl Fn(&Value) -> Option<T>) -> Option<Bound<T>> {
+	Some(match bound {
+		Bound::Included(value) => Bound::Included(convert(value)?),
+		Bound::Excluded(value) => Bound::Excluded(convert(value)?),
+		Bound::Unbounded => Bound::Unbounded,
+	})
+}
+
+// Escape characters with special meaning in tantivy's regex syntax.
+fn escape_regex(string: &str) -> String {
+	let mut escaped = String::with_capacity(string.len());
+	for character in string.chars() {
+		if "\\.+*?()|[]{}^$#&-~".contains(character) {
+			escaped.push('\\');
+		}
+		escaped.push(character);
+	}
+	escaped
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::BTreeSet;
+
+	use ironworks::excel::Language;
+	use tantivy::{
+		collector::DocSetCollector,
+		doc,
+		schema::{TextFieldIndexing, TextOptions, INDEXED},
+	};
+
+	use crate::search::index::schema::register_tokenizers;
+
+	use super::*;
+
+	fn text_index(tokenizer: &str) -> (Index, Field) {
+		let mut builder = Schema::builder();
+		let indexing = TextFieldIndexing::default()
+			.set_tokenizer(tokenizer)
+			.set_index_option(IndexRecordOption::WithFreqsAndPositions);
+		let field = builder.add_text_field(
+			"0@en",
+			TextOptions::default().set_indexing_options(indexing),
+		);
+		let index = Index::create_in_ram(builder.build());
+		register_tokenizers(&index);
+		(index, field)
+	}
+
+	#[test]
+	fn match_queries() {
+		let (index, field) = text_index("en_stem");
+		let schema = index.schema();
+		let executor = Executor::for_language(Language::English);
+		let resolver = resolver(&index, &schema, &executor);
+
+		// Multiple terms must appear in order.
+		let query = resolver.resolve_match("Voidcast Gear", field).unwrap();
+		assert!(query.downcast_ref::<PhraseQuery>().is_some());
+
+		let query = resolver.resolve_match("Voidcast", field).unwrap();
+		assert!(query.downcast_ref::<TermQuery>().is_some());
+
+		// Input without any terms can't be matched against.
+		for input in ["", "!!", "  ."] {
+			assert!(matches!(
+				resolver.resolve_match(input, field),
+				Err(SearchError::MalformedQuery(_))
+			));
+			assert!(matches!(
+				resolver.resolve_prefix(input, field),
+				Err(SearchError::MalformedQuery(_))
+			));
+		}
+	}
+
+	fn resolver<'a>(
+		index: &'a Index,
+		schema: &'a Schema,
+		executor: &'a Executor,
+	) -> QueryResolver<'a> {
+		QueryResolver {
+			index,
+			schema,
+			executor,
+		}
+	}
+
+	// Documents are identified by their position in the index, starting at 0.
+	fn matches(index: &Index, query: &dyn Query) -> BTreeSet<u32> {
+		let searcher = index.reader().unwrap().searcher();
+		searcher
+			.search(query, &DocSetCollector)
+			.unwrap()
+			.into_iter()
+			.map(|address| address.doc_id)
+			.collect()
+	}
+
+	#[test]
+	fn range_queries() {
+		let mut builder = Schema::builder();
+		let unsigned = builder.add_u64_field("0", INDEXED);
+		let signed = builder.add_i64_field("1", INDEXED);
+		let float = builder.add_f64_field("2", INDEXED);
+		let index = Index::create_in_ram(builder.build());
+
+		// Documents 0..5 hold the values 0..5, -2..3, and 0.0..2.5 respectively.
+		let mut writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+		for value in 0..5u64 {
+			let signed_value = i64::try_from(value).unwrap() - 2;
+			writer
+				.add_document(doc!(
+					unsigned => value,
+					signed => signed_value,
+					float => value as f64 * 0.5,
+				))
+				.unwrap();
+		}
+		writer.commit().unwrap();
+
+		let schema = index.schema();
+		let executor = Executor::for_language(Language::English);
+		let resolver = resolver(&index, &schema, &executor);
+		let range = |lower, upper, field| {
+			let query = resolver
+				.resolve_range(&Range { lower, upper }, field)
+				.unwrap();
+			matches(&index, query.as_ref())
+		};
+
+		// >=3
+		assert_eq!(
+			range(Bound::Included(Value::U64(3)), Bound::Unbounded, unsigned),
+			BTreeSet::from([3, 4])
+		);
+		// <0
+		assert_eq!(
+			range(Bound::Unbounded, Bound::Excluded(Value::I64(0)), signed),
+			BTreeSet::from([0, 1])
+		);
+		// <1, with an unsigned bound on a signed field.
+		assert_eq!(
+			range(Bound::Unbounded, Bound::Excluded(Value::U64(1)), signed),
+			BTreeSet::from([0, 1, 2])
+		);
+		// =0.5..1.5
+		assert_eq!(
+			range(
+				Bound::Included(Value::F64(0.5)),
+				Bound::Included(Value::F64(1.5)),
+				float
+			),
+			BTreeSet::from([1, 2, 3])
+		);
+		// =1..2, with integer bounds on a float field.
+		assert_eq!(
+			range(
+				Bound::Included(Value::U64(1)),
+				Bound::Included(Value::U64(2)),
+				float
+			),
+			BTreeSet::from([2, 3, 4])
+		);
+
+		// Bounds that can't be represented by the field are rejected.
+		assert!(matches!(
+			resolver.resolve_range(
+				&Range {
+					lower: Bound::Included(Value::F64(0.5)),
+					upper: Bound::Unbounded,
+				},
+				unsigned
+			),
+			Err(SearchError::FieldType(_))
+		));
+	}
+
+	#[test]
+	fn prefix_queries() {
+		let (index, field) = text_index("en_stem");
+		let mut writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+		for text in ["Voidcast Gear", "Void Gear", "Gear of the Voidcast"] {
+			writer.add_document(doc!(field => text)).unwrap();
+		}
+		writer.commit().unwrap();
+
+		let schema = index.schema();
+		let executor = Executor::for_language(Language::English);
+		let resolver = resolver(&index, &schema, &executor);
+
+		// ^"Voidc"
+		let query = resolver.resolve_prefix("Voidc", field).unwrap();
+		assert_eq!(matches(&index, query.as_ref()), BTreeSet::from([0, 2]));
+
+		// ^"Gear of"
+		let query = resolver.resolve_prefix("Gear of", field).unwrap();
+		assert_eq!(matches(&index, query.as_ref()), BTreeSet::from([2]));
+	}
+
+	#[test]
+	fn cjk_phrase_queries() {
+		let (index, field) = text_index("cjk_ngram");
+		let mut writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+		for text in ["天地人", "人地天", "地人天地"] {
+			writer.add_document(doc!(field => text)).unwrap();
+		}
+		writer.commit().unwrap();
+
+		let schema = index.schema();
+		let executor = Executor::for_language(Language::Japanese);
+		let resolver = resolver(&index, &schema, &executor);
+
+		// "天地"
+		let query = resolver.resolve_match("天地", field).unwrap();
+		assert_eq!(matches(&index, query.as_ref()), BTreeSet::from([0, 2]));
+
+		// "天地人" - every n-gram appears in the last document, but not in sequence.
+		let query = resolver.resolve_match("天地人", field).unwrap();
+		assert_eq!(matches(&index, query.as_ref()), BTreeSet::from([0]));
+	}
+}
@@ -1,10 +1,26 @@
-use ironworks::file::exh;
-use tantivy::schema;
+use ironworks::{excel::Language, file::exh};
+use tantivy::{
+	schema,
+	tokenizer::{
+		BoxTokenStream, Language as StemmerLanguage, LowerCaser, NgramTokenizer, RemoveLongFilter,
+		SimpleTokenizer, Stemmer, TextAnalyzer, Token, TokenStream, Tokenizer,
+	},
+	Index,
+};
+
+use crate::data::LanguageString;
 
 pub const ROW_ID: &str = "row_id";
 pub const SUBROW_ID: &str = "subrow_id";
 
-pub fn build_sheet_schema(columns: &[exh::ColumnDefinition]) -> schema::Schema {
+const TOKENIZER_DE_STEM: &str = "de_stem";
+const TOKENIZER_FR_STEM: &str = "fr_stem";
+const TOKENIZER_CJK_NGRAM: &str = "cjk_ngram";
+
+pub fn build_sheet_schema(
+	columns: &[exh::ColumnDefinition],
+	languages: &[Language],
+) -> schema::Schema {
 	let mut schema_builder = schema::Schema::builder();
 
 	// RowID and SubrowID are the only stored fields, search results can be looked up in real excel for the full dataset.
@@ -16,8 +32,19 @@ pub fn build_sheet_schema(columns: &[exh::ColumnDefinition]) -> schema::Schema {
 
 		use exh::ColumnKind as CK;
 		match column.kind() {
-			// TODO: per-language columns. at the moment, this is just english
-			CK::String => schema_builder.add_text_field(&name, schema::TEXT),
+			// Strings are indexed once per language, each with an appropriate tokenizer.
+			CK::String => {
+				for &language in languages {
+					let indexing = schema::TextFieldIndexing::default()
+						.set_tokenizer(tokenizer_name(language))
+						.set_index_option(schema::IndexRecordOption::WithFreqsAndPositions);
+					schema_builder.add_text_field(
+						&language_field_name(column, language),
+						schema::TextOptions::default().set_indexing_options(indexing),
+					);
+				}
+				continue;
+			}
 
 			CK::Int8 | CK::Int16 | CK::Int32 | CK::Int64 => {
 				schema_builder.add_i64_field(&name, schema::INDEXED)
@@ -63,3 +90,199 @@ pub fn column_field_name(column: &exh::ColumnDefinition) -> String {
 
 	format!("{}{suffix}", column.offset())
 }
+
+pub fn language_field_name(column: &exh::ColumnDefinition, language: Language) -> String {
+	localised_field_name(&column_field_name(column), language)
+}
+
+/// Find the field a string column should be queried through. The requested
+/// language is used if indexed, falling back to unlocalised data in the same
+/// manner as excel reads.
+pub fn resolve_language_field_name(
+	schema: &schema::Schema,
+	column: &exh::ColumnDefinition,
+	language: Language,
+) -> String {
+	resolve_localised_field_name(schema, &column_field_name(column), language)
+}
+
+fn resolve_localised_field_name(schema: &schema::Schema, base: &str, language: Language) -> String {
+	[language, Language::None]
+		.into_iter()
+		.map(|language| localised_field_name(base, language))
+		.find(|name| schema.get_field(name).is_some())
+		.unwrap_or_else(|| localised_field_name(base, language))
+}
+
+fn localised_field_name(base: &str, language: Language) -> String {
+	format!("{base}@{}", LanguageString::from(language))
+}
+
+// Tokenizers are not persisted with an index, and must be registered every time one is opened.
+pub fn register_tokenizers(index: &Index) {
+	let tokenizers = index.tokenizers();
+
+	let stemmed = |language| {
+		TextAnalyzer::from(SimpleTokenizer)
+			.filter(RemoveLongFilter::limit(40))
+			.filter(LowerCaser)
+			.filter(Stemmer::new(language))
+	};
+	tokenizers.register(TOKENIZER_DE_STEM, stemmed(StemmerLanguage::German));
+	tokenizers.register(TOKENIZER_FR_STEM, stemmed(StemmerLanguage::French));
+
+	// CJK text isn't whitespace delimited - index uni- and bi-grams instead of words.
+	tokenizers.register(
+		TOKENIZER_CJK_NGRAM,
+		TextAnalyzer::from(CjkNgramTokenizer(NgramTokenizer::new(1, 2, false))).filter(LowerCaser),
+	);
+}
+
+// tantivy's n-gram tokenizer reports every token at position 0, which lets
+// phrase queries match n-grams in any order. Position each n-gram at the
+// character it starts on instead, so phrases must match in sequence.
+#[derive(Clone)]
+struct CjkNgramTokenizer(NgramTokenizer);
+
+impl Tokenizer for CjkNgramTokenizer {
+	fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+		CjkNgramTokenStream {
+			inner: self.0.token_stream(text),
+			text,
+			offset: 0,
+			position: 0,
+		}
+		.into()
+	}
+}
+
+struct CjkNgramTokenStream<'a> {
+	inner: BoxTokenStream<'a>,
+	text: &'a str,
+	offset: usize,
+	position: usize,
+}
+
+impl TokenStream for CjkNgramTokenStream<'_> {
+	fn advance(&mut self) -> bool {
+		if !self.inner.advance() {
+			return false;
+		}
+
+		let token = self.inner.token_mut();
+		self.position += self.text[self.offset..token.offset_from].chars().count();
+		self.offset = token.offset_from;
+		token.position = self.position;
+
+		true
+	}
+
+	fn token(&self) -> &Token {
+		self.inner.token()
+	}
+
+	fn token_mut(&mut self) -> &mut Token {
+		self.inner.token_mut()
+	}
+}
+
+fn tokenizer_name(language: Language) -> &'static str {
+	use Language as L;
+	match language {
+		// en_stem and default are registered by tantivy.
+		L::English => "en_stem",
+		L::German => TOKENIZER_DE_STEM,
+		L::French => TOKENIZER_FR_STEM,
+		L::Japanese | L::ChineseSimplified | L::ChineseTraditional | L::Korean => {
+			TOKENIZER_CJK_NGRAM
+		}
+		L::None => "default",
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const LANGUAGES: [Language; 8] = [
+		Language::None,
+		Language::Japanese,
+		Language::English,
+		Language::German,
+		Language::French,
+		Language::ChineseSimplified,
+		Language::ChineseTraditional,
+		Language::Korean,
+	];
+
+	fn tokens(index: &Index, language: Language, text: &str) -> Vec<String> {
+		positioned_tokens(index, language, text)
+			.into_iter()
+			.map(|(_, text)| text)
+			.collect()
+	}
+
+	fn positioned_tokens(index: &Index, language: Language, text: &str) -> Vec<(usize, String)> {
+		let analyzer = index.tokenizers().get(tokenizer_name(language)).unwrap();
+		let mut stream = analyzer.token_stream(text);
+		let mut tokens = vec![];
+		while stream.advance() {
+			let token = stream.token();
+			tokens.push((token.position, token.text.clone()));
+		}
+		tokens
+	}
+
+	#[test]
+	fn tokenizers() {
+		let index = Index::create_in_ram(schema::Schema::builder().build());
+		register_tokenizers(&index);
+
+		// Every language must select a tokenizer that is available on the index.
+		for language in LANGUAGES {
+			assert!(
+				index.tokenizers().get(tokenizer_name(language)).is_some(),
+				"missing tokenizer for {language:?}"
+			);
+		}
+
+		assert_eq!(tokens(&index, Language::English, "Running"), ["run"]);
+		assert_eq!(tokens(&index, Language::German, "Häuser"), ["haus"]);
+		assert_eq!(tokens(&index, Language::None, "Running"), ["running"]);
+		assert_eq!(
+			tokens(&index, Language::Japanese, "天地"),
+			["天", "天地", "地"]
+		);
+
+		// CJK n-grams are positioned at the character they start on.
+		assert_eq!(
+			positioned_tokens(&index, Language::Japanese, "天地人"),
+			[
+				(0, "天".to_string()),
+				(0, "天地".to_string()),
+				(1, "地".to_string()),
+				(1, "地人".to_string()),
+				(2, "人".to_string()),
+			]
+		);
+	}
+
+	#[test]
+	fn language_field_fallback() {
+		let mut builder = schema::Schema::builder();
+		builder.add_text_field("0@en", schema::TEXT);
+		builder.add_text_field("0@de", schema::TEXT);
+		builder.add_text_field("4@none", schema::TEXT);
+		let schema = builder.build();
+
+		let resolve = |base, language| resolve_localised_field_name(&schema, base, language);
+
+		// Indexed languages are used directly.
+		assert_eq!(resolve("0", Language::German), "0@de");
+		// Unlocalised fields are used for any language.
+		assert_eq!(resolve("4", Language::English), "4@none");
+		// Missing languages don't fall back to other localised data, and resolve to
+		// a field that will be reported as a mismatch.
+		assert_eq!(resolve("0", Language::French), "0@fr");
+	}
+}
@@ -232,7 +232,7 @@ impl<'a> Normalizer<'a> {
 				Ok(node)
 			}
 
-			pre::Operation::Match(string) => {
+			pre::Operation::Match(_) | pre::Operation::Prefix(_) => {
 				let scalar_columns = collect_scalars(schema, columns, vec![]).ok_or_else(|| {
 					SearchError::SchemaMismatch(MismatchError {
 						// TODO: i'll need to wire down the current query path for this field to be meaningful
@@ -251,7 +251,7 @@ impl<'a> Normalizer<'a> {
 				let group = create_or_group(string_columns.into_iter().map(|column| {
 					post::Node::Leaf(post::Leaf {
 						field: column,
-						operation: post::Operation::Match(string.clone()),
+						operation: normalize_scalar_operation(operation),
 					})
 				}));
 
@@ -260,7 +260,7 @@ impl<'a> Normalizer<'a> {
 
 			// TODO: this should collect all scalars i think?
 			// TODO: this pattern will be pretty repetetive, make a utility that does this or something
-			pre::Operation::Equal(value) => {
+			pre::Operation::Equal(_) | pre::Operation::Range(_) => {
 				let scalar_columns = collect_scalars(schema, columns, vec![]).ok_or_else(|| {
 					SearchError::SchemaMismatch(MismatchError {
 						// TODO: i'll need to wire down the current query path for this field to be meaningful
@@ -272,7 +272,7 @@ impl<'a> Normalizer<'a> {
 				let group = create_or_group(scalar_columns.into_iter().map(|column| {
 					post::Node::Leaf(post::Leaf {
 						field: column,
-						operation: post::Operation::Equal(value.clone()),
+						operation: normalize_scalar_operation(operation),
 					})
 				}));
 
@@ -282,6 +282,17 @@ impl<'a> Normalizer<'a> {
 	}
 }
 
+// Operations on scalar values don't reference fields or targets, and can be carried across as-is.
+fn normalize_scalar_operation(operation: &pre::Operation) -> post::Operation {
+	match operation {
+		pre::Operation::Match(string) => post::Operation::Match(string.clone()),
+		pre::Operation::Prefix(string) => post::Operation::Prefix(string.clone()),
+		pre::Operation::Equal(value) => post::Operation::Equal(value.clone()),
+		pre::Operation::Range(range) => post::Operation::Range(range.clone()),
+		pre::Operation::Relation(_) => unreachable!("relations are not scalar operations"),
+	}
+}
+
 fn create_or_group(mut nodes: impl ExactSizeIterator<Item = post::Node>) -> post::Node {
 	match nodes.len() {
 		0 => todo!("what do i do for the zero case? it's possibly a schema mismatch but i'm not sure. consider callsites"),
@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use std::{ops::Bound, str::FromStr};
 
 use nom::{
 	branch::alt,
@@ -8,7 +8,7 @@ use nom::{
 	error::convert_error,
 	multi::separated_list1,
 	number::complete::double,
-	sequence::{delimited, preceded, terminated, tuple},
+	sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
 	Finish,
 };
 use serde::{de, Deserialize};
@@ -98,7 +98,9 @@ fn field_specifier_array(input: &str) -> IResult<&str, pre::FieldSpecifier> {
 fn operation(input: &str) -> IResult<&str, pre::Operation> {
 	alt((
 		map(relation, pre::Operation::Relation),
+		map(range, pre::Operation::Range),
 		map(preceded(char('='), value), pre::Operation::Equal),
+		map(preceded(char('^'), string), pre::Operation::Prefix),
 		// An un-adorned string acts as a match query. This needs to be last to ensure other sigils take priority.
 		map(string, pre::Operation::Match),
 	))(input)
@@ -111,15 +113,38 @@ fn relation(input: &str) -> IResult<&str, pre::Relation> {
 	})(input)
 }
 
+fn range(input: &str) -> IResult<&str, pre::Range> {
+	let range = |lower, upper| pre::Range { lower, upper };
+	alt((
+		// Inclusive ranges need to be checked before equality, as they share a prefix.
+		map(
+			preceded(char('='), separated_pair(value, tag(".."), value)),
+			move |(lower, upper)| range(Bound::Included(lower), Bound::Included(upper)),
+		),
+		map(preceded(tag(">="), value), move |value| {
+			range(Bound::Included(value), Bound::Unbounded)
+		}),
+		map(preceded(char('>'), value), move |value| {
+			range(Bound::Excluded(value), Bound::Unbounded)
+		}),
+		map(preceded(tag("<="), value), move |value| {
+			range(Bound::Unbounded, Bound::Included(value))
+		}),
+		map(preceded(char('<'), value), move |value| {
+			range(Bound::Unbounded, Bound::Excluded(value))
+		}),
+	))(input)
+}
+
 fn value(input: &str) -> IResult<&str, pre::Value> {
 	alt((
-		// Try to parse the number as a potentially-signed integer. If it's followed by `.`, it'll fall through to the float check.
+		// Try to parse the number as a potentially-signed integer. If it's followed by a single `.`, it'll fall through to the float check - `..` is a range.
 		terminated(
 			alt((
 				map(map_res(digit1, str::parse), pre::Value::U64),
 				map(map_res(take_while1(is_signed), str::parse), pre::Value::I64),
 			)),
-			not(char('.')),
+			not(pair(char('.'), not(char('.')))),
 		),
 		map(double, pre::Value::F64),
 		map(string, pre::Value::String),
@@ -140,3 +165,55 @@ fn string(input: &str) -> IResult<&str, String> {
 fn is_signed(char: char) -> bool {
 	char.is_ascii_digit() || char == '-'
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn parse_operation(input: &str) -> pre::Operation {
+		let (remaining, operation) = operation(input).unwrap();
+		assert_eq!(remaining, "", "{input}");
+		operation
+	}
+
+	#[test]
+	fn ranges() {
+		assert!(matches!(
+			parse_operation("=50..60"),
+			pre::Operation::Range(pre::Range {
+				lower: Bound::Included(pre::Value::U64(50)),
+				upper: Bound::Included(pre::Value::U64(60)),
+			})
+		));
+		assert!(matches!(
+			parse_operation(">=-1.5"),
+			pre::Operation::Range(pre::Range {
+				lower: Bound::Included(pre::Value::F64(value)),
+				upper: Bound::Unbounded,
+			}) if value == -1.5
+		));
+		assert!(matches!(
+			parse_operation("<600"),
+			pre::Operation::Range(pre::Range {
+				lower: Bound::Unbounded,
+				upper: Bound::Excluded(pre::Value::U64(600)),
+			})
+		));
+		assert!(matches!(
+			parse_operation("=50"),
+			pre::Operation::Equal(pre::Value::U64(50))
+		));
+	}
+
+	#[test]
+	fn string_operations() {
+		assert!(matches!(
+			parse_operation(r#"^"Voidcast""#),
+			pre::Operation::Prefix(string) if string == "Voidcast"
+		));
+		assert!(matches!(
+			parse_operation(r#""of the""#),
+			pre::Operation::Match(string) if string == "of the"
+		));
+	}
+}
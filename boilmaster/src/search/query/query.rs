@@ -1,3 +1,5 @@
+use std::ops::Bound;
+
 #[derive(Debug)]
 pub enum Node<F, T> {
 	Group(Group<F, T>),
@@ -27,10 +29,21 @@ pub struct Leaf<F, T> {
 pub enum Operation<F, T> {
 	Relation(Relation<F, T>),
 
+	/// Match strings containing the terms in the query, in order.
 	Match(String),
+	/// Match strings containing the terms in the query, where the final term may
+	/// be incomplete.
+	Prefix(String),
 
 	Equal(Value),
-	// TODO: all the other relevant leaf operations. will need both further math operations, as well as ranges and string ops (given i'm using this instead of generic string param)
+	Range(Range),
+	// TODO: string equality and ranges.
+}
+
+#[derive(Debug, Clone)]
+pub struct Range {
+	pub lower: Bound<Value>,
+	pub upper: Bound<Value>,
 }
 
 #[derive(Debug)]
@@ -1,6 +1,7 @@
 use std::{
+	collections::HashMap,
 	path::PathBuf,
-	sync::{Arc, Mutex},
+	sync::{Arc, RwLock},
 };
 
 use figment::value::magic::RelativePathBuf;
@@ -32,8 +33,8 @@ pub struct Search {
 
 	index_directory: PathBuf,
 
-	// TODO: this should be a map of version keys to search::Version instances
-	temp_version: Mutex<Option<Arc<Version>>>,
+	// Keyed by data version key. Versions are only present once ingested.
+	versions: RwLock<HashMap<String, Arc<Version>>>,
 }
 
 impl Search {
@@ -42,7 +43,7 @@ impl Search {
 		Self {
 			ingester: Ingester::new(config.ingest),
 			index_directory: config.index.directory.relative(),
-			temp_version: Default::default(),
+			versions: Default::default(),
 		}
 	}
 
@@ -53,28 +54,26 @@ impl Search {
 		data: &Data,
 		version: Option<&str>,
 	) -> Result<(), SearchError> {
-		let data_version = data.version(version);
-		let search_version = Arc::new(Version::new(
-			self.index_directory.join(version.unwrap_or("__NONE")),
-		));
+		let data_version = data.version(version).map_err(anyhow::Error::from)?;
+		let key = data_version.key();
+		let search_version = Arc::new(Version::new(self.index_directory.join(key)));
 
+		// Versions are only made available once ingestion has completed.
 		tokio::select! {
-			_ = shutdown => {},
-			result = search_version.clone().ingest(&self.ingester, data_version) => { result? },
+			_ = shutdown => return Ok(()),
+			result = search_version.clone().ingest(&self.ingester, &data_version) => { result? },
 		}
 
-		let mut guard = self.temp_version.lock().unwrap();
-		*guard = Some(search_version);
+		self.versions
+			.write()
+			.unwrap()
+			.insert(key.to_string(), search_version);
 
 		Ok(())
 	}
 
-	pub fn version(&self, version: Option<&str>) -> Option<Arc<Version>> {
-		// TODO: actual version handling
-		if version.is_some() {
-			todo!("search version handling");
-		}
-
-		self.temp_version.lock().unwrap().clone()
+	/// Get the search index for the specified data version key, if it has been ingested.
+	pub fn version(&self, key: &str) -> Option<Arc<Version>> {
+		self.versions.read().unwrap().get(key).cloned()
 	}
 }
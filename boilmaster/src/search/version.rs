@@ -1,4 +1,5 @@
 use std::{
+	cmp::Ordering,
 	collections::{HashMap, HashSet},
 	path::PathBuf,
 	sync::{Arc, RwLock},
@@ -8,7 +9,10 @@ use futures::{stream::FuturesUnordered, StreamExt};
 use ironworks::excel;
 use ironworks_schema::Schema;
 
-use crate::{data::Version as DataVersion, utility::warnings::Warnings};
+use crate::{
+	data::Version as DataVersion,
+	utility::warnings::{Warning, WarningCode, Warnings},
+};
 
 use super::{
 	error::SearchError,
@@ -25,6 +29,14 @@ pub struct SearchResult {
 	pub subrow_id: u16,
 }
 
+/// A page of search results, ordered by descending score across all searched sheets.
+#[derive(Debug)]
+pub struct SearchPage {
+	pub results: Vec<SearchResult>,
+	/// Whether further results are available after this page.
+	pub more: bool,
+}
+
 pub struct Version {
 	path: PathBuf,
 
@@ -88,14 +100,20 @@ impl Version {
 		Ok(())
 	}
 
-	// TODO: continuation?
+	/// Search the indices, returning `limit` results after skipping the first
+	/// `offset`. Indices are never modified once ingested, so requests for
+	/// consecutive pages of the same query will produce consistent results.
+	#[allow(clippy::too_many_arguments)]
 	pub fn search(
 		&self,
 		query: &pre::Node,
-		sheet_filter: Option<HashSet<String>>,
+		sheet_filter: Option<&HashSet<String>>,
+		language: excel::Language,
 		excel: &excel::Excel,
 		schema: &dyn Schema,
-	) -> Result<Warnings<Vec<SearchResult>>, SearchError> {
+		offset: usize,
+		limit: usize,
+	) -> Result<Warnings<SearchPage>, SearchError> {
 		let option = self.indices.read().expect("TODO error poisoned");
 		let indices = option
 			.as_ref()
@@ -106,6 +124,22 @@ impl Version {
 		// This effectively creates a snapshot of the indices at the time of creation.
 		let executor = Executor {
 			indices: indices.clone(),
+			language,
+		};
+
+		// Every index needs to provide enough results to fill the page on its own,
+		// plus one to check if there are further pages.
+		let index_limit = offset + limit + 1;
+
+		let search_index = |name: &String| -> Result<_, SearchError> {
+			// Normalise the query for each requested index using the provided schema.
+			let normalized_query = normalizer.normalize(query, name)?;
+
+			// Execute the query, tagging the results with the sheet the result is from.
+			let results = executor.search(name, &normalized_query, index_limit)?;
+			let sheet = name.clone();
+			let tagged_results = results.map(move |result| (sheet.clone(), result));
+			Ok(tagged_results)
 		};
 
 		// Get an iterator for each of the indexes, lifting any errors from the initial search execution.
@@ -113,69 +147,136 @@ impl Version {
 		let index_results = indices
 			.keys()
 			// Filter to the requested indexes if any sheet filer is specified.
-			.filter(|name| {
-				sheet_filter
-					.as_ref()
-					.map_or(true, |sheets| sheets.contains(name.as_str()))
-			})
+			.filter(|name| sheet_filter.map_or(true, |sheets| sheets.contains(name.as_str())))
 			// Execute the query on each matching index
-			.map(|name| {
-				// Normalise the query for each requested index using the provided schema.
-				let normalized_query = normalizer.normalize(query, name)?;
+			.map(|name| (name, search_index(name)))
+			.try_fold(
+				Warnings::new(vec![]),
+				|warnings, (name, result)| match result {
+					// Successful search results can be pushed to the inner vector in the warnings.
+					Ok(results) => Ok(warnings.map(|mut vec| {
+						vec.push(results);
+						vec
+					})),
+					// Failures should short circuit completely.
+					Err(error @ SearchError::Failure(_)) => Err(error),
+					// Query mismatches will be raised for most sheets, and aren't particularly meaningful for end-users. Skip.
+					// TODO: ... right? i mean, it kind of sucks to not be able to say "oi this field doesn't exist" but... idk.
+					Err(SearchError::QueryMismatch(_)) => Ok(warnings),
+					// Other errors can be raised as warnings without halting the process.
+					Err(error) => Ok(warnings.with_warning(search_warning(error).with_sheet(name))),
+				},
+			)?;
 
-				// Execute the query, tagging the results with the sheet the result is from.
-				let results = executor.search(name, &normalized_query)?;
-				let tagged_results = results.map(|result| SearchResult {
+		// TODO: a zero-length array here implies all indices were query mismatches, or no index was queried at all. disambiguate and error out.
+		// TODO: following the introduction of warnings; that's not quite right - it might all have ended up as warnings, too. While that's possibly _fine_ for i.e. a multi-sheet query, for a _single_ sheet query, it might be more-sane to raise as a top-level error. Think about it a bit, because... yeah. That's not exactly _consistent_ but maybe it's expected?
+
+		// Merge the results from each index, and order them globally. Ties are broken
+		// with the same ordering used within an index, so that the truncated
+		// results of each index remain a prefix of the global order.
+		let page = index_results.map(|vec| {
+			let mut results = vec.into_iter().flatten().collect::<Vec<_>>();
+			results.sort_by(|(a_name, a), (b_name, b)| {
+				b.score
+					.partial_cmp(&a.score)
+					.unwrap_or(Ordering::Equal)
+					.then_with(|| a_name.cmp(b_name))
+					.then_with(|| a.address.cmp(&b.address))
+			});
+
+			let more = results.len() > offset + limit;
+			let results = results
+				.into_iter()
+				.skip(offset)
+				.take(limit)
+				.map(|(name, result)| SearchResult {
 					score: result.score,
-					sheet: name.to_owned(),
+					sheet: name,
 					row_id: result.row_id,
 					subrow_id: result.subrow_id,
-				});
-				Ok(tagged_results)
-			})
-			.try_fold(Warnings::new(vec![]), |warnings, result| match result {
-				// Successful search results can be pushed to the inner vector in the warnings.
-				Ok(results) => Ok(warnings.map(|mut vec| {
-					vec.push(results);
-					vec
-				})),
-				// Failures should short circuit completely.
-				Err(error @ SearchError::Failure(_)) => Err(error),
-				// Query mismatches will be raised for most sheets, and aren't particularly meaningful for end-users. Skip.
-				// TODO: ... right? i mean, it kind of sucks to not be able to say "oi this field doesn't exist" but... idk.
-				Err(SearchError::QueryMismatch(_)) => Ok(warnings),
-				// Other errors can be raised as warnings without halting the process.
-				// TODO: find some way to tag this with the sheet name because at the moment the warnings are entirely unactionable.
-				Err(error) => Ok(warnings.with_warning(error.to_string())),
-			})?;
+				})
+				.collect();
 
-		// TODO: a zero-length array here implies all indices were query mismatches, or no index was queried at all. disambiguate and error out.
-		// TODO: following the introduction of warnings; that's not quite right - it might all have ended up as warnings, too. While that's possibly _fine_ for i.e. a multi-sheet query, for a _single_ sheet query, it might be more-sane to raise as a top-level error. Think about it a bit, because... yeah. That's not exactly _consistent_ but maybe it's expected?
+			SearchPage { results, more }
+		});
 
-		// TODO: this just groups by index, effectively - should probably sort by score at this point
-		// Merge the results from each index into a single vector.
-		let results = index_results.map(|vec| vec.into_iter().flatten().collect::<Vec<_>>());
-
-		Ok(results)
+		Ok(page)
 	}
 }
 
 // TODO: can probably store the number of search executions on this to feed into rate limiting
 pub struct Executor {
 	indices: Arc<HashMap<String, Index>>,
+	pub(super) language: excel::Language,
 }
 
 impl Executor {
+	#[cfg(test)]
+	pub(super) fn for_language(language: excel::Language) -> Self {
+		Self {
+			indices: Default::default(),
+			language,
+		}
+	}
+
 	pub fn search(
 		&self,
 		sheet: &str,
 		query: &post::Node,
+		limit: usize,
 	) -> Result<impl Iterator<Item = IndexResult>, SearchError> {
 		let index = self
 			.indices
 			.get(sheet)
 			.expect("TODO: error handling. this should probably be a hard fail?");
 
-		index.search(self, query)
+		index.search(self, query, limit)
+	}
+}
+
+fn search_warning(error: SearchError) -> Warning {
+	let message = error.to_string();
+	match error {
+		SearchError::FieldType(inner) => {
+			Warning::new(WarningCode::SearchFieldType, message).with_path(inner.field)
+		}
+		SearchError::QueryMismatch(inner) => {
+			Warning::new(WarningCode::SearchQueryMismatch, message).with_path(inner.field)
+		}
+		SearchError::SchemaMismatch(inner) => {
+			Warning::new(WarningCode::SearchSchemaMismatch, message).with_path(inner.field)
+		}
+		SearchError::MalformedQuery(_) | SearchError::Failure(_) => {
+			Warning::new(WarningCode::SearchMalformedQuery, message)
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::search::error::{FieldTypeError, MismatchError};
+
+	use super::*;
+
+	#[test]
+	fn search_warning_paths() {
+		let warning = search_warning(SearchError::FieldType(FieldTypeError {
+			field: "Name".into(),
+			expected: "string".into(),
+			got: "number".into(),
+		}));
+		assert_eq!(warning.code, WarningCode::SearchFieldType);
+		assert_eq!(warning.path.as_deref(), Some("Name"));
+
+		let warning = search_warning(SearchError::SchemaMismatch(MismatchError {
+			field: "Icon".into(),
+			reason: "reason".into(),
+		}));
+		assert_eq!(warning.code, WarningCode::SearchSchemaMismatch);
+		assert_eq!(warning.path.as_deref(), Some("Icon"));
+
+		let warning = search_warning(SearchError::MalformedQuery("query".into()));
+		assert_eq!(warning.code, WarningCode::SearchMalformedQuery);
+		assert_eq!(warning.path, None);
 	}
 }
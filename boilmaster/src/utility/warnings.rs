@@ -1,9 +1,77 @@
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Machine-readable identifier for the kind of a warning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningCode {
+	/// Two field filters of different kinds were merged, and both were ignored.
+	FilterMergeConflict,
+	/// A field filter was ignored, as another filter selected all values.
+	FilterIgnored,
+	/// A search query value could not be coerced to the type of a field.
+	SearchFieldType,
+	/// A search query could not be executed.
+	SearchMalformedQuery,
+	/// A search query could not be mapped onto the schema of a sheet.
+	SearchQueryMismatch,
+	/// The schema for a sheet does not map onto its search index.
+	SearchSchemaMismatch,
+	/// The data for a row could not be read.
+	ReadFailed,
+	/// The requested schema does not define the sheet.
+	SchemaMissing,
+}
+
+/// A non-fatal problem encountered while handling a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Warning {
+	pub code: WarningCode,
+	pub message: String,
+	/// Location within the query value the warning relates to, if any.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub path: Option<String>,
+	/// The sheet the warning relates to, if any.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub sheet: Option<String>,
+}
+
+impl Warning {
+	pub fn new(code: WarningCode, message: impl Into<String>) -> Self {
+		Self {
+			code,
+			message: message.into(),
+			path: None,
+			sheet: None,
+		}
+	}
+
+	#[must_use]
+	pub fn with_path(mut self, path: impl Into<String>) -> Self {
+		self.path = Some(path.into());
+		self
+	}
+
+	#[must_use]
+	pub fn with_sheet(mut self, sheet: impl Into<String>) -> Self {
+		self.sheet = Some(sheet.into());
+		self
+	}
+
+	/// Prefix the path of this warning with a parent segment.
+	#[must_use]
+	pub fn nested(mut self, segment: &str) -> Self {
+		self.path = Some(match self.path {
+			Some(path) => format!("{segment}.{path}"),
+			None => segment.to_string(),
+		});
+		self
+	}
+}
 
 #[derive(Debug)]
 pub struct Warnings<T> {
 	value: T,
-	warnings: Vec<String>,
+	warnings: Vec<Warning>,
 }
 
 impl<T> Warnings<T> {
@@ -15,13 +83,13 @@ impl<T> Warnings<T> {
 	}
 
 	#[must_use]
-	pub fn with_warning(mut self, warning: impl Into<String>) -> Self {
-		self.warnings.push(warning.into());
+	pub fn with_warning(mut self, warning: Warning) -> Self {
+		self.warnings.push(warning);
 		self
 	}
 
 	#[must_use]
-	pub fn with_warnings(mut self, warnings: impl IntoIterator<Item = String>) -> Self {
+	pub fn with_warnings(mut self, warnings: impl IntoIterator<Item = Warning>) -> Self {
 		self.warnings.extend(warnings.into_iter());
 		self
 	}
@@ -36,6 +104,17 @@ impl<T> Warnings<T> {
 		}
 	}
 
+	/// Apply a function to each of the warnings, leaving the value untouched.
+	pub fn map_warnings<F>(self, function: F) -> Self
+	where
+		F: FnMut(Warning) -> Warning,
+	{
+		Self {
+			value: self.value,
+			warnings: self.warnings.into_iter().map(function).collect(),
+		}
+	}
+
 	pub fn and_then<U, F>(self, function: F) -> Warnings<U>
 	where
 		F: FnOnce(T) -> Warnings<U>,
@@ -43,9 +122,7 @@ impl<T> Warnings<T> {
 		function(self.value).with_warnings(self.warnings)
 	}
 
-	// Used primarily for tests at the moment but hey who knows
-	#[allow(dead_code)]
-	pub fn decompose(self) -> (T, Vec<String>) {
+	pub fn decompose(self) -> (T, Vec<Warning>) {
 		(self.value, self.warnings)
 	}
 }
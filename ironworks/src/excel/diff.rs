@@ -0,0 +1,800 @@
+use std::{
+	borrow::Cow,
+	collections::{BTreeMap, BTreeSet},
+};
+
+use crate::{
+	error::{Error, ErrorValue, Result},
+	file::exh,
+};
+
+use super::{excel::Excel, field::Field, language::Language, row::Row, sheet::Sheet};
+
+/// Differences between two Excel databases.
+///
+/// All collections within the report are sorted, such that comparing the same
+/// pair of databases will always produce an identical report.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExcelDiff {
+	/// Sheets present only in the newer database.
+	pub added_sheets: Vec<String>,
+	/// Sheets present only in the older database.
+	pub removed_sheets: Vec<String>,
+	/// Sheets present in both databases whose header or contents differ.
+	pub changed_sheets: Vec<SheetDiff>,
+}
+
+/// Differences between two versions of a single sheet.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SheetDiff {
+	/// Name of the sheet.
+	pub sheet: String,
+	/// Changes to the sheet's header, if any were made.
+	pub header: Option<HeaderDiff>,
+	/// Rows present only in the newer sheet.
+	pub added_rows: Vec<RowKey>,
+	/// Rows present only in the older sheet.
+	pub removed_rows: Vec<RowKey>,
+	/// Rows present in both sheets with differing field values.
+	pub changed_rows: Vec<RowDiff>,
+}
+
+impl SheetDiff {
+	fn is_empty(&self) -> bool {
+		self.header.is_none()
+			&& self.added_rows.is_empty()
+			&& self.removed_rows.is_empty()
+			&& self.changed_rows.is_empty()
+	}
+}
+
+/// A value that differs between two versions.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Change<T> {
+	pub old: T,
+	pub new: T,
+}
+
+impl<T: PartialEq> Change<T> {
+	fn between(old: T, new: T) -> Option<Self> {
+		match old == new {
+			true => None,
+			false => Some(Self { old, new }),
+		}
+	}
+}
+
+/// Differences between two versions of a sheet's header.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeaderDiff {
+	/// Change to the kind of the sheet.
+	pub kind: Option<Change<exh::SheetKind>>,
+	/// Columns that were added, removed, or changed kind or offset.
+	pub columns: Vec<ColumnDiff>,
+	/// Change to the pages of the sheet.
+	pub pages: Option<Change<Vec<Page>>>,
+	/// Change to the languages supported by the sheet.
+	pub languages: Option<Change<Vec<Language>>>,
+}
+
+/// Difference in the definition of a single column. A value of `None` indicates
+/// the column does not exist in that version.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColumnDiff {
+	/// Index of the column within the sheet's columns.
+	pub index: usize,
+	/// Definition of the column in the older sheet.
+	pub old: Option<Column>,
+	/// Definition of the column in the newer sheet.
+	pub new: Option<Column>,
+}
+
+/// Definition of a single column.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Column {
+	pub kind: exh::ColumnKind,
+	pub offset: u16,
+}
+
+/// Definition of a single page of rows.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Page {
+	pub start_id: u32,
+	pub row_count: u32,
+}
+
+/// Identifier of a (sub)row within a sheet, in a single language.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RowKey {
+	pub row_id: u32,
+	pub subrow_id: u16,
+	pub language: Language,
+}
+
+/// Differences between two versions of a single row.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RowDiff {
+	/// The row that changed.
+	pub row: RowKey,
+	/// Fields whose values differ.
+	pub fields: Vec<FieldDiff>,
+}
+
+/// Difference in the value of a single field. A value of `None` indicates the
+/// column does not exist in that version.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldDiff {
+	/// Index of the column within the sheet's columns.
+	pub column: usize,
+	/// Value of the field in the older row.
+	pub old: Option<Value>,
+	/// Value of the field in the newer row.
+	pub new: Option<Value>,
+}
+
+/// Owned representation of a field value.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+	String(String),
+
+	Bool(bool),
+
+	I8(i8),
+	I16(i16),
+	I32(i32),
+	I64(i64),
+
+	U8(u8),
+	U16(u16),
+	U32(u32),
+	U64(u64),
+
+	F32(f32),
+}
+
+impl From<Field> for Value {
+	fn from(field: Field) -> Self {
+		use Field as F;
+		match field {
+			F::String(value) => Self::String(value.to_string()),
+			F::Bool(value) => Self::Bool(value),
+			F::I8(value) => Self::I8(value),
+			F::I16(value) => Self::I16(value),
+			F::I32(value) => Self::I32(value),
+			F::I64(value) => Self::I64(value),
+			F::U8(value) => Self::U8(value),
+			F::U16(value) => Self::U16(value),
+			F::U32(value) => Self::U32(value),
+			F::U64(value) => Self::U64(value),
+			F::F32(value) => Self::F32(value),
+		}
+	}
+}
+
+impl Value {
+	fn same(&self, other: &Self) -> bool {
+		match (self, other) {
+			// Compare bitwise, so NaN values are considered unchanged.
+			(Self::F32(a), Self::F32(b)) => a.to_bits() == b.to_bits(),
+			(a, b) => a == b,
+		}
+	}
+}
+
+impl Excel<'_> {
+	/// Compare this database against a newer database, reporting the sheets and
+	/// rows that differ. Every sheet is read in its entirety - prefer
+	/// [`Excel::diff_sheets`] when the set of potentially changed sheets is known.
+	pub fn diff(&self, newer: &Excel) -> Result<ExcelDiff> {
+		let sheets = self
+			.list()?
+			.iter()
+			.map(|sheet| sheet.to_string())
+			.collect::<Vec<_>>();
+		self.diff_sheets(newer, sheets)
+	}
+
+	/// Compare this database against a newer database, limiting comparison to
+	/// the specified sheets. Added and removed sheets are always reported.
+	///
+	/// Rows are compared in every language each sheet contains data for. Fields
+	/// are compared by column index; if the columns of a sheet have changed, fields will be
+	/// compared as-is regardless of the change in layout.
+	pub fn diff_sheets(
+		&self,
+		newer: &Excel,
+		sheets: impl IntoIterator<Item = impl AsRef<str>>,
+	) -> Result<ExcelDiff> {
+		let old_list = self.list()?;
+		let new_list = newer.list()?;
+
+		let mut diff = ExcelDiff {
+			added_sheets: sorted(new_list.iter().filter(|sheet| !old_list.has(sheet))),
+			removed_sheets: sorted(old_list.iter().filter(|sheet| !new_list.has(sheet))),
+			changed_sheets: vec![],
+		};
+
+		let sheets = sheets
+			.into_iter()
+			.map(|sheet| sheet.as_ref().to_string())
+			.filter(|sheet| old_list.has(sheet) && new_list.has(sheet))
+			.collect::<BTreeSet<_>>();
+
+		for sheet in sheets {
+			let sheet_diff = diff_sheet(self, newer, &sheet)?;
+			if !sheet_diff.is_empty() {
+				diff.changed_sheets.push(sheet_diff);
+			}
+		}
+
+		Ok(diff)
+	}
+
+	/// Paths of every file that comprises the specified sheet.
+	#[cfg(feature = "zipatch")]
+	pub(crate) fn sheet_paths(&self, sheet: &str) -> Result<Vec<String>> {
+		use super::path;
+
+		let sheet_data = self.sheet(sheet)?;
+		let header = sheet_data.header()?;
+		let languages = sheet_data.languages()?;
+
+		let mut paths = vec![path::exh(sheet)];
+		for page in header.pages() {
+			for &language in &languages {
+				paths.push(path::exd(sheet, page.start_id(), language));
+			}
+		}
+
+		Ok(paths)
+	}
+}
+
+fn sorted<'a>(sheets: impl Iterator<Item = Cow<'a, str>>) -> Vec<String> {
+	let mut sheets = sheets.map(|sheet| sheet.to_string()).collect::<Vec<_>>();
+	sheets.sort_unstable();
+	sheets
+}
+
+fn diff_sheet(old: &Excel, new: &Excel, sheet: &str) -> Result<SheetDiff> {
+	let old_sheet = old.sheet(sheet)?;
+	let new_sheet = new.sheet(sheet)?;
+
+	let old_header = old_sheet.header()?;
+	let new_header = new_sheet.header()?;
+	let old_languages = old_sheet.languages()?;
+	let new_languages = new_sheet.languages()?;
+	let column_counts = (old_header.columns().len(), new_header.columns().len());
+
+	let mut old_rows = BTreeMap::new();
+	for &language in &old_languages {
+		for_each_row(&old_sheet, &old_header, language, |key, row| {
+			old_rows.insert(key, row);
+			Ok(())
+		})?;
+	}
+
+	let mut diff = SheetDiff {
+		sheet: sheet.to_string(),
+		header: diff_header(&old_header, &new_header, (&old_languages, &new_languages)),
+		added_rows: vec![],
+		removed_rows: vec![],
+		changed_rows: vec![],
+	};
+
+	for &language in &new_languages {
+		for_each_row(&new_sheet, &new_header, language, |key, new_row| {
+			let old_row = match old_rows.remove(&key) {
+				Some(row) => row,
+				None => {
+					diff.added_rows.push(key);
+					return Ok(());
+				}
+			};
+
+			let fields = diff_row(&old_row, &new_row, column_counts)?;
+			if !fields.is_empty() {
+				diff.changed_rows.push(RowDiff { row: key, fields });
+			}
+			Ok(())
+		})?;
+	}
+
+	diff.added_rows.sort_unstable();
+	diff.changed_rows.sort_unstable_by_key(|row| row.row);
+	diff.removed_rows = old_rows.into_keys().collect();
+
+	Ok(diff)
+}
+
+// Walk every (sub)row declared by the sheet's pages in the specified language.
+// Row IDs within a page may be sparse, so missing rows are skipped - any other
+// failure to read a row is returned.
+fn for_each_row(
+	sheet: &Sheet<&str>,
+	header: &exh::ExcelHeader,
+	language: Language,
+	mut visit: impl FnMut(RowKey, Row) -> Result<()>,
+) -> Result<()> {
+	let mut options = sheet.with();
+	options.language(language);
+
+	for page in header.pages() {
+		let end = page.start_id().saturating_add(page.row_count());
+		for row_id in page.start_id()..end {
+			for subrow_id in 0..=u16::MAX {
+				let row = match options.subrow(row_id, subrow_id) {
+					Ok(row) => row,
+					Err(Error::NotFound(ErrorValue::Row { .. })) => break,
+					Err(error) => return Err(error),
+				};
+
+				let key = RowKey {
+					row_id,
+					subrow_id,
+					language,
+				};
+				visit(key, row)?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+fn diff_header(
+	old: &exh::ExcelHeader,
+	new: &exh::ExcelHeader,
+	(old_languages, new_languages): (&[Language], &[Language]),
+) -> Option<HeaderDiff> {
+	let column = |definition: &exh::ColumnDefinition| Column {
+		kind: definition.kind(),
+		offset: definition.offset(),
+	};
+
+	let column_count = old.columns().len().max(new.columns().len());
+	let columns = (0..column_count)
+		.filter_map(|index| {
+			let old = old.columns().get(index).map(column);
+			let new = new.columns().get(index).map(column);
+			(old != new).then_some(ColumnDiff { index, old, new })
+		})
+		.collect::<Vec<_>>();
+
+	let pages = |header: &exh::ExcelHeader| {
+		header
+			.pages()
+			.iter()
+			.map(|page| Page {
+				start_id: page.start_id(),
+				row_count: page.row_count(),
+			})
+			.collect::<Vec<_>>()
+	};
+
+	let diff = HeaderDiff {
+		kind: Change::between(old.kind(), new.kind()),
+		columns,
+		pages: Change::between(pages(old), pages(new)),
+		languages: Change::between(old_languages.to_vec(), new_languages.to_vec()),
+	};
+
+	match diff.kind.is_none()
+		&& diff.columns.is_empty()
+		&& diff.pages.is_none()
+		&& diff.languages.is_none()
+	{
+		true => None,
+		false => Some(diff),
+	}
+}
+
+fn diff_row(
+	old: &Row,
+	new: &Row,
+	(old_count, new_count): (usize, usize),
+) -> Result<Vec<FieldDiff>> {
+	let mut fields = vec![];
+	for column in 0..old_count.max(new_count) {
+		// Columns only present on one side are always considered changed.
+		let old = match column < old_count {
+			true => Some(Value::from(old.field(column)?)),
+			false => None,
+		};
+		let new = match column < new_count {
+			true => Some(Value::from(new.field(column)?)),
+			false => None,
+		};
+
+		let same = match (&old, &new) {
+			(Some(old), Some(new)) => old.same(new),
+			_ => false,
+		};
+
+		if !same {
+			fields.push(FieldDiff { column, old, new });
+		}
+	}
+
+	Ok(fields)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+	use std::{collections::HashMap, io, sync::Arc};
+
+	use crate::{
+		error::{Error, ErrorValue},
+		file::exh::ColumnKind,
+		FileStream, Ironworks, Resource,
+	};
+
+	use super::{super::path, *};
+
+	/// A default-kind sheet with a single page and no languages. Every column is
+	/// four bytes wide, with row values listed in column order.
+	pub struct TestSheet<'a> {
+		pub name: &'a str,
+		pub columns: &'a [ColumnKind],
+		pub rows: &'a [(u32, &'a [u32])],
+	}
+
+	/// Encode the files for a database containing the provided sheets.
+	pub fn files(sheets: &[TestSheet]) -> Vec<(String, Vec<u8>)> {
+		let mut list = b"EXLT\r\n".to_vec();
+		let mut files = vec![];
+		for sheet in sheets {
+			list.extend_from_slice(format!("{},0\r\n", sheet.name).as_bytes());
+			files.push((path::exh(sheet.name), exh(sheet, &[Language::None])));
+			files.push((path::exd(sheet.name, 0, Language::None), exd(sheet)));
+		}
+		files.push((path::exl().to_string(), list));
+		files
+	}
+
+	fn exh(sheet: &TestSheet, languages: &[Language]) -> Vec<u8> {
+		let row_size = u16::try_from(sheet.columns.len() * 4).unwrap();
+		let row_count = sheet.rows.iter().map(|(id, _)| id + 1).max().unwrap_or(0);
+		let column_count = sheet.columns.len().try_into().unwrap();
+		let language_count = languages.len().try_into().unwrap();
+
+		let mut bytes = b"EXHF".to_vec();
+		for value in [3, row_size, column_count, 1, language_count] {
+			bytes.extend_from_slice(&u16::to_be_bytes(value));
+		}
+		bytes.extend_from_slice(&[0, 0, 0, 1, 0, 0]);
+		bytes.extend_from_slice(&row_count.to_be_bytes());
+		bytes.extend_from_slice(&[0; 8]);
+		for (index, kind) in sheet.columns.iter().enumerate() {
+			bytes.extend_from_slice(&u16::from(*kind).to_be_bytes());
+			bytes.extend_from_slice(&u16::try_from(index * 4).unwrap().to_be_bytes());
+		}
+		bytes.extend_from_slice(&0u32.to_be_bytes());
+		bytes.extend_from_slice(&row_count.to_be_bytes());
+		for language in languages {
+			bytes.extend_from_slice(&[u8::from(*language), 0]);
+		}
+		bytes
+	}
+
+	fn exd(sheet: &TestSheet) -> Vec<u8> {
+		const HEADER_SIZE: usize = 32;
+		let index_size = sheet.rows.len() * 8;
+
+		let mut index = vec![];
+		let mut data = vec![];
+		for (id, values) in sheet.rows {
+			let offset = HEADER_SIZE + index_size + data.len();
+			index.extend_from_slice(&id.to_be_bytes());
+			index.extend_from_slice(&u32::try_from(offset).unwrap().to_be_bytes());
+
+			data.extend_from_slice(&u32::try_from(values.len() * 4).unwrap().to_be_bytes());
+			data.extend_from_slice(&1u16.to_be_bytes());
+			for value in values.iter() {
+				data.extend_from_slice(&value.to_be_bytes());
+			}
+		}
+
+		let mut bytes = b"EXDF\0\x02\0\0".to_vec();
+		bytes.extend_from_slice(&u32::try_from(index_size).unwrap().to_be_bytes());
+		bytes.extend_from_slice(&[0; 20]);
+		bytes.extend(index);
+		bytes.extend(data);
+		bytes
+	}
+
+	struct TestResource(HashMap<String, Vec<u8>>);
+
+	impl Resource for TestResource {
+		fn version(&self, _path: &str) -> Result<String> {
+			Ok("test".into())
+		}
+
+		fn file(&self, path: &str) -> Result<Box<dyn FileStream>> {
+			match self.0.get(path) {
+				Some(bytes) => Ok(Box::new(io::Cursor::new(bytes.clone()))),
+				None => Err(Error::NotFound(ErrorValue::Path(path.into()))),
+			}
+		}
+	}
+
+	fn excel(sheets: &[TestSheet]) -> Excel<'static> {
+		let resource = TestResource(files(sheets).into_iter().collect());
+		Excel::new(Arc::new(Ironworks::new().with_resource(resource)))
+	}
+
+	/// Encode the files for a database containing a single sheet, with each
+	/// localised version of the sheet's data. The header is taken from the first
+	/// version.
+	fn localised_files(sheets: &[(Language, TestSheet)]) -> HashMap<String, Vec<u8>> {
+		let (_, header) = &sheets[0];
+		let languages = sheets
+			.iter()
+			.map(|(language, _)| *language)
+			.collect::<Vec<_>>();
+
+		let mut files = HashMap::from([
+			(
+				path::exl().to_string(),
+				format!("EXLT\r\n{},0\r\n", header.name).into_bytes(),
+			),
+			(path::exh(header.name), exh(header, &languages)),
+		]);
+		for (language, sheet) in sheets {
+			files.insert(path::exd(sheet.name, 0, *language), exd(sheet));
+		}
+		files
+	}
+
+	fn localised_excel(sheets: &[(Language, TestSheet)]) -> Excel<'static> {
+		let resource = TestResource(localised_files(sheets));
+		Excel::new(Arc::new(Ironworks::new().with_resource(resource)))
+	}
+
+	const COLUMNS: &[ColumnKind] = &[ColumnKind::UInt32, ColumnKind::UInt32];
+
+	fn column(kind: ColumnKind, offset: u16) -> Option<Column> {
+		Some(Column { kind, offset })
+	}
+
+	fn key(row_id: u32) -> RowKey {
+		localised_key(row_id, Language::None)
+	}
+
+	fn localised_key(row_id: u32, language: Language) -> RowKey {
+		RowKey {
+			row_id,
+			subrow_id: 0,
+			language,
+		}
+	}
+
+	#[test]
+	fn unchanged() {
+		let sheets = [TestSheet {
+			name: "Sheet",
+			columns: COLUMNS,
+			rows: &[(0, &[1, 2]), (1, &[3, 4])],
+		}];
+
+		let diff = excel(&sheets).diff(&excel(&sheets)).unwrap();
+		assert!(diff.added_sheets.is_empty());
+		assert!(diff.removed_sheets.is_empty());
+		assert!(diff.changed_sheets.is_empty());
+	}
+
+	#[test]
+	fn sheet_changes() {
+		let sheet = |name, value| TestSheet {
+			name,
+			columns: COLUMNS,
+			rows: match value {
+				0 => &[(0, &[0, 0])],
+				_ => &[(0, &[1, 1])],
+			},
+		};
+		let old = excel(&[sheet("A", 0), sheet("B", 0), sheet("C", 0)]);
+		let new = excel(&[sheet("B", 1), sheet("C", 1), sheet("D", 0)]);
+
+		let diff = old.diff(&new).unwrap();
+		assert_eq!(diff.added_sheets, ["D"]);
+		assert_eq!(diff.removed_sheets, ["A"]);
+		let changed = diff
+			.changed_sheets
+			.iter()
+			.map(|sheet| sheet.sheet.as_str())
+			.collect::<Vec<_>>();
+		assert_eq!(changed, ["B", "C"]);
+
+		// Limiting the compared sheets still reports additions and removals.
+		let diff = old.diff_sheets(&new, ["C", "A"]).unwrap();
+		assert_eq!(diff.added_sheets, ["D"]);
+		assert_eq!(diff.removed_sheets, ["A"]);
+		assert_eq!(diff.changed_sheets.len(), 1);
+		assert_eq!(diff.changed_sheets[0].sheet, "C");
+	}
+
+	#[test]
+	fn row_changes() {
+		let old = excel(&[TestSheet {
+			name: "Sheet",
+			columns: COLUMNS,
+			rows: &[(0, &[1, 2]), (1, &[3, 4]), (2, &[5, 6])],
+		}]);
+		let new = excel(&[TestSheet {
+			name: "Sheet",
+			columns: COLUMNS,
+			rows: &[(0, &[1, 2]), (1, &[3, 5]), (3, &[7, 8])],
+		}]);
+
+		let mut diff = old.diff(&new).unwrap();
+		assert_eq!(diff.changed_sheets.len(), 1);
+		let sheet = diff.changed_sheets.remove(0);
+
+		assert_eq!(sheet.added_rows, [key(3)]);
+		assert_eq!(sheet.removed_rows, [key(2)]);
+		assert_eq!(sheet.changed_rows.len(), 1);
+		let row = &sheet.changed_rows[0];
+		assert_eq!(row.row, key(1));
+		assert_eq!(row.fields.len(), 1);
+		assert_eq!(
+			(row.fields[0].column, &row.fields[0].old, &row.fields[0].new),
+			(1, &Some(Value::U32(4)), &Some(Value::U32(5)))
+		);
+
+		// The only header change is the page growing to fit the new row.
+		let header = sheet.header.unwrap();
+		assert_eq!(header.kind, None);
+		assert!(header.columns.is_empty());
+		assert_eq!(header.languages, None);
+		let page = |row_count| Page {
+			start_id: 0,
+			row_count,
+		};
+		assert_eq!(
+			header.pages,
+			Some(Change {
+				old: vec![page(3)],
+				new: vec![page(4)]
+			})
+		);
+	}
+
+	#[test]
+	fn column_changes() {
+		let old = excel(&[TestSheet {
+			name: "Sheet",
+			columns: COLUMNS,
+			rows: &[(0, &[1, 2])],
+		}]);
+		let new = excel(&[TestSheet {
+			name: "Sheet",
+			columns: &[ColumnKind::Int32, ColumnKind::UInt32, ColumnKind::UInt32],
+			rows: &[(0, &[1, 2, 3])],
+		}]);
+
+		let mut diff = old.diff(&new).unwrap();
+		let sheet = diff.changed_sheets.remove(0);
+
+		let columns = sheet.header.unwrap().columns;
+		let columns = columns
+			.iter()
+			.map(|column| (column.index, column.old, column.new))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			columns,
+			[
+				(
+					0,
+					column(ColumnKind::UInt32, 0),
+					column(ColumnKind::Int32, 0)
+				),
+				(2, None, column(ColumnKind::UInt32, 8)),
+			]
+		);
+
+		// Fields are compared by index, so a change in kind is a change in value.
+		let fields = sheet.changed_rows[0]
+			.fields
+			.iter()
+			.map(|field| (field.column, field.old.clone(), field.new.clone()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			fields,
+			[
+				(0, Some(Value::U32(1)), Some(Value::I32(1))),
+				(2, None, Some(Value::U32(3))),
+			]
+		);
+		assert!(sheet.added_rows.is_empty());
+		assert!(sheet.removed_rows.is_empty());
+	}
+
+	#[test]
+	fn localised_rows() {
+		let sheet = |rows| TestSheet {
+			name: "Sheet",
+			columns: COLUMNS,
+			rows,
+		};
+		let old = localised_excel(&[
+			(Language::Japanese, sheet(&[(0, &[1, 2])])),
+			(Language::English, sheet(&[(0, &[1, 2])])),
+		]);
+		let new = localised_excel(&[
+			(Language::Japanese, sheet(&[(0, &[1, 3]), (1, &[4, 5])])),
+			(Language::English, sheet(&[(0, &[1, 2]), (1, &[4, 5])])),
+		]);
+
+		let mut diff = old.diff(&new).unwrap();
+		assert_eq!(diff.changed_sheets.len(), 1);
+		let sheet = diff.changed_sheets.remove(0);
+
+		assert_eq!(
+			sheet.added_rows,
+			[
+				localised_key(1, Language::Japanese),
+				localised_key(1, Language::English)
+			]
+		);
+		assert!(sheet.removed_rows.is_empty());
+		assert_eq!(sheet.changed_rows.len(), 1);
+		assert_eq!(
+			sheet.changed_rows[0].row,
+			localised_key(0, Language::Japanese)
+		);
+		assert_eq!(sheet.header.unwrap().languages, None);
+	}
+
+	#[test]
+	fn missing_page() {
+		let sheet = || TestSheet {
+			name: "Sheet",
+			columns: COLUMNS,
+			rows: &[(0, &[1, 2])],
+		};
+		let old = localised_excel(&[(Language::Japanese, sheet()), (Language::English, sheet())]);
+
+		let mut files =
+			localised_files(&[(Language::Japanese, sheet()), (Language::English, sheet())]);
+		files.remove(&path::exd("Sheet", 0, Language::Japanese));
+		let new = Excel::new(Arc::new(
+			Ironworks::new().with_resource(TestResource(files)),
+		));
+
+		// Pages that fail to read are reported, rather than treated as missing rows.
+		assert!(matches!(
+			old.diff(&new),
+			Err(Error::NotFound(ErrorValue::Path(_)))
+		));
+	}
+
+	#[test]
+	fn value_nan_equality() {
+		assert!(Value::F32(f32::NAN).same(&Value::F32(f32::NAN)));
+		assert!(!Value::F32(0.0).same(&Value::F32(-0.0)));
+		assert!(!Value::U8(1).same(&Value::U16(1)));
+	}
+
+	#[test]
+	fn change_between() {
+		assert_eq!(Change::between(1, 1), None);
+		assert_eq!(Change::between(1, 2), Some(Change { old: 1, new: 2 }));
+	}
+}
@@ -1,6 +1,7 @@
 //! Tools for working with the Excel database format.
 
 mod borrowed;
+pub(crate) mod diff;
 mod excel;
 mod field;
 mod language;
@@ -10,6 +11,10 @@ mod row;
 mod sheet;
 
 pub use {
+	diff::{
+		Change, Column, ColumnDiff, ExcelDiff, FieldDiff, HeaderDiff, Page, RowDiff, RowKey,
+		SheetDiff, Value,
+	},
 	excel::{Excel, ExcelOptions},
 	field::Field,
 	language::Language,
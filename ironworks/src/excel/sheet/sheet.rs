@@ -69,6 +69,32 @@ impl<'i, S: SheetMetadata> Sheet<'i, S> {
 		Ok(columns)
 	}
 
+	/// Fetch metadata for all data pages in this sheet.
+	pub fn pages(&self) -> Result<Vec<exh::PageDefinition>> {
+		let pages = self.header()?.pages().clone();
+		Ok(pages)
+	}
+
+	/// Get the total number of rows in this sheet. For sheets with subrows, this
+	/// counts rows, not subrows.
+	pub fn row_count(&self) -> Result<u32> {
+		let row_count = self.header()?.row_count();
+		Ok(row_count)
+	}
+
+	/// Get the languages this sheet contains data for, in ascending order. Sheets
+	/// without localised data will report only [`Language::None`].
+	pub fn languages(&self) -> Result<Vec<Language>> {
+		let mut languages = self
+			.header()?
+			.languages()
+			.iter()
+			.filter_map(|&language| Language::try_from(language).ok())
+			.collect::<Vec<_>>();
+		languages.sort_by_key(|&language| u8::from(language));
+		Ok(languages)
+	}
+
 	/// Create a row options builder for this sheet.
 	pub fn with(&'i self) -> RowOptions<'i, S> {
 		RowOptions::new(self)
@@ -131,7 +157,7 @@ impl<'i, S: SheetMetadata> Sheet<'i, S> {
 			.map_err(|error| Error::Invalid(row_error_value(), error.to_string()))
 	}
 
-	pub(super) fn header(&self) -> Result<Arc<exh::ExcelHeader>> {
+	pub(crate) fn header(&self) -> Result<Arc<exh::ExcelHeader>> {
 		self.cache.header.try_get_or_insert(|| {
 			let path = path::exh(&self.sheet_metadata.name());
 			self.ironworks.file(&path)
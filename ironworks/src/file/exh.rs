@@ -37,8 +37,10 @@ pub struct ExcelHeader {
 	kind: SheetKind,
 
 	// unknown3: u16,
+	/// Total number of rows across all pages of this sheet.
 	#[br(pad_before = 2)]
-	_row_count: u32,
+	#[get_copy = "pub"]
+	row_count: u32,
 
 	// unknown4: [u32; 2],
 	/// Column definitions for rows in this sheet.
@@ -72,6 +74,7 @@ impl File for ExcelHeader {
 /// The kind of sheet.
 #[binread]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[br(repr = u8)]
 pub enum SheetKind {
 	/// Unknown kind. Will be treated equivalently to Default.
@@ -104,6 +107,7 @@ pub struct ColumnDefinition {
 #[allow(missing_docs)]
 #[binread]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[br(big, repr = u16)]
 #[repr(u16)]
 pub enum ColumnKind {
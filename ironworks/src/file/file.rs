@@ -1,3 +1,5 @@
+use std::io::{Seek, Write};
+
 use crate::{error::Result, FileStream};
 
 /// A file that can be read from ironworks.
@@ -13,3 +15,9 @@ impl File for Vec<u8> {
 		Ok(buffer)
 	}
 }
+
+/// A file that can be written back out to its raw byte representation.
+pub trait WritableFile: File {
+	/// Write the raw byte representation of this file to the provided writer.
+	fn write(&self, writer: impl Write + Seek) -> Result<()>;
+}
@@ -1,8 +1,15 @@
-use std::sync::Arc;
+use std::{
+	io::{Seek, Write},
+	sync::Arc,
+};
 
-use binrw::BinRead;
+use binrw::{BinRead, BinWriterExt};
 
-use crate::{error::Result, file::File, FileStream};
+use crate::{
+	error::{Error, ErrorValue, Result},
+	file::{File, WritableFile},
+	FileStream,
+};
 
 use super::{
 	model::{Lod, Model},
@@ -22,6 +29,14 @@ impl File for ModelContainer {
 	}
 }
 
+impl WritableFile for ModelContainer {
+	fn write(&self, mut writer: impl Write + Seek) -> Result<()> {
+		self.file.validate()?;
+		writer.write_le(self.file.as_ref())?;
+		Ok(())
+	}
+}
+
 impl ModelContainer {
 	// TODO: consider how variants will work
 	// TODO: some stuff doesn't have models at lower lods - should that be exposed at this level?
@@ -33,4 +48,103 @@ impl ModelContainer {
 			level,
 		}
 	}
+
+	/// Get mutable access to the raw vertex buffer for the specified LOD. Models
+	/// previously retrieved from this container will not observe changes.
+	pub fn vertex_buffer_mut(&mut self, level: Lod) -> Result<&mut [u8]> {
+		let lod = usize::from(level);
+		let file = Arc::make_mut(&mut self.file);
+		let (offset, size) = (file.vertex_offset[lod], file.vertex_buffer_size[lod]);
+		buffer_mut(&mut file.data, offset, size, file.data_offset, "vertex")
+	}
+
+	/// Get mutable access to the raw index buffer for the specified LOD. Models
+	/// previously retrieved from this container will not observe changes.
+	pub fn index_buffer_mut(&mut self, level: Lod) -> Result<&mut [u8]> {
+		let lod = usize::from(level);
+		let file = Arc::make_mut(&mut self.file);
+		let (offset, size) = (file.index_offset[lod], file.index_buffer_size[lod]);
+		buffer_mut(&mut file.data, offset, size, file.data_offset, "index")
+	}
+}
+
+// Buffer offsets and sizes are read from file data, and may be malformed.
+fn buffer_mut<'a>(
+	data: &'a mut [u8],
+	offset: u32,
+	size: u32,
+	data_offset: u64,
+	name: &str,
+) -> Result<&'a mut [u8]> {
+	let length = data.len();
+	u64::from(offset)
+		.checked_sub(data_offset)
+		.and_then(|start| usize::try_from(start).ok())
+		.and_then(|start| Some(start..start.checked_add(usize::try_from(size).ok()?)?))
+		.and_then(|range| data.get_mut(range))
+		.ok_or_else(|| {
+			Error::Invalid(
+				ErrorValue::Other(format!("model {name} buffer")),
+				format!("buffer of {size} at {offset} exceeds data of {length} from {data_offset}"),
+			)
+		})
+}
+
+#[cfg(test)]
+mod test {
+	use std::{io::Cursor, sync::Arc};
+
+	use crate::file::{File, WritableFile};
+
+	use super::{super::model::test::quad, Lod, ModelContainer};
+
+	fn write(container: &ModelContainer) -> Vec<u8> {
+		let mut cursor = Cursor::new(vec![]);
+		container.write(&mut cursor).unwrap();
+		cursor.into_inner()
+	}
+
+	#[test]
+	fn round_trip() {
+		let bytes = write(&ModelContainer {
+			file: Arc::new(quad()),
+		});
+		let container = ModelContainer::read(Cursor::new(bytes.clone())).unwrap();
+		assert_eq!(container.file.meshes.len(), 1);
+		assert_eq!(container.file.vertex_declarations[0].elements.len(), 1);
+		assert_eq!(write(&container), bytes);
+	}
+
+	#[test]
+	fn round_trip_modified() {
+		let bytes = write(&ModelContainer {
+			file: Arc::new(quad()),
+		});
+		let mut container = ModelContainer::read(Cursor::new(bytes)).unwrap();
+		container.index_buffer_mut(Lod::High).unwrap()[0] = 9;
+
+		let modified = ModelContainer::read(Cursor::new(write(&container))).unwrap();
+		let indices = modified.model(Lod::High).meshes()[0].indices().unwrap();
+		assert_eq!(indices, [9, 1, 2, 0, 2, 3]);
+	}
+
+	#[test]
+	fn oversized_section() {
+		let mut file = quad();
+		file.attribute_name_offsets = vec![0; 0x10000];
+		let container = ModelContainer {
+			file: Arc::new(file),
+		};
+		assert!(container.write(Cursor::new(vec![])).is_err());
+	}
+
+	#[test]
+	fn buffer_out_of_bounds() {
+		let mut file = quad();
+		file.index_buffer_size[0] = u32::MAX;
+		let mut container = ModelContainer {
+			file: Arc::new(file),
+		};
+		assert!(container.index_buffer_mut(Lod::High).is_err());
+	}
 }
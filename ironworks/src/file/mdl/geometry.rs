@@ -0,0 +1,70 @@
+use std::ops::Range;
+
+use super::mesh::{Mesh, VertexAttribute};
+
+/// Final geometry of a mesh, after shapes and attribute masks have been applied.
+#[derive(Debug)]
+pub struct Geometry {
+	/// The mesh this geometry was built from.
+	pub mesh: Mesh,
+	/// Indices of vertices within the mesh, in a triangle list topology.
+	pub indices: Vec<u16>,
+	/// Vertex attributes for all vertices in the mesh. Vertices introduced by
+	/// shapes are stored alongside the base vertices, and will be referenced
+	/// by `indices` as required.
+	pub attributes: Vec<VertexAttribute>,
+}
+
+pub fn substitute_index(indices: &mut [u16], offset: u16, value: u16) {
+	// Shape values pointing outside the mesh are ignored rather than failing the
+	// entire mesh - the game does not appear to validate these either.
+	if let Some(index) = indices.get_mut(usize::from(offset)) {
+		*index = value;
+	}
+}
+
+pub fn attributes_enabled(required_mask: u32, enabled_mask: u32) -> bool {
+	required_mask & !enabled_mask == 0
+}
+
+pub fn collect_ranges(indices: &[u16], ranges: impl Iterator<Item = Range<usize>>) -> Vec<u16> {
+	ranges
+		.filter_map(|range| indices.get(range))
+		.flatten()
+		.copied()
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn substitute() {
+		let mut indices = vec![0, 1, 2, 2, 1, 3];
+		substitute_index(&mut indices, 1, 4);
+		substitute_index(&mut indices, 4, 4);
+		assert_eq!(indices, vec![0, 4, 2, 2, 4, 3]);
+	}
+
+	#[test]
+	fn substitute_out_of_bounds() {
+		let mut indices = vec![0, 1, 2];
+		substitute_index(&mut indices, 3, 4);
+		assert_eq!(indices, vec![0, 1, 2]);
+	}
+
+	#[test]
+	fn attribute_mask() {
+		assert!(attributes_enabled(0b000, 0b000));
+		assert!(attributes_enabled(0b010, 0b011));
+		assert!(!attributes_enabled(0b110, 0b011));
+	}
+
+	#[test]
+	fn masked_ranges() {
+		let indices = vec![0, 1, 2, 3, 4, 5, 6, 7, 8];
+		let output = collect_ranges(&indices, [0..3, 6..9].into_iter());
+		assert_eq!(output, vec![0, 1, 2, 6, 7, 8]);
+	}
+}
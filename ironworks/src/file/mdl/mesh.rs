@@ -1,14 +1,15 @@
 use std::{
+	collections::HashSet,
 	io::{Cursor, Read, Seek, SeekFrom},
 	sync::Arc,
 };
 
-use binrw::{BinRead, NullString, VecArgs};
+use binrw::{BinRead, VecArgs};
 use half::f16;
 
-use crate::error::Result;
+use crate::error::{Error, ErrorValue, Result};
 
-use super::{model::Lod, structs};
+use super::{geometry, model::Lod, structs};
 
 // TODO: improve the debug output of these things
 /// A single mesh within a model.
@@ -29,13 +30,7 @@ impl Mesh {
 	pub fn material(&self) -> Result<String> {
 		let mesh = &self.file.meshes[self.mesh_index];
 		let name_offset = self.file.material_name_offsets[usize::from(mesh.material_index)];
-
-		// todo: this logic should probably be abstracted in the structs impl, and the buffer hidden?
-		let mut cursor = Cursor::new(&self.file.string_buffer);
-		cursor.set_position(name_offset.into());
-
-		let name = NullString::read(&mut cursor)?.to_string();
-		Ok(name)
+		Ok(self.file.string_at(name_offset)?)
 	}
 
 	// TODO: iterator?
@@ -61,6 +56,124 @@ impl Mesh {
 		Ok(indices)
 	}
 
+	/// Indices of vertices within the mesh, with the vertex substitutions of the
+	/// enabled shapes applied, and any submeshes masked out by attributes that
+	/// are not enabled omitted. Names not present on the model are ignored.
+	pub fn masked_indices(
+		&self,
+		shapes: &HashSet<&str>,
+		attributes: &HashSet<&str>,
+	) -> Result<Vec<u16>> {
+		let mut indices = self.indices()?;
+
+		for (offset, value) in self.shape_values(shapes)? {
+			geometry::substitute_index(&mut indices, offset, value);
+		}
+
+		// Meshes without any submeshes can't be masked.
+		let mesh = &self.file.meshes[self.mesh_index];
+		if mesh.sub_mesh_count == 0 {
+			return Ok(indices);
+		}
+
+		let enabled_mask = self.attribute_mask(attributes)?;
+		let submeshes = get_range(
+			&self.file.submeshes,
+			usize::from(mesh.sub_mesh_index),
+			usize::from(mesh.sub_mesh_count),
+			"submeshes",
+		)?;
+		let ranges = submeshes
+			.iter()
+			.filter(|submesh| {
+				geometry::attributes_enabled(submesh.attribute_index_mask, enabled_mask)
+			})
+			.map(|submesh| {
+				// Submesh offsets are relative to the LOD's index buffer, rather than the mesh.
+				let range = submesh
+					.index_offset
+					.checked_sub(mesh.start_index)
+					.and_then(|offset| {
+						let start = usize::try_from(offset).ok()?;
+						let end = start.checked_add(usize::try_from(submesh.index_count).ok()?)?;
+						Some(start..end)
+					});
+
+				range.ok_or_else(|| {
+					Error::Invalid(
+						ErrorValue::Other("model submesh".into()),
+						format!(
+							"index range of {} from {} is outside mesh starting at {}",
+							submesh.index_count, submesh.index_offset, mesh.start_index
+						),
+					)
+				})
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(geometry::collect_ranges(&indices, ranges.into_iter()))
+	}
+
+	fn shape_values(&self, shapes: &HashSet<&str>) -> Result<Vec<(u16, u16)>> {
+		let mesh = &self.file.meshes[self.mesh_index];
+		let level = usize::from(self.level);
+
+		let mut values = vec![];
+		for shape in &self.file.shapes {
+			if !shapes.contains(self.file.string_at(shape.string_offset)?.as_str()) {
+				continue;
+			}
+
+			// Each shape has a set of shape meshes per LOD, only one of which (if any)
+			// will target this mesh.
+			let shape_meshes = get_range(
+				&self.file.shape_meshes,
+				usize::from(shape.shape_mesh_start_index[level]),
+				usize::from(shape.shape_mesh_count[level]),
+				"shape meshes",
+			)?
+			.iter()
+			.filter(|shape_mesh| shape_mesh.start_index == mesh.start_index);
+
+			for shape_mesh in shape_meshes {
+				let shape_values = get_range(
+					&self.file.shape_values,
+					usize::try_from(shape_mesh.shape_value_offset).unwrap(),
+					usize::try_from(shape_mesh.shape_value_count).unwrap(),
+					"shape values",
+				)?;
+				values.extend(shape_values.iter().map(|value| (value.offset, value.value)));
+			}
+		}
+
+		Ok(values)
+	}
+
+	fn attribute_mask(&self, attributes: &HashSet<&str>) -> Result<u32> {
+		self.file
+			.attribute_name_offsets
+			.iter()
+			.enumerate()
+			.try_fold(0u32, |mask, (index, offset)| -> Result<_> {
+				let name = self.file.string_at(*offset)?;
+				if !attributes.contains(name.as_str()) {
+					return Ok(mask);
+				}
+
+				let bit = u32::try_from(index)
+					.ok()
+					.and_then(|index| 1u32.checked_shl(index))
+					.ok_or_else(|| {
+						Error::Invalid(
+							ErrorValue::Other("model attributes".into()),
+							format!("attribute index {index} exceeds mask width of 32"),
+						)
+					})?;
+
+				Ok(mask | bit)
+			})
+	}
+
 	// TODO: fn to get a specific attr?
 	// TODO: iterator?
 	/// Get the vertex attributes for all vertices in the mesh.
@@ -68,7 +181,7 @@ impl Mesh {
 		let mesh = &self.file.meshes[self.mesh_index];
 
 		// Get the elements for this mesh's vertices.
-		let elements = &self.file.vertex_declarations[self.mesh_index].0;
+		let elements = &self.file.vertex_declarations[self.mesh_index].elements;
 
 		// Vertices are stored across multipe streams of data - set up a cursor for each.
 		let mut streams = (0..usize::from(mesh.vertex_stream_count))
@@ -119,6 +232,22 @@ impl Mesh {
 	}
 }
 
+// Offsets and counts are read from file data, and may be malformed.
+fn get_range<'a, T>(items: &'a [T], start: usize, count: usize, name: &str) -> Result<&'a [T]> {
+	start
+		.checked_add(count)
+		.and_then(|end| items.get(start..end))
+		.ok_or_else(|| {
+			Error::Invalid(
+				ErrorValue::Other(format!("model {name}")),
+				format!(
+					"range of {count} from {start} exceeds available {}",
+					items.len()
+				),
+			)
+		})
+}
+
 fn read_values<R, F, O>(
 	offsets: impl Iterator<Item = u64>,
 	reader: &mut R,
@@ -1,12 +1,14 @@
 //! Structs and utilities for parsing .mdl files.
 
 mod container;
+mod geometry;
 mod mesh;
 mod model;
 mod structs;
 
 pub use {
 	container::ModelContainer,
+	geometry::Geometry,
 	mesh::{Mesh, VertexAttribute, VertexValues},
 	model::{Lod, Model},
 	structs::VertexAttributeKind,
@@ -1,8 +1,10 @@
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
 use num_enum::IntoPrimitive;
 
-use super::{mesh::Mesh, structs};
+use crate::error::Result;
+
+use super::{geometry::Geometry, mesh::Mesh, structs};
 
 // TODO: consider if it makes sense to keep Lod around as it's enum repr for anything beyond user facing api
 /// Level of detail.
@@ -55,6 +57,43 @@ impl Model {
 			.collect()
 	}
 
+	/// Names of the shapes that may be applied to this model.
+	pub fn shapes(&self) -> Result<Vec<String>> {
+		self.file
+			.shapes
+			.iter()
+			.map(|shape| Ok(self.file.string_at(shape.string_offset)?))
+			.collect()
+	}
+
+	/// Names of the attributes that may be used to mask submeshes of this model.
+	pub fn attributes(&self) -> Result<Vec<String>> {
+		self.file
+			.attribute_name_offsets
+			.iter()
+			.map(|offset| Ok(self.file.string_at(*offset)?))
+			.collect()
+	}
+
+	/// Build the final geometry of each mesh in this model, with the specified
+	/// shapes applied, and submeshes with attributes outside the enabled set hidden.
+	pub fn geometry(
+		&self,
+		shapes: &HashSet<&str>,
+		attributes: &HashSet<&str>,
+	) -> Result<Vec<Geometry>> {
+		self.meshes()
+			.into_iter()
+			.map(|mesh| {
+				Ok(Geometry {
+					indices: mesh.masked_indices(shapes, attributes)?,
+					attributes: mesh.attributes()?,
+					mesh,
+				})
+			})
+			.collect()
+	}
+
 	fn get_ranges(&self) -> Vec<(MeshKind, u16, u16)> {
 		let level = usize::from(self.level);
 		let current_lod = &self.file.lods[level];
@@ -129,3 +168,141 @@ pub enum MeshKind {
 	MaterialChange,
 	CrestChange,
 }
+
+#[cfg(test)]
+pub(super) mod test {
+	use std::{collections::HashSet, io::Cursor, sync::Arc};
+
+	use binrw::BinWriterExt;
+
+	use crate::file::mdl::{
+		mesh::VertexValues,
+		structs::{self, VertexAttributeKind, VertexElement, VertexFormat},
+	};
+
+	use super::{Lod, Model};
+
+	// A single quad of two triangles, where the second triangle is masked by the
+	// attribute `atr_a`, and the shape `shp_a` swaps the second index to vertex 3.
+	// Most structures have private fields, so can't be built with initializers.
+	#[allow(clippy::field_reassign_with_default)]
+	pub fn quad() -> structs::File {
+		let mut file = structs::File::default();
+
+		let vertices = [[0f32, 0., 0.], [1., 0., 0.], [1., 1., 0.], [0., 1., 0.]];
+		file.data = vertices
+			.iter()
+			.flatten()
+			.flat_map(|v| v.to_le_bytes())
+			.collect();
+		file.vertex_buffer_size[0] = file.data.len().try_into().unwrap();
+		file.index_offset[0] = file.vertex_buffer_size[0];
+		file.data
+			.extend([0u16, 1, 2, 0, 2, 3].iter().flat_map(|i| i.to_le_bytes()));
+		file.index_buffer_size[0] = 12;
+
+		file.vertex_declarations = vec![structs::VertexDeclaration::new(vec![VertexElement::new(
+			0,
+			0,
+			VertexFormat::Single3,
+			VertexAttributeKind::Position,
+		)])];
+
+		file.string_buffer = b"atr_a\0shp_a\0".to_vec();
+		file.attribute_name_offsets = vec![0];
+
+		file.lods[0].mesh_count = 1;
+
+		let mut mesh = structs::Mesh::default();
+		mesh.vertex_count = 4;
+		mesh.index_count = 6;
+		mesh.sub_mesh_count = 2;
+		mesh.vertex_buffer_stride[0] = 12;
+		mesh.vertex_stream_count = 1;
+		file.meshes = vec![mesh];
+
+		let mut first = structs::Submesh::default();
+		first.index_count = 3;
+		let mut second = structs::Submesh::default();
+		second.index_offset = 3;
+		second.index_count = 3;
+		second.attribute_index_mask = 0b1;
+		file.submeshes = vec![first, second];
+
+		file.shapes = vec![structs::Shape {
+			string_offset: 6,
+			shape_mesh_start_index: [0; 3],
+			shape_mesh_count: [1, 0, 0],
+		}];
+		file.shape_meshes = vec![structs::ShapeMesh {
+			start_index: 0,
+			shape_value_count: 1,
+			shape_value_offset: 0,
+		}];
+		file.shape_values = vec![structs::ShapeValue {
+			offset: 1,
+			value: 3,
+		}];
+
+		// Buffer offsets are relative to the start of the file, after the header.
+		let mut cursor = Cursor::new(vec![]);
+		cursor.write_le(&file).unwrap();
+		file.data_offset = cursor.position() - u64::try_from(file.data.len()).unwrap();
+		let header_size = u32::try_from(file.data_offset).unwrap();
+		file.vertex_offset[0] += header_size;
+		file.index_offset[0] += header_size;
+
+		file
+	}
+
+	fn model(file: structs::File) -> Model {
+		Model {
+			file: Arc::new(file),
+			level: Lod::High,
+		}
+	}
+
+	#[test]
+	fn geometry_unmodified() {
+		let geometry = model(quad())
+			.geometry(&HashSet::new(), &HashSet::from(["atr_a"]))
+			.unwrap();
+		assert_eq!(geometry.len(), 1);
+		assert_eq!(geometry[0].indices, [0, 1, 2, 0, 2, 3]);
+		assert!(matches!(
+			&geometry[0].attributes[0].values,
+			VertexValues::Vector3(values) if values.len() == 4 && values[3] == [0., 1., 0.]
+		));
+	}
+
+	#[test]
+	fn geometry_shapes_and_attributes() {
+		let geometry = model(quad())
+			.geometry(&HashSet::from(["shp_a", "unknown"]), &HashSet::new())
+			.unwrap();
+		assert_eq!(geometry[0].indices, [0, 3, 2]);
+	}
+
+	#[test]
+	fn geometry_malformed_submeshes() {
+		let mut file = quad();
+		file.meshes[0].sub_mesh_count = 3;
+		assert!(model(file)
+			.geometry(&HashSet::new(), &HashSet::new())
+			.is_err());
+	}
+
+	#[test]
+	fn geometry_malformed_shapes() {
+		let mut file = quad();
+		file.shape_meshes[0].shape_value_offset = 1;
+		assert!(model(file.clone())
+			.geometry(&HashSet::from(["shp_a"]), &HashSet::new())
+			.is_err());
+
+		file.shapes[0].shape_mesh_start_index[0] = u16::MAX;
+		assert!(model(file)
+			.geometry(&HashSet::from(["shp_a"]), &HashSet::new())
+			.is_err());
+	}
+}
@@ -1,18 +1,23 @@
 // TODO: REMOVE
 #![allow(dead_code, clippy::identity_op)]
 
-use std::io::{Read, Seek};
+use std::io::{Cursor, Read, Seek, Write};
 
-use binrw::{binread, until_eof, BinRead, BinResult, ReadOptions};
+use binrw::{
+	binrw, until_eof, BinRead, BinResult, BinWrite, NullString, ReadOptions, WriteOptions,
+};
 use derivative::Derivative;
 use modular_bitfield::bitfield;
 
+use crate::{error::Result, utility::check_size};
+
 const MAX_LODS: usize = 3;
 
 // TODO: this is currently inlining a bunch of structures - look into if it's worth pulling it apart at all.
-#[binread]
-#[br(little)]
-#[derive(Derivative)]
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Derivative)]
+#[cfg_attr(test, derive(Default))]
 #[derivative(Debug)]
 pub struct File {
 	// Model file header
@@ -20,33 +25,35 @@ pub struct File {
 	stack_size: u32,
 	runtime_size: u32,
 	#[br(temp)]
+	#[bw(calc = vertex_declarations.len().try_into().unwrap())]
 	vertex_declaration_count: u16,
 	material_count: u16,
 	pub vertex_offset: [u32; MAX_LODS],
 	pub index_offset: [u32; MAX_LODS],
-	vertex_buffer_size: [u32; MAX_LODS],
-	index_buffer_size: [u32; MAX_LODS],
+	pub vertex_buffer_size: [u32; MAX_LODS],
+	pub index_buffer_size: [u32; MAX_LODS],
 	lod_count: u8,
 
 	#[br(map = to_bool)]
+	#[bw(map = from_bool)]
 	enable_index_buffer_streaming: bool,
 
 	#[br(map = to_bool)]
+	#[bw(map = from_bool)]
 	enable_edge_geometry: bool,
 
 	// padding: u8
 
 	// Loose data
-	#[br(
-    pad_before = 1,
-    count = vertex_declaration_count,
-  )]
+	#[brw(pad_before = 1)]
+	#[br(count = vertex_declaration_count)]
 	pub vertex_declarations: Vec<VertexDeclaration>,
 
-	#[br(temp)]
 	string_count: u16,
 	// padding: u16,
-	#[br(pad_before = 2, temp)]
+	#[br(temp)]
+	#[bw(calc = string_buffer.len().try_into().unwrap())]
+	#[brw(pad_before = 2)]
 	string_size: u32,
 	// TODO: lumina eagerly builds a map of offset -> string. worth doing?
 	#[br(count = string_size)]
@@ -56,30 +63,41 @@ pub struct File {
 	// TODO: this has name conflicts with the file header - they seem to always be equiv, either skip one of them or break up the struct
 	radius: f32,
 	#[br(temp)]
+	#[bw(calc = meshes.len().try_into().unwrap())]
 	mesh_count: u16,
 	#[br(temp)]
+	#[bw(calc = attribute_name_offsets.len().try_into().unwrap())]
 	attribute_count: u16,
 	#[br(temp)]
+	#[bw(calc = submeshes.len().try_into().unwrap())]
 	submesh_count: u16,
 	#[br(temp)]
+	#[bw(calc = material_name_offsets.len().try_into().unwrap())]
 	material_count_2: u16,
 	#[br(temp)]
+	#[bw(calc = bone_name_offsets.len().try_into().unwrap())]
 	bone_count: u16,
 	#[br(temp)]
+	#[bw(calc = bone_tables.len().try_into().unwrap())]
 	bone_table_count: u16,
 	#[br(temp)]
+	#[bw(calc = shapes.len().try_into().unwrap())]
 	shape_count: u16,
 	#[br(temp)]
+	#[bw(calc = shape_meshes.len().try_into().unwrap())]
 	shape_mesh_count: u16,
 	#[br(temp)]
+	#[bw(calc = shape_values.len().try_into().unwrap())]
 	shape_value_count: u16,
 	lod_count_2: u8,
 
 	flags1: Flags1,
 
 	#[br(temp)]
+	#[bw(calc = element_ids.len().try_into().unwrap())]
 	element_id_count: u16,
 	#[br(temp)]
+	#[bw(calc = terrain_shadow_meshes.len().try_into().unwrap())]
 	terrain_shadow_mesh_count: u8,
 
 	flags2: Flags2,
@@ -88,6 +106,7 @@ pub struct File {
 	shadow_clip_out_distance: f32,
 	unknown4: u16,
 	#[br(temp)]
+	#[bw(calc = terrain_shadow_submeshes.len().try_into().unwrap())]
 	terrain_shadow_submesh_count: u16,
 	unknown5: u8,
 	bg_change_material_index: u8,
@@ -95,7 +114,7 @@ pub struct File {
 	unknown6: u8,
 	unknown7: u16,
 	unknown8: u16,
-	#[br(pad_after = 6)]
+	#[brw(pad_after = 6)]
 	unknown9: u16,
 
 	// padding: [u8; 6],
@@ -110,13 +129,13 @@ pub struct File {
 	pub meshes: Vec<Mesh>,
 
 	#[br(count = attribute_count)]
-	attribute_name_offsets: Vec<u32>,
+	pub attribute_name_offsets: Vec<u32>,
 
 	#[br(count = terrain_shadow_mesh_count)]
 	terrain_shadow_meshes: Vec<TerrainShadowMesh>,
 
 	#[br(count = submesh_count)]
-	submeshes: Vec<Submesh>,
+	pub submeshes: Vec<Submesh>,
 
 	#[br(count = terrain_shadow_submesh_count)]
 	terrain_shadow_submeshes: Vec<TerrainShadowSubmesh>,
@@ -131,23 +150,27 @@ pub struct File {
 	bone_tables: Vec<BoneTable>,
 
 	#[br(count = shape_count)]
-	shapes: Vec<Shape>,
+	pub shapes: Vec<Shape>,
 
 	#[br(count = shape_mesh_count)]
-	shape_meshes: Vec<ShapeMesh>,
+	pub shape_meshes: Vec<ShapeMesh>,
 
 	#[br(count = shape_value_count)]
-	shape_values: Vec<ShapeValue>,
+	pub shape_values: Vec<ShapeValue>,
 
 	#[br(temp)]
+	#[bw(calc = (submesh_bone_map.len() * 2).try_into().unwrap())]
 	submesh_bone_map_size: u32,
 	#[br(count = submesh_bone_map_size / 2)]
 	submesh_bone_map: Vec<u16>,
 
 	// lmao what
 	#[br(temp)]
+	#[bw(calc = padding.len().try_into().unwrap())]
 	padding_size: u8,
-	#[br(pad_before = padding_size)]
+	#[br(count = padding_size)]
+	padding: Vec<u8>,
+
 	bounding_boxes: BoundingBox,
 	model_bounding_boxes: BoundingBox,
 	water_bounding_boxes: BoundingBox,
@@ -158,6 +181,7 @@ pub struct File {
 	// ??????
 	// this is going to be a collection of smaller buffers - i'll probably be better off with manual accessors to fetch specific parts of it
 	#[br(parse_with = current_position)]
+	#[bw(ignore)]
 	pub data_offset: u64,
 
 	#[br(parse_with = until_eof)]
@@ -165,6 +189,42 @@ pub struct File {
 	pub data: Vec<u8>,
 }
 
+impl File {
+	/// Read a null-terminated string from the string buffer at the given offset.
+	pub fn string_at(&self, offset: u32) -> BinResult<String> {
+		let mut cursor = Cursor::new(&self.string_buffer);
+		cursor.set_position(offset.into());
+		Ok(NullString::read(&mut cursor)?.to_string())
+	}
+
+	/// Check that all sections will fit within the sizes recorded in the header.
+	pub fn validate(&self) -> Result<()> {
+		check_size::<u16>("model vertex declarations", self.vertex_declarations.len())?;
+		check_size::<u32>("model string buffer", self.string_buffer.len())?;
+		check_size::<u16>("model meshes", self.meshes.len())?;
+		check_size::<u16>("model attributes", self.attribute_name_offsets.len())?;
+		check_size::<u16>("model submeshes", self.submeshes.len())?;
+		check_size::<u16>("model materials", self.material_name_offsets.len())?;
+		check_size::<u16>("model bones", self.bone_name_offsets.len())?;
+		check_size::<u16>("model bone tables", self.bone_tables.len())?;
+		check_size::<u16>("model shapes", self.shapes.len())?;
+		check_size::<u16>("model shape meshes", self.shape_meshes.len())?;
+		check_size::<u16>("model shape values", self.shape_values.len())?;
+		check_size::<u16>("model element IDs", self.element_ids.len())?;
+		check_size::<u8>(
+			"model terrain shadow meshes",
+			self.terrain_shadow_meshes.len(),
+		)?;
+		check_size::<u16>(
+			"model terrain shadow submeshes",
+			self.terrain_shadow_submeshes.len(),
+		)?;
+		check_size::<u32>("model submesh bone map", self.submesh_bone_map.len() * 2)?;
+		check_size::<u8>("model padding", self.padding.len())?;
+		Ok(())
+	}
+}
+
 fn current_position<R: Read + Seek>(reader: &mut R, _: &ReadOptions, _: ()) -> BinResult<u64> {
 	Ok(reader.stream_position()?)
 }
@@ -173,8 +233,17 @@ fn to_bool(value: u8) -> bool {
 	value != 0
 }
 
-#[derive(Debug)]
-pub struct VertexDeclaration(pub Vec<VertexElement>);
+fn from_bool(value: &bool) -> u8 {
+	u8::from(*value)
+}
+
+#[derive(Clone, Debug)]
+pub struct VertexDeclaration {
+	pub elements: Vec<VertexElement>,
+	// Unused trailing elements, retained to allow lossless writing.
+	unused: Vec<VertexElement>,
+}
+
 impl BinRead for VertexDeclaration {
 	type Args = ();
 
@@ -187,30 +256,76 @@ impl BinRead for VertexDeclaration {
 		// invalid data - remove them.
 		// TODO: This eagerly reads all 17 - can use parse_with and skip some reading.
 		let raw = <[VertexElement; 17]>::read_options(reader, options, args)?;
-		let filtered = raw
-			.into_iter()
-			.take_while(|element| element.stream != 255)
-			.collect::<Vec<_>>();
-		Ok(Self(filtered))
+		let count = raw
+			.iter()
+			.position(|element| element.stream == 255)
+			.unwrap_or(raw.len());
+		let mut elements = Vec::from(raw);
+		let unused = elements.split_off(count);
+		Ok(Self { elements, unused })
 	}
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
+#[cfg(test)]
+impl VertexDeclaration {
+	pub fn new(elements: Vec<VertexElement>) -> Self {
+		let terminator =
+			VertexElement::new(255, 0, VertexFormat::None, VertexAttributeKind::Position);
+		Self {
+			unused: vec![terminator; 17 - elements.len()],
+			elements,
+		}
+	}
+}
+
+impl BinWrite for VertexDeclaration {
+	type Args = ();
+
+	fn write_options<W: Write + Seek>(
+		&self,
+		writer: &mut W,
+		options: &WriteOptions,
+		args: Self::Args,
+	) -> BinResult<()> {
+		self.elements.write_options(writer, options, args)?;
+		self.unused.write_options(writer, options, args)
+	}
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
 pub struct VertexElement {
 	// todo names
 	pub stream: u8,
 	pub offset: u8,
 	pub format: VertexFormat,
 	pub attribute: VertexAttributeKind,
-	#[br(pad_after = 3)]
+	#[brw(pad_after = 3)]
 	usage_index: u8,
 }
 
-#[binread]
-#[br(repr = u8)]
-#[derive(Debug)]
+#[cfg(test)]
+impl VertexElement {
+	pub fn new(
+		stream: u8,
+		offset: u8,
+		format: VertexFormat,
+		attribute: VertexAttributeKind,
+	) -> Self {
+		Self {
+			stream,
+			offset,
+			format,
+			attribute,
+			usage_index: 0,
+		}
+	}
+}
+
+#[binrw]
+#[brw(repr = u8)]
+#[derive(Clone, Copy, Debug)]
 pub enum VertexFormat {
 	None = 0,
 	Single3 = 2,
@@ -223,8 +338,8 @@ pub enum VertexFormat {
 
 /// The kind of data represented by a vertex attribute.
 #[allow(missing_docs)]
-#[binread]
-#[br(repr = u8)]
+#[binrw]
+#[brw(repr = u8)]
 #[derive(Clone, Copy, Debug)]
 pub enum VertexAttributeKind {
 	Position = 0,
@@ -238,9 +353,11 @@ pub enum VertexAttributeKind {
 }
 
 #[bitfield]
-#[binread]
-#[derive(Debug)]
+#[binrw]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(test, derive(Default))]
 #[br(map = Self::from_bytes)]
+#[bw(map = |flags: &Self| flags.into_bytes())]
 struct Flags1 {
 	dust_occlusion_enabled: bool,
 	show_occlusion_enabled: bool,
@@ -253,9 +370,11 @@ struct Flags1 {
 }
 
 #[bitfield]
-#[binread]
-#[derive(Debug)]
+#[binrw]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(test, derive(Default))]
 #[br(map = Self::from_bytes)]
+#[bw(map = |flags: &Self| flags.into_bytes())]
 struct Flags2 {
 	unknown2: bool,
 	bg_uv_scroll_enabled: bool,
@@ -267,9 +386,9 @@ struct Flags2 {
 	unknown3: bool,
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
 struct ElementId {
 	element_id: u32,
 	// name?
@@ -280,9 +399,10 @@ struct ElementId {
 
 // TODO: index/count pattern is super repetetive - abstract?
 //       ...it's not contiguous, and spread across two structs - could be fiddly. maybe a parse=skip or something that post-processes it into a vec or w/e?
-#[binread]
-#[br(little)]
-#[derive(Debug)]
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
+#[cfg_attr(test, derive(Default))]
 pub struct Lod {
 	pub mesh_index: u16,
 	pub mesh_count: u16,
@@ -306,9 +426,9 @@ pub struct Lod {
 	index_data_offset: u32,
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
 pub struct ExtraLod {
 	pub light_shaft_mesh_index: u16,
 	pub light_shaft_mesh_count: u16,
@@ -332,17 +452,18 @@ pub struct ExtraLod {
 	unknown12: u16,
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
+#[cfg_attr(test, derive(Default))]
 pub struct Mesh {
 	pub vertex_count: u16,
 	//padding:u16,
-	#[br(pad_before = 2)]
+	#[brw(pad_before = 2)]
 	pub index_count: u32,
 	pub material_index: u16,
-	sub_mesh_index: u16,
-	sub_mesh_count: u16,
+	pub sub_mesh_index: u16,
+	pub sub_mesh_count: u16,
 	bone_table_index: u16,
 	pub start_index: u32,
 	// TODO: the 3 here is the no. of streams
@@ -351,20 +472,21 @@ pub struct Mesh {
 	pub vertex_stream_count: u8,
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
-struct Submesh {
-	index_offset: u32,
-	index_count: u32,
-	attribute_index_mask: u32,
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
+#[cfg_attr(test, derive(Default))]
+pub struct Submesh {
+	pub index_offset: u32,
+	pub index_count: u32,
+	pub attribute_index_mask: u32,
 	bone_start_index: u16,
 	bone_count: u16,
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
 struct TerrainShadowMesh {
 	index_count: u32,
 	start_index: u32,
@@ -372,14 +494,14 @@ struct TerrainShadowMesh {
 	vertex_count: u16,
 	sub_mesh_index: u16,
 	sub_mesh_count: u16,
-	#[br(pad_after = 1)]
+	#[brw(pad_after = 1)]
 	vertex_buffer_stride: u8,
 	// padding: u8,
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
 struct TerrainShadowSubmesh {
 	index_offset: u32,
 	index_count: u32,
@@ -387,45 +509,51 @@ struct TerrainShadowSubmesh {
 	unknown2: u16,
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
 struct BoneTable {
 	bone_index: [u16; 64],
-	#[br(pad_after = 3)]
+	#[brw(pad_after = 3)]
 	bone_count: u8,
 	// padding: [u8; 3],
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
-struct Shape {
-	string_offset: u32,
-	shape_mesh_start_index: [u16; MAX_LODS],
-	shape_mesh_count: [u16; MAX_LODS],
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
+#[cfg_attr(test, derive(Default))]
+pub struct Shape {
+	pub string_offset: u32,
+	pub shape_mesh_start_index: [u16; MAX_LODS],
+	pub shape_mesh_count: [u16; MAX_LODS],
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
-struct ShapeMesh {
-	start_index: u32,
-	shape_value_count: u32,
-	shape_value_offset: u32,
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
+#[cfg_attr(test, derive(Default))]
+pub struct ShapeMesh {
+	pub start_index: u32,
+	pub shape_value_count: u32,
+	pub shape_value_offset: u32,
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
-struct ShapeValue {
-	offset: u16,
-	value: u16,
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
+#[cfg_attr(test, derive(Default))]
+pub struct ShapeValue {
+	// Index within the owning mesh's index buffer to replace.
+	pub offset: u16,
+	// Vertex index to substitute in.
+	pub value: u16,
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
+#[cfg_attr(test, derive(Default))]
 struct BoundingBox {
 	min: [f32; 4],
 	max: [f32; 4],
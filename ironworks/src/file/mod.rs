@@ -22,7 +22,9 @@ pub mod patch;
 pub mod pbd;
 #[cfg(feature = "sklb")]
 pub mod sklb;
+#[cfg(feature = "stm")]
+pub mod stm;
 #[cfg(feature = "tex")]
 pub mod tex;
 
-pub use file::File;
+pub use file::{File, WritableFile};
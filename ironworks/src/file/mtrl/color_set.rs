@@ -0,0 +1,189 @@
+use half::f16;
+
+use crate::error::{Error, ErrorValue, Result};
+
+const MAX_DYE_TEMPLATE: u16 = 0x7FF;
+
+/// A single row of a material's colour set table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorSetRow {
+	/// Diffuse colour, as linear RGB.
+	pub diffuse: [f32; 3],
+	/// Strength of the specular highlight.
+	pub specular_strength: f32,
+	/// Specular colour, as linear RGB.
+	pub specular: [f32; 3],
+	/// Glossiness of the surface.
+	pub gloss: f32,
+	/// Emissive colour, as linear RGB.
+	pub emissive: [f32; 3],
+	/// Index of the detail tile texture used by this row.
+	pub tile_index: u8,
+	/// 2x2 transformation matrix applied to detail tile UVs, laid out as
+	/// `[uu, uv, vu, vv]`.
+	pub tile_transform: [f32; 4],
+	/// Dye configuration for this row, if the material supports dyeing.
+	pub dye: Option<ColorSetDye>,
+}
+
+impl ColorSetRow {
+	pub(super) fn from_raw(raw: &[u16], dye: Option<u16>) -> Self {
+		let value = |index: usize| f16::from_bits(raw[index]).to_f32();
+		Self {
+			diffuse: [value(0), value(1), value(2)],
+			specular_strength: value(3),
+			specular: [value(4), value(5), value(6)],
+			gloss: value(7),
+			emissive: [value(8), value(9), value(10)],
+			// The tile index is stored as a float pre-divided by 64.
+			tile_index: (value(11) * 64.).round() as u8,
+			tile_transform: [value(12), value(13), value(14), value(15)],
+			dye: dye.map(ColorSetDye::from_raw),
+		}
+	}
+
+	pub(super) fn to_raw(self) -> [u16; 16] {
+		let [r, g, b] = self.diffuse;
+		let [sr, sg, sb] = self.specular;
+		let [er, eg, eb] = self.emissive;
+		let [uu, uv, vu, vv] = self.tile_transform;
+		[
+			r,
+			g,
+			b,
+			self.specular_strength,
+			sr,
+			sg,
+			sb,
+			self.gloss,
+			er,
+			eg,
+			eb,
+			f32::from(self.tile_index) / 64.,
+			uu,
+			uv,
+			vu,
+			vv,
+		]
+		.map(|value| f16::from_f32(value).to_bits())
+	}
+}
+
+/// Dye configuration for a colour set row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorSetDye {
+	/// Row of the staining template to read dyed values from.
+	pub template: u16,
+	/// Whether the diffuse colour is affected by dye.
+	pub diffuse: bool,
+	/// Whether the specular colour is affected by dye.
+	pub specular: bool,
+	/// Whether the emissive colour is affected by dye.
+	pub emissive: bool,
+	/// Whether the gloss value is affected by dye.
+	pub gloss: bool,
+	/// Whether the specular strength is affected by dye.
+	pub specular_strength: bool,
+}
+
+impl ColorSetDye {
+	fn from_raw(raw: u16) -> Self {
+		Self {
+			template: raw >> 5,
+			diffuse: raw & 0x01 != 0,
+			specular: raw & 0x02 != 0,
+			emissive: raw & 0x04 != 0,
+			gloss: raw & 0x08 != 0,
+			specular_strength: raw & 0x10 != 0,
+		}
+	}
+
+	pub(super) fn to_raw(self) -> Result<u16> {
+		// The template ID is packed into the upper 11 bits.
+		if self.template > MAX_DYE_TEMPLATE {
+			return Err(Error::Invalid(
+				ErrorValue::Other("material colour set dye".into()),
+				format!(
+					"template {} exceeds maximum of {MAX_DYE_TEMPLATE}",
+					self.template
+				),
+			));
+		}
+
+		let flags = [
+			self.diffuse,
+			self.specular,
+			self.emissive,
+			self.gloss,
+			self.specular_strength,
+		]
+		.iter()
+		.enumerate()
+		.fold(0, |flags, (bit, enabled)| {
+			flags | (u16::from(*enabled) << bit)
+		});
+		Ok((self.template << 5) | flags)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use half::f16;
+
+	use super::*;
+
+	fn raw_row() -> [u16; 16] {
+		[
+			1.0, 0.5, 0.25, 1.0, 0.0, 0.125, 1.0, 20.0, 0.0, 0.0, 0.0, 0.5, 16.0, 0.0, 0.0, 16.0,
+		]
+		.map(|value: f32| f16::from_f32(value).to_bits())
+	}
+
+	#[test]
+	fn decode_row() {
+		let row = ColorSetRow::from_raw(&raw_row(), None);
+		assert_eq!(row.diffuse, [1.0, 0.5, 0.25]);
+		assert_eq!(row.gloss, 20.0);
+		assert_eq!(row.tile_index, 32);
+		assert_eq!(row.tile_transform, [16.0, 0.0, 0.0, 16.0]);
+		assert_eq!(row.dye, None);
+	}
+
+	#[test]
+	fn decode_dye() {
+		let dye = ColorSetDye::from_raw((3 << 5) | 0b10101);
+		assert_eq!(
+			dye,
+			ColorSetDye {
+				template: 3,
+				diffuse: true,
+				specular: false,
+				emissive: true,
+				gloss: false,
+				specular_strength: true,
+			}
+		);
+	}
+
+	#[test]
+	fn round_trip() {
+		let raw = raw_row();
+		let dye = (3 << 5) | 0b01010;
+		let row = ColorSetRow::from_raw(&raw, Some(dye));
+		assert_eq!(row.to_raw(), raw);
+		assert_eq!(row.dye.unwrap().to_raw().unwrap(), dye);
+	}
+
+	#[test]
+	fn oversized_dye_template() {
+		let dye = ColorSetDye {
+			template: MAX_DYE_TEMPLATE + 1,
+			diffuse: false,
+			specular: false,
+			emissive: false,
+			gloss: false,
+			specular_strength: false,
+		};
+		assert!(dye.to_raw().is_err());
+	}
+}
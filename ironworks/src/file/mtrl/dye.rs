@@ -0,0 +1,107 @@
+use crate::{error::Result, file::stm::StainingTemplate};
+
+use super::{color_set::ColorSetRow, material::Material};
+
+impl Material {
+	/// Get the colour set of this material with the specified stain applied,
+	/// using values from the provided staining template file. Rows that are not
+	/// marked as dyeable, or values not provided by the template, are left
+	/// unchanged. Returns `None` if the material does not have a colour set.
+	pub fn dyed_color_set(
+		&self,
+		stain: u8,
+		templates: &StainingTemplate,
+	) -> Result<Option<Vec<ColorSetRow>>> {
+		let color_set = match self.color_set() {
+			Some(color_set) => color_set,
+			None => return Ok(None),
+		};
+
+		let rows = color_set
+			.iter()
+			.map(|row| dye_row(*row, stain, templates))
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(Some(rows))
+	}
+}
+
+fn dye_row(mut row: ColorSetRow, stain: u8, templates: &StainingTemplate) -> Result<ColorSetRow> {
+	let dye = match row.dye {
+		Some(dye) if dye.template != 0 => dye,
+		_ => return Ok(row),
+	};
+
+	let values = match templates.template(dye.template)?.stain(stain) {
+		Some(values) => values,
+		None => return Ok(row),
+	};
+
+	fn apply<T>(enabled: bool, target: &mut T, value: Option<T>) {
+		if let (true, Some(value)) = (enabled, value) {
+			*target = value;
+		}
+	}
+
+	apply(dye.diffuse, &mut row.diffuse, values.diffuse);
+	apply(dye.specular, &mut row.specular, values.specular);
+	apply(dye.emissive, &mut row.emissive, values.emissive);
+	apply(dye.gloss, &mut row.gloss, values.gloss);
+	apply(
+		dye.specular_strength,
+		&mut row.specular_strength,
+		values.specular_strength,
+	);
+
+	Ok(row)
+}
+
+#[cfg(test)]
+mod test {
+	use std::io::Cursor;
+
+	use half::f16;
+
+	use crate::file::{stm::StainingTemplate, File};
+
+	use super::super::material::test::{raw_material, read, to_bytes};
+
+	fn half(value: f32) -> u16 {
+		f16::from_f32(value).to_bits()
+	}
+
+	// Template 2, with a shared diffuse and gloss value, and no specular values.
+	fn templates() -> StainingTemplate {
+		let data =
+			[3, 3, 3, 4, 4]
+				.into_iter()
+				.chain([half(1.0), half(0.5), half(0.25), half(30.0)]);
+		let raw = [0u16, 0, 1, 0, 2, 0]
+			.into_iter()
+			.chain(data)
+			.flat_map(u16::to_le_bytes)
+			.collect::<Vec<_>>();
+		StainingTemplate::read(Cursor::new(raw)).unwrap()
+	}
+
+	#[test]
+	fn dye_color_set() {
+		let material = read(to_bytes(&raw_material()));
+		let original = material.color_set().unwrap();
+		let rows = material.dyed_color_set(5, &templates()).unwrap().unwrap();
+
+		// Rows dye diffuse and specular only - specular has no template values.
+		for (row, original) in rows.iter().zip(original) {
+			assert_eq!(row.diffuse, [1.0, 0.5, 0.25]);
+			assert_eq!(row.specular, original.specular);
+			assert_eq!(row.gloss, original.gloss);
+		}
+	}
+
+	#[test]
+	fn undyed_stain() {
+		let material = read(to_bytes(&raw_material()));
+		let rows = material.dyed_color_set(0, &templates()).unwrap().unwrap();
+		assert_eq!(rows, material.color_set().unwrap());
+	}
+}
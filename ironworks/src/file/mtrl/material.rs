@@ -1,21 +1,32 @@
-use std::{fmt, io::Cursor};
+use std::{
+	fmt,
+	io::{Cursor, Seek, Write},
+};
 
-use binrw::{BinRead, NullString};
+use binrw::{BinRead, BinWriterExt, NullString};
 use getset::{CopyGetters, Getters};
 
-use crate::{error::Result, file::File, FileStream};
+use crate::{
+	error::{Error, ErrorValue, Result},
+	file::{File, WritableFile},
+	FileStream,
+};
 
-use super::structs;
+use super::{
+	color_set::ColorSetRow,
+	structs::{self, COLOR_SET_ROWS, COLOR_SET_ROW_SIZE},
+};
 
 /// A material. Contains metadata to be used by shaders, and references to the
 /// shader and requisite assets used by the material.
 pub struct Material {
 	file: structs::Material,
 	shader: String,
-	// TODO: Color sets
-	// TODO: Shader keys
-	// TODO: Constants
-	// TODO: Shader values
+	uv_sets: Vec<VertexSet>,
+	color_sets: Vec<VertexSet>,
+	color_set: Option<Vec<ColorSetRow>>,
+	shader_keys: Vec<ShaderKey>,
+	constants: Vec<Constant>,
 	samplers: Vec<Sampler>,
 }
 
@@ -31,34 +42,127 @@ impl Material {
 		&self.shader
 	}
 
+	/// Named UV sets used by the material.
+	pub fn uv_sets(&self) -> &[VertexSet] {
+		&self.uv_sets
+	}
+
+	/// Named vertex colour sets used by the material.
+	pub fn color_sets(&self) -> &[VertexSet] {
+		&self.color_sets
+	}
+
+	/// Colour set table of the material, if it has one. Tables always contain
+	/// 16 rows.
+	pub fn color_set(&self) -> Option<&[ColorSetRow]> {
+		self.color_set.as_deref()
+	}
+
+	/// Mutable access to the colour set table of the material, if it has one.
+	pub fn color_set_mut(&mut self) -> Option<&mut [ColorSetRow]> {
+		self.color_set.as_deref_mut()
+	}
+
+	/// Shader keys used to select shader permutations for the material.
+	pub fn shader_keys(&self) -> &[ShaderKey] {
+		&self.shader_keys
+	}
+
+	/// Shader constants configured by the material.
+	pub fn constants(&self) -> &[Constant] {
+		&self.constants
+	}
+
+	/// Mutable access to the shader constants configured by the material.
+	pub fn constants_mut(&mut self) -> &mut [Constant] {
+		&mut self.constants
+	}
+
 	/// Texture samplers used by the material.
 	pub fn samplers(&self) -> &[Sampler] {
 		&self.samplers
 	}
+
+	/// Mutable access to the texture samplers used by the material.
+	pub fn samplers_mut(&mut self) -> &mut [Sampler] {
+		&mut self.samplers
+	}
 }
 
 // Construction logic.
 impl Material {
-	fn read_shader(file: &structs::Material) -> Result<String> {
+	fn read_string(file: &structs::Material, offset: u16) -> Result<String> {
 		let mut cursor = Cursor::new(&file.string_data);
-		cursor.set_position(file.shader_package_name_offset.into());
-		let shader = NullString::read(&mut cursor)?.to_string();
-		Ok(shader)
+		cursor.set_position(offset.into());
+		let string = NullString::read(&mut cursor)?.to_string();
+		Ok(string)
 	}
 
-	fn read_samplers(file: &structs::Material) -> Result<Vec<Sampler>> {
-		let mut cursor = Cursor::new(&file.string_data);
+	fn read_vertex_sets(
+		file: &structs::Material,
+		sets: &[structs::NamedSet],
+	) -> Result<Vec<VertexSet>> {
+		sets.iter()
+			.map(|set| {
+				Ok(VertexSet {
+					name: Material::read_string(file, set.name_offset)?,
+					index: set.index,
+				})
+			})
+			.collect()
+	}
+
+	fn read_color_set(file: &structs::Material) -> Option<Vec<ColorSetRow>> {
+		let info = file.color_set_info.as_ref()?;
 
+		let rows = (0..COLOR_SET_ROWS)
+			.map(|index| {
+				let start = index * COLOR_SET_ROW_SIZE;
+				let dye = file.color_set_dye_info.map(|dye| dye[index]);
+				ColorSetRow::from_raw(&info[start..start + COLOR_SET_ROW_SIZE], dye)
+			})
+			.collect();
+
+		Some(rows)
+	}
+
+	fn read_shader_keys(file: &structs::Material) -> Vec<ShaderKey> {
+		file.shader_keys
+			.iter()
+			.map(|key| ShaderKey {
+				category: key.category,
+				value: key.value,
+			})
+			.collect()
+	}
+
+	fn read_constants(file: &structs::Material) -> Vec<Constant> {
+		file.constants
+			.iter()
+			.map(|constant| {
+				// Offset and size are in bytes, shader values are f32.
+				let start = usize::from(constant.value_offset / 4);
+				let end = start + usize::from(constant.value_size / 4);
+
+				Constant {
+					id: constant.constant_id,
+					values: file.shader_values.get(start..end).unwrap_or(&[]).to_vec(),
+				}
+			})
+			.collect()
+	}
+
+	fn read_samplers(file: &structs::Material) -> Result<Vec<Sampler>> {
 		file.samplers
 			.iter()
 			.map(|sampler| {
 				let offset = &file.texture_offsets[usize::from(sampler.texture_index)];
-				cursor.set_position(offset.offset.into());
-				let texture = NullString::read(&mut cursor)?.to_string();
+				let texture = Material::read_string(file, offset.offset)?;
 
 				Ok(Sampler {
 					id: sampler.id,
 					// state: sampler.state,
+					texture_index: sampler.texture_index,
 					texture,
 				})
 			})
@@ -70,23 +174,229 @@ impl File for Material {
 	fn read(mut stream: impl FileStream) -> Result<Self> {
 		let file = structs::Material::read(&mut stream)?;
 		Ok(Material {
-			shader: Material::read_shader(&file)?,
+			shader: Material::read_string(&file, file.shader_package_name_offset)?,
+			uv_sets: Material::read_vertex_sets(&file, &file.uv_sets)?,
+			color_sets: Material::read_vertex_sets(&file, &file.color_sets)?,
+			color_set: Material::read_color_set(&file),
+			shader_keys: Material::read_shader_keys(&file),
+			constants: Material::read_constants(&file),
 			samplers: Material::read_samplers(&file)?,
 			file,
 		})
 	}
 }
 
+// Writing logic.
+impl Material {
+	fn build_file(&self) -> Result<structs::Material> {
+		let mut file = self.file.clone();
+
+		if let Some(rows) = &self.color_set {
+			Material::write_color_set(&mut file, rows)?;
+		}
+
+		for (raw, constant) in file.constants.iter().zip(&self.constants) {
+			let start = usize::from(raw.value_offset / 4);
+			if let Some(values) = file
+				.shader_values
+				.get_mut(start..start + constant.values.len())
+			{
+				values.copy_from_slice(&constant.values);
+			}
+		}
+
+		// Samplers may have been assigned new textures - if any have changed, the
+		// string table needs to be rebuilt.
+		let original_textures = file
+			.texture_offsets
+			.iter()
+			.map(|offset| Material::read_string(&file, offset.offset))
+			.collect::<Result<Vec<_>>>()?;
+		let mut textures = original_textures.clone();
+		let mut changed = vec![false; textures.len()];
+		for sampler in &self.samplers {
+			let index = usize::from(sampler.texture_index);
+			if sampler.texture == original_textures[index] {
+				continue;
+			}
+
+			// Samplers sharing a texture must agree on any change made to it.
+			if changed[index] && sampler.texture != textures[index] {
+				return Err(Error::Invalid(
+					ErrorValue::Other(format!("material texture {index}")),
+					format!(
+						"conflicting textures {:?} and {:?} assigned by samplers",
+						textures[index], sampler.texture
+					),
+				));
+			}
+
+			textures[index] = sampler.texture.clone();
+			changed[index] = true;
+		}
+		let textures_changed = changed.contains(&true);
+
+		if textures_changed {
+			Material::rebuild_strings(&mut file, &textures)?;
+		}
+
+		Ok(file)
+	}
+
+	fn write_color_set(file: &mut structs::Material, rows: &[ColorSetRow]) -> Result<()> {
+		// Encoding is lossy - only rows that have been modified are written back,
+		// leaving the original data of untouched rows intact.
+		let original = Material::read_color_set(file).unwrap_or_default();
+		let info = file
+			.color_set_info
+			.get_or_insert([0u16; COLOR_SET_ROWS * COLOR_SET_ROW_SIZE]);
+		for (index, row) in rows.iter().enumerate() {
+			if original.get(index) != Some(row) {
+				let start = index * COLOR_SET_ROW_SIZE;
+				info[start..start + COLOR_SET_ROW_SIZE].copy_from_slice(&row.to_raw());
+			}
+		}
+
+		let dye_changed = rows
+			.iter()
+			.enumerate()
+			.any(|(index, row)| original.get(index).map(|row| row.dye) != Some(row.dye));
+		if !dye_changed {
+			return Ok(());
+		}
+
+		let dye_info = file
+			.color_set_dye_info
+			.get_or_insert([0u16; COLOR_SET_ROWS]);
+		for (index, row) in rows.iter().enumerate() {
+			if original.get(index).map(|row| row.dye) != Some(row.dye) {
+				dye_info[index] = row.dye.map_or(Ok(0), |dye| dye.to_raw())?;
+			}
+		}
+
+		Ok(())
+	}
+
+	fn rebuild_strings(file: &mut structs::Material, textures: &[String]) -> Result<()> {
+		let mut string_data = Vec::<u8>::new();
+		let mut push_string = |string: &str| -> Result<u16> {
+			let offset = string_data.len().try_into().map_err(|_| {
+				Error::Invalid(
+					ErrorValue::Other("material string table".into()),
+					format!("offset of {string:?} exceeds maximum"),
+				)
+			})?;
+			string_data.extend_from_slice(string.as_bytes());
+			string_data.push(0);
+			Ok(offset)
+		};
+
+		for (offset, texture) in file.texture_offsets.iter_mut().zip(textures) {
+			offset.offset = push_string(texture)?;
+		}
+
+		let uv_set_names = Material::read_set_names(file, &file.uv_sets)?;
+		for (set, name) in file.uv_sets.iter_mut().zip(uv_set_names) {
+			set.name_offset = push_string(&name)?;
+		}
+
+		let color_set_names = Material::read_set_names(file, &file.color_sets)?;
+		for (set, name) in file.color_sets.iter_mut().zip(color_set_names) {
+			set.name_offset = push_string(&name)?;
+		}
+
+		let shader = Material::read_string(file, file.shader_package_name_offset)?;
+		file.shader_package_name_offset = push_string(&shader)?;
+
+		// String tables are padded to a 4-byte boundary.
+		string_data.resize((string_data.len() + 3) & !3, 0);
+
+		let old_size = i64::try_from(file.string_data.len()).unwrap();
+		let new_size = i64::try_from(string_data.len()).unwrap();
+		file.file_size = (i64::from(file.file_size) + new_size - old_size)
+			.try_into()
+			.map_err(|_| {
+				Error::Invalid(
+					ErrorValue::Other("material string table".into()),
+					format!("size {new_size} exceeds maximum file size"),
+				)
+			})?;
+		file.string_data = string_data;
+
+		Ok(())
+	}
+
+	fn read_set_names(file: &structs::Material, sets: &[structs::NamedSet]) -> Result<Vec<String>> {
+		sets.iter()
+			.map(|set| Material::read_string(file, set.name_offset))
+			.collect()
+	}
+}
+
+impl WritableFile for Material {
+	fn write(&self, mut writer: impl Write + Seek) -> Result<()> {
+		let file = self.build_file()?;
+		file.validate()?;
+		writer.write_le(&file)?;
+		Ok(())
+	}
+}
+
 impl fmt::Debug for Material {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		f.debug_struct("Material")
 			.field("version", &self.version())
 			.field("shader", &self.shader)
+			.field("uv_sets", &self.uv_sets)
+			.field("color_sets", &self.color_sets)
+			.field("color_set", &self.color_set)
+			.field("shader_keys", &self.shader_keys)
+			.field("constants", &self.constants)
 			.field("samplers", &self.samplers)
 			.finish()
 	}
 }
 
+/// A named vertex data set, such as a UV channel.
+#[derive(Debug, Getters, CopyGetters)]
+pub struct VertexSet {
+	/// Name of the set.
+	#[get = "pub"]
+	name: String,
+	/// Index of the set within the vertex data.
+	#[get_copy = "pub"]
+	index: u8,
+}
+
+/// Shader key, selecting a value for a category of shader permutations.
+#[derive(Debug, CopyGetters)]
+#[get_copy = "pub"]
+pub struct ShaderKey {
+	/// ID of the key category.
+	category: u32,
+	/// ID of the value selected for the category.
+	value: u32,
+}
+
+/// Shader constant, providing values to a shader parameter.
+#[derive(Debug, Getters, CopyGetters)]
+pub struct Constant {
+	/// ID of the constant. This is a CRC of the shader parameter's name.
+	#[get_copy = "pub"]
+	id: u32,
+	/// Values of the constant. Constants may span multiple values, i.e. a vec3
+	/// colour will contain 3 values.
+	#[get = "pub"]
+	values: Vec<f32>,
+}
+
+impl Constant {
+	/// Mutable access to the values of the constant.
+	pub fn values_mut(&mut self) -> &mut [f32] {
+		&mut self.values
+	}
+}
+
 /// Texture sampler for a material.
 #[derive(Debug, Getters, CopyGetters)]
 pub struct Sampler {
@@ -94,6 +404,7 @@ pub struct Sampler {
 	#[get_copy = "pub"]
 	id: u32,
 	// state: u32,
+	texture_index: u8,
 	texture: String,
 }
 
@@ -102,4 +413,176 @@ impl Sampler {
 	pub fn texture(&self) -> String {
 		self.texture.clone()
 	}
+
+	/// Set the path to the texture used by this sampler. Samplers sharing a
+	/// texture with this sampler will also be updated when the material is written.
+	pub fn set_texture(&mut self, texture: impl Into<String>) {
+		self.texture = texture.into();
+	}
+}
+
+#[cfg(test)]
+pub(super) mod test {
+	use std::io::Cursor;
+
+	use binrw::BinWriterExt;
+	use half::f16;
+
+	use crate::file::{File, WritableFile};
+
+	use super::{
+		structs::{self, COLOR_SET_ROWS, COLOR_SET_ROW_SIZE},
+		Material,
+	};
+
+	const STRINGS: &[u8] = b"a.tex\0b.tex\0uv0\0col0\0shader.shpk\0\0\0";
+
+	pub fn raw_material() -> structs::Material {
+		let mut color_set_info = [0u16; COLOR_SET_ROWS * COLOR_SET_ROW_SIZE];
+		for (index, value) in color_set_info.iter_mut().enumerate() {
+			// Tile index values that don't sit exactly on a multiple of 1/64, which
+			// are not preserved by decoding.
+			*value = f16::from_f32(index as f32 / 100.).to_bits();
+		}
+
+		structs::Material {
+			version: 0x1030000,
+			file_size: 0,
+			shader_package_name_offset: 21,
+			texture_offsets: vec![
+				structs::TextureOffset {
+					offset: 0,
+					flags: 0x8000,
+				},
+				structs::TextureOffset {
+					offset: 6,
+					flags: 0,
+				},
+			],
+			uv_sets: vec![structs::NamedSet {
+				name_offset: 12,
+				index: 0,
+				unknown1: 0,
+			}],
+			color_sets: vec![structs::NamedSet {
+				name_offset: 16,
+				index: 0,
+				unknown1: 0,
+			}],
+			string_data: STRINGS.to_vec(),
+			additional_data: vec![1, 2, 3, 4],
+			color_set_info: Some(color_set_info),
+			color_set_dye_info: Some([(2 << 5) | 0b11; COLOR_SET_ROWS]),
+			data_set_extra: vec![0xAA; 8],
+			unknown1: 0,
+			unknown2: 0,
+			shader_keys: vec![structs::ShaderKey {
+				category: 1,
+				value: 2,
+			}],
+			constants: vec![structs::Constant {
+				constant_id: 3,
+				value_offset: 0,
+				value_size: 12,
+			}],
+			samplers: vec![
+				structs::Sampler {
+					id: 4,
+					state: 5,
+					texture_index: 0,
+				},
+				structs::Sampler {
+					id: 6,
+					state: 7,
+					texture_index: 1,
+				},
+				structs::Sampler {
+					id: 8,
+					state: 9,
+					texture_index: 1,
+				},
+			],
+			shader_values: vec![0.1, 0.2, 0.3],
+		}
+	}
+
+	pub fn to_bytes(file: &structs::Material) -> Vec<u8> {
+		let mut cursor = Cursor::new(vec![]);
+		cursor.write_le(file).unwrap();
+		cursor.into_inner()
+	}
+
+	fn round_trip(material: &Material) -> Vec<u8> {
+		let mut cursor = Cursor::new(vec![]);
+		material.write(&mut cursor).unwrap();
+		cursor.into_inner()
+	}
+
+	pub fn read(bytes: Vec<u8>) -> Material {
+		Material::read(Cursor::new(bytes)).unwrap()
+	}
+
+	#[test]
+	fn round_trip_unmodified() {
+		let bytes = to_bytes(&raw_material());
+		let material = read(bytes.clone());
+		assert_eq!(material.samplers()[2].texture(), "b.tex");
+		assert_eq!(round_trip(&material), bytes);
+	}
+
+	#[test]
+	fn round_trip_modified() {
+		let mut material = read(to_bytes(&raw_material()));
+		material.color_set_mut().unwrap()[1].gloss = 2.;
+		material.constants_mut()[0].values_mut()[1] = 1.;
+		material.samplers_mut()[1].set_texture("c.tex");
+
+		let modified = read(round_trip(&material));
+
+		let original = read(to_bytes(&raw_material()));
+		let rows = modified.color_set().unwrap();
+		assert_eq!(rows[1].gloss, 2.);
+		assert_eq!(rows[0], original.color_set().unwrap()[0]);
+		assert_eq!(rows[2..], original.color_set().unwrap()[2..]);
+		assert_eq!(modified.constants()[0].values(), &[0.1, 1., 0.3]);
+
+		let textures = modified
+			.samplers()
+			.iter()
+			.map(|sampler| sampler.texture())
+			.collect::<Vec<_>>();
+		assert_eq!(textures, ["a.tex", "c.tex", "c.tex"]);
+		assert_eq!(modified.shader(), "shader.shpk");
+		assert_eq!(modified.uv_sets()[0].name(), "uv0");
+		assert_eq!(modified.color_sets()[0].name(), "col0");
+	}
+
+	#[test]
+	fn untouched_rows_preserved() {
+		let raw = raw_material();
+		let mut material = read(to_bytes(&raw));
+		material.color_set_mut().unwrap()[1].gloss = 2.;
+
+		let written = material.build_file().unwrap();
+		let (original, updated) = (raw.color_set_info.unwrap(), written.color_set_info.unwrap());
+		assert_eq!(original[..16], updated[..16]);
+		assert_ne!(original[16..32], updated[16..32]);
+		assert_eq!(original[32..], updated[32..]);
+		assert_eq!(raw.color_set_dye_info, written.color_set_dye_info);
+	}
+
+	#[test]
+	fn conflicting_samplers() {
+		let mut material = read(to_bytes(&raw_material()));
+		material.samplers_mut()[1].set_texture("c.tex");
+		material.samplers_mut()[2].set_texture("d.tex");
+		assert!(material.write(Cursor::new(vec![])).is_err());
+	}
+
+	#[test]
+	fn oversized_section() {
+		let mut material = read(to_bytes(&raw_material()));
+		material.file.additional_data = vec![0; 256];
+		assert!(material.write(Cursor::new(vec![])).is_err());
+	}
 }
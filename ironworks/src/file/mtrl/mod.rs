@@ -1,6 +1,12 @@
 //! Structs and utilities for parsing .mtrl files.
 
+mod color_set;
+#[cfg(feature = "stm")]
+mod dye;
 mod material;
 mod structs;
 
-pub use material::{Material, Sampler};
+pub use {
+	color_set::{ColorSetDye, ColorSetRow},
+	material::{Constant, Material, Sampler, ShaderKey, VertexSet},
+};
@@ -1,120 +1,169 @@
-use binrw::binread;
-use getset::CopyGetters;
+use binrw::binrw;
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
+use crate::{error::Result, utility::check_size};
+
+pub const COLOR_SET_ROWS: usize = 16;
+pub const COLOR_SET_ROW_SIZE: usize = 16;
+
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
 pub struct Material {
 	// Container header
 	pub version: u32,
-	_file_size: u16,
+	pub file_size: u16,
 	#[br(temp)]
+	#[bw(calc = data_set_size(color_set_info, color_set_dye_info, data_set_extra))]
 	data_set_size: u16,
 	#[br(temp)]
+	#[bw(calc = string_data.len().try_into().unwrap())]
 	string_table_size: u16,
 	pub shader_package_name_offset: u16,
 	#[br(temp)]
+	#[bw(calc = texture_offsets.len().try_into().unwrap())]
 	texture_count: u8,
 	#[br(temp)]
+	#[bw(calc = uv_sets.len().try_into().unwrap())]
 	uv_set_count: u8,
 	#[br(temp)]
+	#[bw(calc = color_sets.len().try_into().unwrap())]
 	color_set_count: u8,
 	#[br(temp)]
+	#[bw(calc = additional_data.len().try_into().unwrap())]
 	additional_data_size: u8,
 
 	#[br(count = texture_count)]
 	pub texture_offsets: Vec<TextureOffset>,
 
 	#[br(count = uv_set_count)]
-	_uv_color_sets: Vec<UvColorSet>,
+	pub uv_sets: Vec<NamedSet>,
 
 	#[br(count = color_set_count)]
-	_color_set_offsets: Vec<u32>,
+	pub color_sets: Vec<NamedSet>,
 
 	// TODO: can this be eagerly resolved?
-	#[br(
-    count = string_table_size,
-	  // TODO: unknown, seems to be a struct of some kind
-    pad_after = additional_data_size,
-  )]
+	#[br(count = string_table_size)]
 	pub string_data: Vec<u8>,
 
-	// TODO: Check this info, stems from TT
+	// TODO: unknown, seems to be a struct of some kind
+	#[br(count = additional_data_size)]
+	pub additional_data: Vec<u8>,
+
+	// Colour set table, stored as 16 rows of 16 half floats.
 	#[br(if(data_set_size > 0))]
-	_color_set_info: Option<[u16; 256]>,
+	pub color_set_info: Option<[u16; COLOR_SET_ROWS * COLOR_SET_ROW_SIZE]>,
+	// Dye flags and template for each row in the colour set table.
 	#[br(if(data_set_size > 512))]
-	_color_set_dye_info: Option<[u16; 16]>,
+	pub color_set_dye_info: Option<[u16; COLOR_SET_ROWS]>,
+	// Any data set content beyond the known tables, retained to allow lossless writing.
+	#[br(count = data_set_size.saturating_sub(self::data_set_size(&color_set_info, &color_set_dye_info, &[])))]
+	pub data_set_extra: Vec<u8>,
 
 	// Material header
 	#[br(temp)]
+	#[bw(calc = (shader_values.len() * 4).try_into().unwrap())]
 	shader_value_list_size: u16,
 	#[br(temp)]
+	#[bw(calc = shader_keys.len().try_into().unwrap())]
 	shader_key_count: u16,
 	#[br(temp)]
+	#[bw(calc = constants.len().try_into().unwrap())]
 	constant_count: u16,
 	#[br(temp)]
+	#[bw(calc = samplers.len().try_into().unwrap())]
 	sampler_count: u16,
-	_unknown1: u16,
-	_unknown2: u16,
+	pub unknown1: u16,
+	pub unknown2: u16,
 
 	#[br(count = shader_key_count)]
-	_shader_keys: Vec<ShaderKey>,
+	pub shader_keys: Vec<ShaderKey>,
 
 	#[br(count = constant_count)]
-	_constants: Vec<Constant>,
+	pub constants: Vec<Constant>,
 
 	#[br(count = sampler_count)]
 	pub samplers: Vec<Sampler>,
 
 	#[br(count = shader_value_list_size / 4)]
-	_shader_values: Vec<f32>,
+	pub shader_values: Vec<f32>,
 }
 
 // todo: actually u32?
-#[binread]
-#[br(little)]
-#[derive(Debug)]
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
 pub struct TextureOffset {
 	pub offset: u16,
 	// TODO: Unknown if actually flags.
-	_flags: u16,
+	pub flags: u16,
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
-struct UvColorSet {
-	_name_offset: u16,
-	_index: u16,
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
+pub struct NamedSet {
+	pub name_offset: u16,
+	pub index: u8,
+	pub unknown1: u8,
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug, CopyGetters)]
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
 pub struct ShaderKey {
-	_category: u32,
-	_value: u32,
+	pub category: u32,
+	pub value: u32,
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
 pub struct Constant {
-	_constant_id: u32,
-	// These seem to be byte offsets into the shader values?
-	// Size is a mult. of 4, seen 4 and 12 - assume that 12 is a vec3?
-	_value_offset: u16,
-	_value_size: u16,
+	pub constant_id: u32,
+	// Byte offset and size of the constant's value within the shader values.
+	pub value_offset: u16,
+	pub value_size: u16,
 }
 
-#[binread]
-#[br(little)]
-#[derive(Debug)]
+#[binrw]
+#[brw(little)]
+#[derive(Clone, Debug)]
 pub struct Sampler {
 	pub id: u32,
 	// TODO: bitfield, unknown fields.
 	pub state: u32,
-	#[br(pad_after = 3)]
+	#[brw(pad_after = 3)]
 	pub texture_index: u8,
 	// padding: [u8; 3].
 }
+
+impl Material {
+	/// Check that all sections will fit within the sizes recorded in the header.
+	pub fn validate(&self) -> Result<()> {
+		let tables_size = data_set_size(&self.color_set_info, &self.color_set_dye_info, &[]);
+		check_size::<u16>(
+			"material data set",
+			usize::from(tables_size) + self.data_set_extra.len(),
+		)?;
+		check_size::<u16>("material string table", self.string_data.len())?;
+		check_size::<u8>("material texture list", self.texture_offsets.len())?;
+		check_size::<u8>("material UV set list", self.uv_sets.len())?;
+		check_size::<u8>("material colour set list", self.color_sets.len())?;
+		check_size::<u8>("material additional data", self.additional_data.len())?;
+		check_size::<u16>("material shader value list", self.shader_values.len() * 4)?;
+		check_size::<u16>("material shader key list", self.shader_keys.len())?;
+		check_size::<u16>("material constant list", self.constants.len())?;
+		check_size::<u16>("material sampler list", self.samplers.len())?;
+		Ok(())
+	}
+}
+
+fn data_set_size(
+	color_set_info: &Option<[u16; COLOR_SET_ROWS * COLOR_SET_ROW_SIZE]>,
+	color_set_dye_info: &Option<[u16; COLOR_SET_ROWS]>,
+	extra: &[u8],
+) -> u16 {
+	let info_size = color_set_info.map_or(0, |info| info.len() * 2);
+	let dye_size = color_set_dye_info.map_or(0, |dye| dye.len() * 2);
+	(info_size + dye_size + extra.len()).try_into().unwrap()
+}
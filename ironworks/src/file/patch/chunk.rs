@@ -1,6 +1,6 @@
 use std::io::{Read, Seek};
 
-use binrw::{binread, BinRead, BinResult, ReadOptions};
+use binrw::{binread, binrw, BinRead, BinResult, ReadOptions};
 use getset::{CopyGetters, Getters};
 
 use super::command::{
@@ -40,13 +40,13 @@ pub enum Chunk {
 }
 
 /// Metadata about the .patch file and information it contains.
-#[binread]
-#[br(big)]
+#[binrw]
+#[brw(big)]
 #[derive(Debug, Getters, CopyGetters)]
 pub struct FileHeaderChunk {
 	// unk1: u16
 	/// Version of the patch format.
-	#[br(pad_before = 2)]
+	#[brw(pad_before = 2)]
 	#[get_copy = "pub"]
 	version: u8,
 
@@ -56,7 +56,7 @@ pub struct FileHeaderChunk {
 	/// NOTE: This value is likely untrustworthy, and frequently does not match
 	/// the type of patch indicated by the patch's file name. Take with a grain
 	/// of salt.
-	#[br(pad_before = 1)]
+	#[brw(pad_before = 1)]
 	#[get_copy = "pub"]
 	patch_kind: PatchKind,
 
@@ -70,20 +70,36 @@ pub struct FileHeaderChunk {
 	v3: Option<FileHeaderV3>,
 }
 
-#[binread]
-#[br(big)]
+impl FileHeaderChunk {
+	/// Create a new file header. Headers including version 3 fields will be
+	/// written as version 3 of the format, and version 2 otherwise.
+	pub fn new(patch_kind: PatchKind, entry_files: u32, v3: Option<FileHeaderV3>) -> Self {
+		Self {
+			version: if v3.is_some() { 3 } else { 2 },
+			patch_kind,
+			entry_files,
+			v3,
+		}
+	}
+}
+
+/// The kind of content contained within a patch file.
+#[binrw]
+#[brw(big)]
 #[derive(Debug, Clone, Copy)]
 pub enum PatchKind {
-	#[br(magic = b"DIFF")]
+	/// Incremental update, applied on top of the previous patch in the chain.
+	#[brw(magic = b"DIFF")]
 	Diff,
 
-	#[br(magic = b"HIST")]
+	/// Historical patch, containing the full data of the files it touches.
+	#[brw(magic = b"HIST")]
 	Hist,
 }
 
 /// Additional fields available in file header chunks in version 3 of the format.
-#[binread]
-#[br(big)]
+#[binrw]
+#[brw(big)]
 #[derive(Debug, CopyGetters)]
 #[getset(get_copy = "pub")]
 pub struct FileHeaderV3 {
@@ -94,11 +110,14 @@ pub struct FileHeaderV3 {
 
 	// wtaf?
 	#[br(temp)]
+	#[bw(calc = (*delete_data & 0xFFFF_FFFF).try_into().unwrap())]
 	delete_data_1: u32,
 	#[br(temp)]
+	#[bw(calc = (*delete_data >> 32).try_into().unwrap())]
 	delete_data_2: u32,
 	///
 	#[br(calc = u64::from(delete_data_1) | u64::from(delete_data_2) << 32)]
+	#[bw(ignore)]
 	delete_data: u64,
 
 	///
@@ -119,9 +138,36 @@ pub struct FileHeaderV3 {
 	sqpack_file_commands: u32,
 }
 
+impl FileHeaderV3 {
+	/// Create version 3 header fields describing the number of each kind of
+	/// command within a patch. Directory and deletion counts are left empty.
+	pub fn new(
+		commands: u32,
+		sqpack_add_commands: u32,
+		sqpack_delete_commands: u32,
+		sqpack_expand_commands: u32,
+		sqpack_header_commands: u32,
+		sqpack_file_commands: u32,
+	) -> Self {
+		Self {
+			add_directories: 0,
+			delete_directories: 0,
+			delete_data: 0,
+			minor_version: 0,
+			repository_name: 0,
+			commands,
+			sqpack_add_commands,
+			sqpack_delete_commands,
+			sqpack_expand_commands,
+			sqpack_header_commands,
+			sqpack_file_commands,
+		}
+	}
+}
+
 /// An option key-value pair that should be applied while reading remaining chunks.
-#[binread]
-#[br(big)]
+#[binrw]
+#[brw(big)]
 #[derive(Debug, CopyGetters)]
 #[getset(get_copy = "pub")]
 pub struct ApplyChunk {
@@ -131,14 +177,24 @@ pub struct ApplyChunk {
 	// unk1: u32,
 	/// Value to set for the option. For both known options, a non-zero `value`
 	/// represents `true`.
-	#[br(pad_before = 4)]
+	#[brw(pad_before = 4)]
 	value: u32,
 	// unk2: [u8; 4],
 }
 
+impl ApplyChunk {
+	/// Create a new option chunk.
+	pub fn new(option: OptionKind, value: bool) -> Self {
+		Self {
+			option,
+			value: value.into(),
+		}
+	}
+}
+
 #[allow(missing_docs)]
-#[binread]
-#[br(big, repr = u32)]
+#[binrw]
+#[brw(big, repr = u32)]
 #[derive(Debug, Clone, Copy)]
 pub enum OptionKind {
 	IgnoreMissing = 1,
@@ -146,35 +202,53 @@ pub enum OptionKind {
 }
 
 /// Create a new directory.
-#[binread]
-#[br(big)]
+#[binrw]
+#[brw(big)]
 #[derive(Debug, Getters)]
 #[getset(get = "pub")]
 pub struct AddDirectoryChunk {
 	#[br(temp)]
+	#[bw(calc = path.len().try_into().unwrap())]
 	length: u32,
 
 	/// Path of the directory to add. Path is relative to the target folder.
 	#[br(count = length)]
 	#[br(try_map = String::from_utf8)]
+	#[bw(map = |path: &String| path.as_bytes().to_vec())]
 	path: String,
 }
 
+impl AddDirectoryChunk {
+	/// Create a chunk adding the specified directory.
+	pub fn new(path: impl Into<String>) -> Self {
+		Self { path: path.into() }
+	}
+}
+
 /// Delete an empty folder. Deleting a non-empty folder is considered an error.
-#[binread]
-#[br(big)]
+#[binrw]
+#[brw(big)]
 #[derive(Debug, Getters)]
 #[getset(get = "pub")]
 pub struct DeleteDirectoryChunk {
 	#[br(temp)]
+	#[bw(calc = path.len().try_into().unwrap())]
 	length: u32,
 
 	/// Path of the directory to delete. Path is relative to the target folder.
 	#[br(count = length)]
 	#[br(try_map = String::from_utf8)]
+	#[bw(map = |path: &String| path.as_bytes().to_vec())]
 	path: String,
 }
 
+impl DeleteDirectoryChunk {
+	/// Create a chunk deleting the specified directory.
+	pub fn new(path: impl Into<String>) -> Self {
+		Self { path: path.into() }
+	}
+}
+
 /// Extension chunk to perform operations on a SqPack-based game install.
 #[derive(Debug)]
 pub enum SqPackChunk {
@@ -193,7 +267,7 @@ pub enum SqPackChunk {
 	/// Update the header of a file.
 	HeaderUpdate(HeaderUpdateCommand),
 
-	/// Update an entry in a SqPack index file. This command is currently unused.
+	/// Update an entry in a SqPack index file.
 	IndexUpdate(IndexUpdateCommand),
 
 	/// Metadata about the SqPack patch.
@@ -214,9 +288,17 @@ impl BinRead for SqPackChunk {
 	) -> BinResult<Self> {
 		// NOTE: in all observed instances, this size value is equivalent to the parent size on the chunk container.
 		let inner_size = u32::read_options(reader, options, ())?;
-		assert!(inner_size == chunk_size);
 
 		let pos = reader.stream_position()?;
+		if inner_size != chunk_size {
+			return Err(binrw::Error::AssertFail {
+				pos,
+				message: format!(
+					"SqPack chunk size {inner_size} does not match container size {chunk_size}"
+				),
+			});
+		}
+
 		let magic = u8::read_options(reader, options, ())?;
 
 		// The command is 5 bytes smaller than the chunk, due to the header read above.
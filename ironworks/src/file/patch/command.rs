@@ -1,6 +1,6 @@
 use std::io::{Read, Seek, SeekFrom};
 
-use binrw::{binread, BinRead, BinResult, NullString, PosValue, ReadOptions};
+use binrw::{binread, binrw, BinRead, BinResult, NullString, PosValue, ReadOptions};
 use getset::{CopyGetters, Getters};
 
 const UNCOMPRESSED_MARKER_SIZE: u32 = 32_000;
@@ -14,8 +14,8 @@ const UNCOMPRESSED_MARKER_SIZE: u32 = 32_000;
 /// Where `platform` is a string, such as `"win32"`, `file_type` is `"dat"` or
 /// `"index"`, and `maybe_file_id` is an empty string for indices with `file_id == 0`,
 /// and otherwise equivalent to `file_id`.
-#[binread]
-#[br(big)]
+#[binrw]
+#[brw(big)]
 #[derive(Debug, Clone, Copy, CopyGetters)]
 #[get_copy = "pub"]
 pub struct SqPackFile {
@@ -27,81 +27,143 @@ pub struct SqPackFile {
 	file_id: u32,
 }
 
+impl SqPackFile {
+	/// Create a reference to a file within a SqPack file tree.
+	pub fn new(main_id: u16, sub_id: u16, file_id: u32) -> Self {
+		Self {
+			main_id,
+			sub_id,
+			file_id,
+		}
+	}
+}
+
 /// Write data to a file.
-#[binread]
-#[br(big)]
+#[binrw]
+#[brw(big)]
 #[derive(Debug, CopyGetters)]
 #[get_copy = "pub"]
 pub struct AddCommand {
 	// unk1: [u8; 3]
 	/// File to modify.
-	#[br(pad_before = 3)]
+	#[brw(pad_before = 3)]
 	file: SqPackFile,
 	/// Target file offset to start writing, in bytes.
 	#[br(map = |value: u32| value << 7)]
+	#[bw(map = |value: &u32| value >> 7)]
 	target_offset: u32,
 	/// Size of data to copy, in bytes.
 	#[br(map = |value: u32| value << 7)]
+	#[bw(map = |value: &u32| value >> 7)]
 	data_size: u32,
 	/// Number of bytes to blank after writing.
 	#[br(map = |value: u32| value << 7)]
+	#[bw(map = |value: &u32| value >> 7)]
 	delete_size: u32,
 
 	/// Offset in bytes within the patch file to read the data from.
 	#[br(map = |value: PosValue<()>| value.pos)]
+	#[bw(ignore)]
 	source_offset: u64,
 }
 
+impl AddCommand {
+	// Data is written by the patch writer directly following the command.
+	pub(super) fn new(
+		file: SqPackFile,
+		target_offset: u32,
+		data_size: u32,
+		delete_size: u32,
+	) -> Self {
+		Self {
+			file,
+			target_offset,
+			data_size,
+			delete_size,
+			source_offset: 0,
+		}
+	}
+}
+
 /// Delete data from a file.
-#[binread]
-#[br(big)]
+#[binrw]
+#[brw(big)]
 #[derive(Debug, CopyGetters)]
 #[get_copy = "pub"]
 pub struct DeleteCommand {
 	// unk1: [u8; 3]
 	/// File to modify.
-	#[br(pad_before = 3)]
+	#[brw(pad_before = 3)]
 	file: SqPackFile,
 	/// Offset to start writing at.
 	#[br(map = |value: u32| value << 7)]
+	#[bw(map = |value: &u32| value >> 7)]
 	target_offset: u32,
 	/// Number of blank bytes that that should be written.
 	#[br(map = |value: u32| value << 7)]
+	#[bw(map = |value: &u32| value >> 7)]
 	delete_size: u32,
 }
 
+impl DeleteCommand {
+	/// Create a new command. Offsets and sizes must be multiples of 128 bytes.
+	pub fn new(file: SqPackFile, target_offset: u32, delete_size: u32) -> Self {
+		Self {
+			file,
+			target_offset,
+			delete_size,
+		}
+	}
+}
+
 /// Expand the size of a file.
-#[binread]
-#[br(big)]
+#[binrw]
+#[brw(big)]
 #[derive(Debug, CopyGetters)]
 #[get_copy = "pub"]
 pub struct ExpandCommand {
 	// unk1: [u8; 3]
 	/// File to modify.
-	#[br(pad_before = 3)]
+	#[brw(pad_before = 3)]
 	file: SqPackFile,
 	/// Offset to start writing at.
 	#[br(map = |value: u32| value << 7)]
+	#[bw(map = |value: &u32| value >> 7)]
 	target_offset: u32,
 	/// Number of blank bytes that that should be written.
 	#[br(map = |value: u32| value << 7)]
+	#[bw(map = |value: &u32| value >> 7)]
 	delete_size: u32,
 }
 
+impl ExpandCommand {
+	/// Create a new command. Offsets and sizes must be multiples of 128 bytes.
+	pub fn new(file: SqPackFile, target_offset: u32, delete_size: u32) -> Self {
+		Self {
+			file,
+			target_offset,
+			delete_size,
+		}
+	}
+}
+
 /// Perform a file operation.
-#[binread]
+#[binrw]
 #[derive(Debug, Getters, CopyGetters)]
-#[br(big, import(command_size: u32))]
+#[brw(big)]
+#[br(import(command_size: u32))]
 pub struct FileOperationCommand {
 	#[br(temp, map = |value: PosValue<()>| value.pos)]
+	#[bw(ignore)]
 	command_start: u64,
 
 	#[br(temp)]
+	#[bw(calc = operation.magic())]
 	operation_magic: u8,
 
 	// unk1: [u8; 2]
 	/// Offset within the target file to start writing in the case of an AddFile operation.
-	#[br(pad_before = 2)]
+	#[brw(pad_before = 2)]
 	#[get_copy = "pub"]
 	target_offset: u64,
 
@@ -110,7 +172,9 @@ pub struct FileOperationCommand {
 	#[get_copy = "pub"]
 	target_size: u64,
 
+	// Path length is checked by the patch writer before the command is built.
 	#[br(temp)]
+	#[bw(calc = u32::try_from(path.len() + 1).unwrap())]
 	path_length: u32,
 
 	///
@@ -119,17 +183,37 @@ pub struct FileOperationCommand {
 
 	// unk2: [u8; 2]
 	/// Path of the target file within the game's directory.
-	#[br(pad_before = 2)]
+	#[brw(pad_before = 2)]
 	#[br(pad_size_to = path_length)]
 	#[get = "pub"]
 	path: NullString,
 
 	/// File operation to be performed.
 	#[br(args(operation_magic, command_start, command_size))]
+	#[bw(ignore)]
 	#[get = "pub"]
 	operation: FileOperation,
 }
 
+impl FileOperationCommand {
+	// Block data is written by the patch writer directly following the command.
+	pub(super) fn new(
+		operation: FileOperation,
+		target_offset: u64,
+		target_size: u64,
+		repository_id: u16,
+		path: &str,
+	) -> Self {
+		Self {
+			target_offset,
+			target_size,
+			repository_id,
+			path: path.into(),
+			operation,
+		}
+	}
+}
+
 /// The operation that should be performed by a file operation command.
 #[binread]
 #[br(import(magic: u8, command_start: u64, command_size: u32))]
@@ -158,6 +242,17 @@ pub enum FileOperation {
 	RemoveAll,
 }
 
+impl FileOperation {
+	fn magic(&self) -> u8 {
+		match self {
+			Self::AddFile(_) => b'A',
+			Self::DeleteFile => b'D',
+			Self::MakeDirTree => b'M',
+			Self::RemoveAll => b'R',
+		}
+	}
+}
+
 fn parse_block_headers<R: Read + Seek>(
 	reader: &mut R,
 	options: &ReadOptions,
@@ -225,8 +320,8 @@ impl BlockHeader {
 }
 
 /// Update the header of a file.
-#[binread]
-#[br(big)]
+#[binrw]
+#[brw(big)]
 #[derive(Debug, CopyGetters)]
 #[get_copy = "pub"]
 pub struct HeaderUpdateCommand {
@@ -236,22 +331,41 @@ pub struct HeaderUpdateCommand {
 	header_kind: HeaderKind,
 
 	/// File to modify.
-	#[br(pad_before = 1)]
+	#[brw(pad_before = 1)]
 	file: SqPackFile,
 
 	/// Offset within the patch file that the payload starts.
 	#[br(map = |value: PosValue<()>| value.pos)]
+	#[bw(ignore)]
 	offset: u64,
 
 	// It's _always_ 1kb of data.
 	/// Number of bytes that should be written
 	#[br(calc = 1024)]
+	#[bw(ignore)]
 	size: u32,
 }
 
+impl HeaderUpdateCommand {
+	// Header data is written by the patch writer directly following the command.
+	pub(super) fn new(
+		file_kind: HeaderFileKind,
+		header_kind: HeaderKind,
+		file: SqPackFile,
+	) -> Self {
+		Self {
+			file_kind,
+			header_kind,
+			file,
+			offset: 0,
+			size: 1024,
+		}
+	}
+}
+
 #[allow(missing_docs)]
-#[binread]
-#[br(repr = u8)]
+#[binrw]
+#[brw(repr = u8)]
 #[derive(Debug, Clone, Copy)]
 #[repr(u8)]
 pub enum HeaderFileKind {
@@ -260,8 +374,8 @@ pub enum HeaderFileKind {
 }
 
 #[allow(missing_docs)]
-#[binread]
-#[br(repr = u8)]
+#[binrw]
+#[brw(repr = u8)]
 #[derive(Debug, Clone, Copy)]
 #[repr(u8)]
 pub enum HeaderKind {
@@ -274,8 +388,8 @@ pub enum HeaderKind {
 }
 
 /// Update an entry in a SqPack index file.
-#[binread]
-#[br(big)]
+#[binrw]
+#[brw(big)]
 #[derive(Debug, CopyGetters)]
 #[get_copy = "pub"]
 pub struct IndexUpdateCommand {
@@ -283,10 +397,11 @@ pub struct IndexUpdateCommand {
 	kind: IndexUpdateKind,
 	/// If the target entry is a synonym.
 	#[br(map = |value: u8| value != 0)]
+	#[bw(map = |value: &bool| u8::from(*value))]
 	is_synonym: bool,
 	// align: u8
 	/// Index file to modify.
-	#[br(pad_before = 1)]
+	#[brw(pad_before = 1)]
 	file: SqPackFile,
 	/// Hash key of the index entry to modify.
 	file_hash: u64,
@@ -296,9 +411,30 @@ pub struct IndexUpdateCommand {
 	block_count: u32,
 }
 
+impl IndexUpdateCommand {
+	/// Create a new index update command.
+	pub fn new(
+		kind: IndexUpdateKind,
+		is_synonym: bool,
+		file: SqPackFile,
+		file_hash: u64,
+		block_offset: u32,
+		block_count: u32,
+	) -> Self {
+		Self {
+			kind,
+			is_synonym,
+			file,
+			file_hash,
+			block_offset,
+			block_count,
+		}
+	}
+}
+
 #[allow(missing_docs)]
-#[binread]
-#[br(repr = u8)]
+#[binrw]
+#[brw(repr = u8)]
 #[derive(Debug, Clone, Copy)]
 #[repr(u8)]
 pub enum IndexUpdateKind {
@@ -307,8 +443,8 @@ pub enum IndexUpdateKind {
 }
 
 /// Metadata about the SqPack patch.
-#[binread]
-#[br(big)]
+#[binrw]
+#[brw(big)]
 #[derive(Debug, CopyGetters)]
 #[get_copy = "pub"]
 pub struct PatchInfoCommand {
@@ -319,19 +455,30 @@ pub struct PatchInfoCommand {
 
 	// align: u8,
 	///
-	#[br(pad_before = 1)]
+	#[brw(pad_before = 1)]
 	install_size: u64,
 }
 
+impl PatchInfoCommand {
+	/// Create a new patch metadata command.
+	pub fn new(status: u8, version: u8, install_size: u64) -> Self {
+		Self {
+			status,
+			version,
+			install_size,
+		}
+	}
+}
+
 /// Metadata about the target SqPack install.
-#[binread]
-#[br(big)]
+#[binrw]
+#[brw(big)]
 #[derive(Debug, CopyGetters)]
 #[get_copy = "pub"]
 pub struct TargetInfoCommand {
 	// unk1: [u8; 3]
 	/// The target platform of this patch.
-	#[br(pad_before = 3)]
+	#[brw(pad_before = 3)]
 	platform: TargetPlatform,
 
 	/// The target game service region of this patch.
@@ -339,6 +486,7 @@ pub struct TargetInfoCommand {
 
 	///
 	#[br(map = |value: u16| value !=0)]
+	#[bw(map = |value: &bool| u16::from(*value))]
 	is_debug: bool,
 
 	///
@@ -351,9 +499,28 @@ pub struct TargetInfoCommand {
 	_seek_count: u64,
 }
 
+impl TargetInfoCommand {
+	/// Create a new target metadata command.
+	pub fn new(
+		platform: TargetPlatform,
+		region: TargetRegion,
+		is_debug: bool,
+		version: u16,
+	) -> Self {
+		Self {
+			platform,
+			region,
+			is_debug,
+			version,
+			_deleted_data_size: 0,
+			_seek_count: 0,
+		}
+	}
+}
+
 #[allow(missing_docs)]
-#[binread]
-#[br(repr = u16)]
+#[binrw]
+#[brw(repr = u16)]
 #[derive(Debug, Clone, Copy)]
 pub enum TargetPlatform {
 	Win32 = 0,
@@ -363,8 +530,8 @@ pub enum TargetPlatform {
 }
 
 #[allow(missing_docs)]
-#[binread]
-#[br(repr = i16)]
+#[binrw]
+#[brw(repr = i16)]
 #[derive(Debug, Clone, Copy)]
 pub enum TargetRegion {
 	Global = -1,
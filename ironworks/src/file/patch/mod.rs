@@ -2,17 +2,19 @@
 
 mod chunk;
 mod command;
+mod writer;
 mod zipatch;
 
 pub use {
 	chunk::{
 		AddDirectoryChunk, ApplyChunk, Chunk, DeleteDirectoryChunk, FileHeaderChunk, FileHeaderV3,
-		OptionKind, SqPackChunk,
+		OptionKind, PatchKind, SqPackChunk,
 	},
 	command::{
 		AddCommand, BlockHeader, DeleteCommand, ExpandCommand, FileOperation, FileOperationCommand,
 		HeaderFileKind, HeaderKind, HeaderUpdateCommand, IndexUpdateCommand, IndexUpdateKind,
 		PatchInfoCommand, SqPackFile, TargetInfoCommand, TargetPlatform, TargetRegion,
 	},
+	writer::{ZiPatchWriter, MAX_BLOCK_SIZE},
 	zipatch::{ChunkIterator, ZiPatch},
 };
@@ -0,0 +1,341 @@
+use std::io::{Cursor, Write};
+
+use binrw::{BinWrite, BinWriterExt};
+use flate2::{write::DeflateEncoder, Compression, Crc};
+
+use crate::error::{Error, ErrorValue, Result};
+
+use super::{
+	chunk::{Chunk, SqPackChunk},
+	command::{
+		AddCommand, FileOperation, FileOperationCommand, HeaderFileKind, HeaderKind,
+		HeaderUpdateCommand, SqPackFile,
+	},
+	zipatch::ZIPATCH_MAGIC,
+};
+
+const UNCOMPRESSED_MARKER_SIZE: u32 = 32_000;
+const BLOCK_HEADER_SIZE: u32 = 16;
+
+/// Maximum number of bytes stored in a single block of a file operation.
+pub const MAX_BLOCK_SIZE: usize = 16_000;
+
+/// Writer for ZiPatch .patch files.
+///
+/// Chunks are written to the underlying writer in the order they are provided.
+/// Commands that carry payload data must be written via their dedicated methods,
+/// such as [`add_file`](Self::add_file).
+#[derive(Debug)]
+pub struct ZiPatchWriter<W> {
+	writer: W,
+}
+
+impl<W: Write> ZiPatchWriter<W> {
+	/// Create a new patch writer, writing the file magic to the provided writer.
+	pub fn new(mut writer: W) -> Result<Self> {
+		writer.write_all(ZIPATCH_MAGIC)?;
+		Ok(Self { writer })
+	}
+
+	/// Write a chunk that does not carry any payload data.
+	pub fn write_chunk(&mut self, chunk: &Chunk) -> Result<()> {
+		match chunk {
+			Chunk::FileHeader(chunk) => self.write_binrw(b"FHDR", chunk),
+			Chunk::Apply(chunk) => self.write_binrw(b"APLY", chunk),
+			Chunk::AddDirectory(chunk) => self.write_binrw(b"ADIR", chunk),
+			Chunk::DeleteDirectory(chunk) => self.write_binrw(b"DELD", chunk),
+			Chunk::SqPack(chunk) => self.write_sqpack(chunk),
+			Chunk::EndOfFile => self.write_raw(b"EOF_", &[]),
+		}
+	}
+
+	/// Write data to a SqPack .dat file. The target offset and data length must
+	/// be multiples of 128 bytes.
+	pub fn add(
+		&mut self,
+		file: SqPackFile,
+		target_offset: u32,
+		data: &[u8],
+		delete_size: u32,
+	) -> Result<()> {
+		let data_size = u32::try_from(data.len()).map_err(|_| too_large("add data"))?;
+		for (value, name) in [
+			(target_offset, "target offset"),
+			(data_size, "data size"),
+			(delete_size, "delete size"),
+		] {
+			if value % 128 != 0 {
+				return Err(Error::Invalid(
+					ErrorValue::Other("add command".into()),
+					format!("{name} {value} is not a multiple of 128"),
+				));
+			}
+		}
+
+		let mut body = Cursor::new(Vec::new());
+		body.write_be(&AddCommand::new(
+			file,
+			target_offset,
+			data_size,
+			delete_size,
+		))?;
+		body.write_all(data)?;
+
+		self.write_sqpack_command(b'A', &body.into_inner())
+	}
+
+	/// Replace a 1kb header within a SqPack file.
+	pub fn header_update(
+		&mut self,
+		file_kind: HeaderFileKind,
+		header_kind: HeaderKind,
+		file: SqPackFile,
+		data: &[u8; 1024],
+	) -> Result<()> {
+		let mut body = Cursor::new(Vec::new());
+		body.write_be(&HeaderUpdateCommand::new(file_kind, header_kind, file))?;
+		body.write_all(data)?;
+
+		self.write_sqpack_command(b'H', &body.into_inner())
+	}
+
+	/// Write data to a file within the game directory, starting at the specified
+	/// offset. Writes at offset 0 will replace the target file entirely.
+	///
+	/// Data is compressed in blocks of up to [`MAX_BLOCK_SIZE`] bytes.
+	pub fn add_file(
+		&mut self,
+		path: &str,
+		repository_id: u16,
+		target_offset: u64,
+		data: &[u8],
+	) -> Result<()> {
+		let mut blocks = Cursor::new(Vec::new());
+		for block in data.chunks(MAX_BLOCK_SIZE) {
+			write_block(&mut blocks, block)?;
+		}
+
+		self.write_file_operation(
+			FileOperation::AddFile(vec![]),
+			path,
+			repository_id,
+			target_offset,
+			data.len().try_into().unwrap(),
+			&blocks.into_inner(),
+		)
+	}
+
+	/// Delete a file within the game directory.
+	pub fn delete_file(&mut self, path: &str, repository_id: u16) -> Result<()> {
+		self.write_file_operation(FileOperation::DeleteFile, path, repository_id, 0, 0, &[])
+	}
+
+	/// Remove all files belonging to a repository, other than those the game's
+	/// own patcher retains.
+	pub fn remove_all(&mut self, repository_id: u16) -> Result<()> {
+		self.write_file_operation(FileOperation::RemoveAll, "", repository_id, 0, 0, &[])
+	}
+
+	/// Write the end of file chunk, returning the underlying writer.
+	pub fn finish(mut self) -> Result<W> {
+		self.write_chunk(&Chunk::EndOfFile)?;
+		self.writer.flush()?;
+		Ok(self.writer)
+	}
+
+	fn write_sqpack(&mut self, chunk: &SqPackChunk) -> Result<()> {
+		let mut body = Cursor::new(Vec::new());
+		let magic = match chunk {
+			SqPackChunk::Delete(command) => {
+				body.write_be(command)?;
+				b'D'
+			}
+			SqPackChunk::Expand(command) => {
+				body.write_be(command)?;
+				b'E'
+			}
+			SqPackChunk::IndexUpdate(command) => {
+				body.write_be(command)?;
+				b'I'
+			}
+			SqPackChunk::PatchInfo(command) => {
+				body.write_be(command)?;
+				b'X'
+			}
+			SqPackChunk::TargetInfo(command) => {
+				body.write_be(command)?;
+				b'T'
+			}
+			SqPackChunk::Add(_) | SqPackChunk::FileOperation(_) | SqPackChunk::HeaderUpdate(_) => {
+				return Err(Error::Invalid(
+					ErrorValue::Other("sqpack chunk".into()),
+					"commands with payloads must be written with their dedicated methods".into(),
+				))
+			}
+		};
+
+		self.write_sqpack_command(magic, &body.into_inner())
+	}
+
+	fn write_file_operation(
+		&mut self,
+		operation: FileOperation,
+		path: &str,
+		repository_id: u16,
+		target_offset: u64,
+		target_size: u64,
+		blocks: &[u8],
+	) -> Result<()> {
+		// Paths are stored null terminated, with their length as a u32.
+		if u32::try_from(path.len() + 1).is_err() {
+			return Err(too_large("file operation path"));
+		}
+
+		let mut body = Cursor::new(Vec::new());
+		body.write_be(&FileOperationCommand::new(
+			operation,
+			target_offset,
+			target_size,
+			repository_id,
+			path,
+		))?;
+		body.write_all(blocks)?;
+
+		self.write_sqpack_command(b'F', &body.into_inner())
+	}
+
+	fn write_sqpack_command(&mut self, magic: u8, command: &[u8]) -> Result<()> {
+		// The SqPack chunk repeats the size of the chunk, including itself and the magic.
+		let size = u32::try_from(command.len() + 5).map_err(|_| too_large("sqpack chunk"))?;
+
+		let mut body = Cursor::new(Vec::with_capacity(command.len() + 5));
+		body.write_be(&size)?;
+		body.write_be(&magic)?;
+		body.write_all(command)?;
+
+		self.write_raw(b"SQPK", &body.into_inner())
+	}
+
+	fn write_binrw<T>(&mut self, tag: &[u8; 4], value: &T) -> Result<()>
+	where
+		T: BinWrite<Args = ()>,
+	{
+		let mut body = Cursor::new(Vec::new());
+		body.write_be(value)?;
+		self.write_raw(tag, &body.into_inner())
+	}
+
+	fn write_raw(&mut self, tag: &[u8; 4], body: &[u8]) -> Result<()> {
+		let size = u32::try_from(body.len()).map_err(|_| too_large("chunk"))?;
+
+		// The checksum covers the tag as well as the body.
+		let mut crc = Crc::new();
+		crc.update(tag);
+		crc.update(body);
+
+		self.writer.write_all(&size.to_be_bytes())?;
+		self.writer.write_all(tag)?;
+		self.writer.write_all(body)?;
+		self.writer.write_all(&crc.sum().to_be_bytes())?;
+
+		Ok(())
+	}
+}
+
+fn write_block(writer: &mut impl Write, data: &[u8]) -> Result<()> {
+	let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+	encoder.write_all(data)?;
+	let compressed = encoder.finish()?;
+
+	// Data that doesn't benefit from compression is stored as-is.
+	let decompressed_size = u32::try_from(data.len()).unwrap();
+	let (compressed_size, payload) = match compressed.len() < data.len() {
+		true => (u32::try_from(compressed.len()).unwrap(), &compressed[..]),
+		false => (UNCOMPRESSED_MARKER_SIZE, data),
+	};
+
+	writer.write_all(&BLOCK_HEADER_SIZE.to_le_bytes())?;
+	writer.write_all(&[0; 4])?;
+	writer.write_all(&compressed_size.to_le_bytes())?;
+	writer.write_all(&decompressed_size.to_le_bytes())?;
+	writer.write_all(payload)?;
+
+	// Blocks are padded out to 128 byte alignment, including their header.
+	let length = BLOCK_HEADER_SIZE as usize + payload.len();
+	let aligned = (payload.len() + 0x8F) & !0x7F;
+	writer.write_all(&vec![0; aligned - length])?;
+
+	Ok(())
+}
+
+fn too_large(value: &str) -> Error {
+	Error::Invalid(
+		ErrorValue::Other(value.into()),
+		"exceeds maximum size of 4GiB".into(),
+	)
+}
+
+#[cfg(test)]
+mod test {
+	use crate::file::{
+		patch::{ApplyChunk, FileHeaderChunk, FileOperation, OptionKind, PatchKind, ZiPatch},
+		File,
+	};
+
+	use super::*;
+
+	#[test]
+	fn round_trip() {
+		let data = (0..40_000u32)
+			.map(|value| (value % 7) as u8)
+			.collect::<Vec<_>>();
+
+		let mut writer = ZiPatchWriter::new(Cursor::new(Vec::new())).unwrap();
+		writer
+			.write_chunk(&Chunk::FileHeader(FileHeaderChunk::new(
+				PatchKind::Diff,
+				0,
+				None,
+			)))
+			.unwrap();
+		writer
+			.write_chunk(&Chunk::Apply(ApplyChunk::new(
+				OptionKind::IgnoreMissing,
+				true,
+			)))
+			.unwrap();
+		writer
+			.add_file("sqpack/ffxiv/000000.win32.dat0", 0, 0, &data)
+			.unwrap();
+		writer
+			.delete_file("sqpack/ffxiv/000000.win32.dat1", 0)
+			.unwrap();
+		let bytes = writer.finish().unwrap().into_inner();
+
+		let patch = ZiPatch::read(Cursor::new(bytes)).unwrap();
+		let chunks = patch.chunks().collect::<Result<Vec<_>>>().unwrap();
+		assert_eq!(chunks.len(), 5);
+
+		assert!(matches!(&chunks[0], Chunk::FileHeader(header) if header.version() == 2));
+		assert!(matches!(&chunks[1], Chunk::Apply(chunk) if chunk.value() == 1));
+		assert!(matches!(&chunks[4], Chunk::EndOfFile));
+
+		let command = match &chunks[2] {
+			Chunk::SqPack(SqPackChunk::FileOperation(command)) => command,
+			other => panic!("unexpected chunk {other:?}"),
+		};
+		assert_eq!(command.path().to_string(), "sqpack/ffxiv/000000.win32.dat0");
+		assert_eq!(command.target_size(), 40_000);
+		let blocks = match command.operation() {
+			FileOperation::AddFile(blocks) => blocks,
+			other => panic!("unexpected operation {other:?}"),
+		};
+		assert_eq!(blocks.len(), 3);
+
+		assert!(matches!(
+			&chunks[3],
+			Chunk::SqPack(SqPackChunk::FileOperation(command))
+				if matches!(command.operation(), FileOperation::DeleteFile)
+		));
+	}
+}
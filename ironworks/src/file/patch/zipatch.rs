@@ -10,7 +10,7 @@ use crate::{error::Result, file::File, FileStream};
 
 use super::chunk::Chunk;
 
-const ZIPATCH_MAGIC: &[u8; 12] = b"\x91ZIPATCH\x0D\x0A\x1A\x0A";
+pub(super) const ZIPATCH_MAGIC: &[u8; 12] = b"\x91ZIPATCH\x0D\x0A\x1A\x0A";
 
 /// ZiPatch incremental patch file format.
 ///
@@ -29,6 +29,12 @@ impl ZiPatch {
 	pub fn chunks(&self) -> ChunkIterator {
 		ChunkIterator::new(self.stream.clone())
 	}
+
+	/// Shared handle to the underlying stream, for reading chunk payloads.
+	#[cfg(feature = "zipatch")]
+	pub(crate) fn stream(&self) -> &Arc<Mutex<Box<dyn FileStream>>> {
+		&self.stream
+	}
 }
 
 impl File for ZiPatch {
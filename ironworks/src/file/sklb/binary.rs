@@ -1,13 +1,11 @@
-//! Structs and utilities for parsing .sklb files.
-
 use std::io::{Read, Seek, SeekFrom};
 
 use binrw::{binread, count, until_eof, BinRead, BinResult, ReadOptions};
 use getset::{CopyGetters, Getters};
 
-use crate::{error::Result, FileStream};
+use crate::{error::Result, file::File, FileStream};
 
-use super::file::File;
+use super::skeleton::Skeleton;
 
 /// Skeleton data and related mappings.
 #[binread]
@@ -34,7 +32,7 @@ pub struct SkeletonBinary {
 	#[br(temp)]
 	layer_count: u16,
 
-	///
+	/// Animation layers, and the bones within the skeleton that they affect.
 	#[br(args {
 		count: layer_count.into(),
 		inner: (header.layer_offset().into(),)
@@ -60,7 +58,7 @@ impl SkeletonBinary {
 		}
 	}
 
-	///
+	/// IDs of the characters this skeleton may be mapped to.
 	pub fn mapper_character_id(&self) -> [u32; 4] {
 		match &self.header {
 			Header::V1(header) => header.mapper_character_id,
@@ -68,7 +66,7 @@ impl SkeletonBinary {
 		}
 	}
 
-	///
+	/// Indices of the bones used to connect this skeleton to a parent skeleton.
 	pub fn connect_bones(&self) -> Vec<i16> {
 		match &self.header {
 			Header::V1(header) => header.connect_bones.to_vec(),
@@ -76,13 +74,19 @@ impl SkeletonBinary {
 		}
 	}
 
-	///
+	/// Number of bones sampled at each LOD. Only present in older file versions.
 	pub fn lod_sample_bone_count(&self) -> Option<[i16; 3]> {
 		match &self.header {
 			Header::V1(header) => Some(header.lod_sample_bone_count),
 			Header::V2(_) => None,
 		}
 	}
+
+	/// Decode the embedded Havok skeleton, resolving animation layers against its
+	/// bones.
+	pub fn havok_skeleton(&self) -> Result<Skeleton> {
+		Skeleton::read(&self.skeleton, &self.animation_layers)
+	}
 }
 
 impl File for SkeletonBinary {
@@ -173,14 +177,14 @@ struct HeaderV2 {
 	mapper_character_id: [u32; 4],
 }
 
-///
+/// An animation layer, and the bones it affects.
 #[derive(Debug, Getters, CopyGetters)]
 pub struct AnimationLayer {
-	///
+	/// ID of the layer.
 	#[get_copy = "pub"]
 	layer: u32,
 
-	///
+	/// Indices of the affected bones within the Havok skeleton.
 	#[get = "pub"]
 	bone_indices: Vec<i16>,
 }
@@ -0,0 +1,10 @@
+//! Structs and utilities for parsing .sklb files.
+
+mod binary;
+mod skeleton;
+mod tagfile;
+
+pub use {
+	binary::{AnimationLayer, SkeletonBinary, Version},
+	skeleton::{Bone, Layer, Skeleton, Transform},
+};
@@ -0,0 +1,531 @@
+use getset::{CopyGetters, Getters};
+
+use crate::error::{Error, ErrorValue, Result};
+
+use super::{
+	binary::AnimationLayer,
+	tagfile::{Record, Tagfile, Value},
+};
+
+/// A Havok skeleton, decoded from the tagfile embedded within a skeleton binary.
+#[derive(Debug, Getters)]
+#[get = "pub"]
+pub struct Skeleton {
+	/// Name of the skeleton.
+	name: String,
+
+	/// Bones within the skeleton. Parents are guaranteed to be ordered before
+	/// their children.
+	bones: Vec<Bone>,
+
+	/// Animation layers, mapped to bones within the skeleton.
+	layers: Vec<Layer>,
+}
+
+impl Skeleton {
+	pub(super) fn read(tagfile: &[u8], animation_layers: &[AnimationLayer]) -> Result<Self> {
+		let tagfile = Tagfile::read(tagfile)?;
+		let root = tagfile.root()?;
+		let skeleton = find_skeleton(&tagfile, root.as_record()?)?;
+		let skeleton = skeleton.as_record()?;
+
+		let parents = skeleton.field("parentIndices")?.as_array()?;
+		let bones = skeleton.field("bones")?.as_array()?;
+		let reference_pose = skeleton.field("referencePose")?.as_array()?;
+
+		if parents.len() != bones.len() || reference_pose.len() != bones.len() {
+			return Err(invalid(format!(
+				"Mismatched bone counts: {} bones, {} parents, {} poses.",
+				bones.len(),
+				parents.len(),
+				reference_pose.len()
+			)));
+		}
+
+		let bones = bones
+			.iter()
+			.zip(parents)
+			.zip(reference_pose)
+			.enumerate()
+			.map(|(index, ((bone, parent), pose))| {
+				let bone = bone.as_record()?;
+				Ok(Bone {
+					name: bone.field("name")?.as_string()?.to_string(),
+					parent: read_parent(index, parent.as_integer()?)?,
+					lock_translation: bone.field("lockTranslation")?.as_bool()?,
+					transform: Transform::from_floats(&pose.floats())?,
+				})
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		let layers = animation_layers
+			.iter()
+			.map(|layer| {
+				let bones = layer
+					.bone_indices()
+					.iter()
+					.map(|index| {
+						usize::try_from(*index)
+							.ok()
+							.filter(|index| *index < bones.len())
+							.ok_or_else(|| {
+								invalid(format!(
+									"Layer {} references bone {index}, but skeleton has {} bones.",
+									layer.layer(),
+									bones.len()
+								))
+							})
+					})
+					.collect::<Result<Vec<_>>>()?;
+
+				Ok(Layer {
+					id: layer.layer(),
+					bones,
+				})
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(Self {
+			name: skeleton.field("name")?.as_string()?.to_string(),
+			bones,
+			layers,
+		})
+	}
+}
+
+// Skeletons are stored within an animation container, referenced by one of the
+// root level container's named variants.
+fn find_skeleton(tagfile: &Tagfile, root: &Record) -> Result<Value> {
+	for variant in root.field("namedVariants")?.as_array()? {
+		let variant = variant.as_record()?;
+		if variant.field("className")?.as_string()? != "hkaAnimationContainer" {
+			continue;
+		}
+
+		let container = match variant.field("variant")?.as_pointer()? {
+			Some(index) => tagfile.item(index)?,
+			None => continue,
+		};
+
+		let skeletons = container.as_record()?.field("skeletons")?.as_array()?;
+		if let Some(index) = skeletons
+			.first()
+			.map(Value::as_pointer)
+			.transpose()?
+			.flatten()
+		{
+			return tagfile.item(index);
+		}
+	}
+
+	Err(Error::NotFound(ErrorValue::Other("hkaSkeleton".into())))
+}
+
+// Parents must be ordered before their children, which also precludes cycles.
+// Root bones use a parent index of -1.
+fn read_parent(index: usize, parent: i64) -> Result<Option<usize>> {
+	if parent == -1 {
+		return Ok(None);
+	}
+
+	match usize::try_from(parent) {
+		Ok(parent) if parent < index => Ok(Some(parent)),
+		_ => Err(invalid(format!(
+			"Bone {index} has invalid parent {parent}."
+		))),
+	}
+}
+
+fn invalid(message: String) -> Error {
+	Error::Invalid(ErrorValue::Other("hkaSkeleton".into()), message)
+}
+
+/// A single bone within a skeleton.
+#[derive(Debug, Getters, CopyGetters)]
+pub struct Bone {
+	/// Name of the bone.
+	#[get = "pub"]
+	name: String,
+
+	/// Index of the parent of this bone, or `None` for root bones.
+	#[get_copy = "pub"]
+	parent: Option<usize>,
+
+	/// Whether translation of this bone is locked during animation.
+	#[get_copy = "pub"]
+	lock_translation: bool,
+
+	/// Reference pose of this bone, relative to its parent.
+	#[get_copy = "pub"]
+	transform: Transform,
+}
+
+/// Transform, in translation, rotation, scale order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+	/// Translation.
+	pub translation: [f32; 3],
+	/// Rotation, as a quaternion in `[x, y, z, w]` order.
+	pub rotation: [f32; 4],
+	/// Scale.
+	pub scale: [f32; 3],
+}
+
+impl Transform {
+	// hkQsTransform is stored as three vector4s.
+	fn from_floats(floats: &[f32]) -> Result<Self> {
+		if floats.len() != 12 {
+			return Err(invalid(format!(
+				"Expected 12 transform values, got {}.",
+				floats.len()
+			)));
+		}
+
+		Ok(Self {
+			translation: [floats[0], floats[1], floats[2]],
+			rotation: [floats[4], floats[5], floats[6], floats[7]],
+			scale: [floats[8], floats[9], floats[10]],
+		})
+	}
+}
+
+/// An animation layer, and the bones it affects.
+#[derive(Debug, Getters, CopyGetters)]
+pub struct Layer {
+	/// ID of the layer.
+	#[get_copy = "pub"]
+	id: u32,
+
+	/// Indices of the bones within the skeleton that are affected by this layer.
+	#[get = "pub"]
+	bones: Vec<usize>,
+}
+
+#[cfg(test)]
+mod test {
+	use std::io::Cursor;
+
+	use crate::file::{sklb::SkeletonBinary, File};
+
+	use super::{Skeleton, Transform};
+
+	const KIND_BOOL: u32 = 2;
+	const KIND_STRING: u32 = 3;
+	const KIND_I16: u32 = 4 | 0x200 | 0x8000;
+	const KIND_U8: u32 = 4 | 0x4000;
+	const KIND_FLOAT: u32 = 5;
+	const KIND_POINTER: u32 = 6;
+	const KIND_RECORD: u32 = 7;
+	const KIND_ARRAY: u32 = 8;
+	const KIND_TRANSFORM: u32 = 9 | (12 << 8);
+
+	// Type indices within the tagfile built below.
+	const ROOT: u32 = 1;
+	const VARIANT: u32 = 3;
+	const CONTAINER: u32 = 6;
+	const POINTER: u32 = 5;
+	const SKELETON: u32 = 8;
+	const INT16: u32 = 10;
+	const BONE: u32 = 12;
+	const TRANSFORM: u32 = 15;
+	const CHAR: u32 = 17;
+
+	// (name, flags, pointer, size, members)
+	type TypeDef = (
+		&'static str,
+		u32,
+		u32,
+		u32,
+		&'static [(&'static str, u32, u32)],
+	);
+	const TYPES: &[TypeDef] = &[
+		(
+			"hkRootLevelContainer",
+			KIND_RECORD,
+			0,
+			8,
+			&[("namedVariants", 0, 2)],
+		),
+		("hkArray<NamedVariant>", KIND_ARRAY, 3, 8, &[]),
+		(
+			"hkRootLevelContainer::NamedVariant",
+			KIND_RECORD,
+			0,
+			24,
+			&[("name", 0, 4), ("className", 8, 4), ("variant", 16, 5)],
+		),
+		("hkStringPtr", KIND_STRING, 0, 8, &[]),
+		("hkRefVariant", KIND_POINTER, 0, 8, &[]),
+		(
+			"hkaAnimationContainer",
+			KIND_RECORD,
+			0,
+			8,
+			&[("skeletons", 0, 7)],
+		),
+		("hkArray<hkaSkeleton*>", KIND_ARRAY, 5, 8, &[]),
+		(
+			"hkaSkeleton",
+			KIND_RECORD,
+			0,
+			32,
+			&[
+				("name", 0, 4),
+				("parentIndices", 8, 9),
+				("bones", 16, 11),
+				("referencePose", 24, 14),
+			],
+		),
+		("hkArray<hkInt16>", KIND_ARRAY, 10, 8, &[]),
+		("hkInt16", KIND_I16, 0, 2, &[]),
+		("hkArray<hkaBone>", KIND_ARRAY, 12, 8, &[]),
+		(
+			"hkaBone",
+			KIND_RECORD,
+			0,
+			16,
+			&[("name", 0, 4), ("lockTranslation", 8, 13)],
+		),
+		("hkBool", KIND_BOOL, 0, 1, &[]),
+		("hkArray<hkQsTransform>", KIND_ARRAY, 15, 8, &[]),
+		("hkQsTransform", KIND_TRANSFORM, 16, 48, &[]),
+		("hkReal", KIND_FLOAT, 0, 4, &[]),
+		("char", KIND_U8, 0, 1, &[]),
+	];
+
+	fn packed(value: u32) -> Vec<u8> {
+		match value {
+			0..=0x7F => vec![value as u8],
+			0x80..=0x3FFF => vec![0x80 | (value >> 8) as u8, value as u8],
+			_ => vec![0xC0 | (value >> 16) as u8, (value >> 8) as u8, value as u8],
+		}
+	}
+
+	fn section(tag: &[u8; 4], content: &[u8]) -> Vec<u8> {
+		let size = u32::try_from(content.len() + 8).unwrap();
+		[&size.to_be_bytes()[..], tag, content].concat()
+	}
+
+	fn strings<'a>(values: impl Iterator<Item = &'a str>) -> Vec<u8> {
+		values
+			.flat_map(|value| [value.as_bytes(), &[0]].concat())
+			.collect()
+	}
+
+	fn type_section() -> Vec<u8> {
+		let fields = TYPES
+			.iter()
+			.flat_map(|(_, _, _, _, members)| members.iter().map(|(name, _, _)| *name))
+			.collect::<Vec<_>>();
+
+		let mut names = packed(u32::try_from(TYPES.len() + 1).unwrap());
+		let mut bodies = vec![];
+		let mut field_index = 0;
+		for (index, (_, flags, pointer, size, members)) in TYPES.iter().enumerate() {
+			names.extend(packed(index.try_into().unwrap()));
+			names.push(0);
+
+			let body_flags = 0x01 | 0x08 | if *pointer != 0 { 0x02 } else { 0 } | 0x20;
+			bodies.extend(packed((index + 1).try_into().unwrap()));
+			bodies.extend([0, body_flags]);
+			bodies.extend(packed(*flags));
+			if *pointer != 0 {
+				bodies.extend(packed(*pointer));
+			}
+			bodies.extend([*size as u8, 4]);
+			bodies.extend(packed(members.len().try_into().unwrap()));
+			for (_, offset, type_index) in members.iter() {
+				bodies.extend([field_index, 0, *offset as u8, *type_index as u8]);
+				field_index += 1;
+			}
+		}
+
+		[
+			section(b"TSTR", &strings(TYPES.iter().map(|kind| kind.0))),
+			section(b"FSTR", &strings(fields.into_iter())),
+			section(b"TNAM", &names),
+			section(b"TBOD", &bodies),
+		]
+		.concat()
+	}
+
+	#[derive(Default)]
+	struct Data {
+		data: Vec<u8>,
+		// Item 0 is null, and item 1 is reserved for the root object.
+		items: Vec<[u32; 3]>,
+	}
+
+	impl Data {
+		fn item(&mut self, type_index: u32, bytes: &[u8], count: usize) -> u32 {
+			if self.items.is_empty() {
+				self.items = vec![[0; 3]; 2];
+			}
+			self.items.push([
+				type_index,
+				self.data.len().try_into().unwrap(),
+				count.try_into().unwrap(),
+			]);
+			self.data.extend_from_slice(bytes);
+			(self.items.len() - 1).try_into().unwrap()
+		}
+
+		fn string(&mut self, value: &str) -> u32 {
+			let bytes = [value.as_bytes(), &[0]].concat();
+			self.item(CHAR, &bytes, bytes.len())
+		}
+	}
+
+	// Item references are stored in 8 byte slots.
+	fn refs(values: &[u32]) -> Vec<u8> {
+		values
+			.iter()
+			.flat_map(|value| [value.to_le_bytes(), [0; 4]].concat())
+			.collect()
+	}
+
+	fn tagfile(parents: &[i16]) -> Vec<u8> {
+		let mut data = Data::default();
+
+		let names = (0..parents.len())
+			.map(|index| data.string(&format!("bone_{index}")))
+			.collect::<Vec<_>>();
+		let bones = names
+			.iter()
+			.enumerate()
+			.flat_map(|(index, name)| [refs(&[*name]), vec![u8::from(index == 0); 8]].concat())
+			.collect::<Vec<_>>();
+		let bones = data.item(BONE, &bones, parents.len());
+
+		let parent_bytes = parents
+			.iter()
+			.flat_map(|parent| parent.to_le_bytes())
+			.collect::<Vec<_>>();
+		let parents_item = data.item(INT16, &parent_bytes, parents.len());
+
+		let poses = (0..parents.len())
+			.flat_map(|index| {
+				[index as f32, 0., 0., 0., 0., 0., 0., 1., 1., 1., 1., 0.]
+					.map(f32::to_le_bytes)
+					.concat()
+			})
+			.collect::<Vec<_>>();
+		let poses = data.item(TRANSFORM, &poses, parents.len());
+
+		let name = data.string("skeleton");
+		let skeleton = data.item(SKELETON, &refs(&[name, parents_item, bones, poses]), 1);
+		let skeletons = data.item(POINTER, &refs(&[skeleton]), 1);
+		let container = data.item(CONTAINER, &refs(&[skeletons]), 1);
+
+		let class_name = data.string("hkaAnimationContainer");
+		let variant_name = data.string("Merged Animation Container");
+		let variants = data.item(VARIANT, &refs(&[variant_name, class_name, container]), 1);
+
+		let root_offset = data.data.len().try_into().unwrap();
+		data.data.extend(refs(&[variants]));
+		data.items[1] = [ROOT, root_offset, 1];
+
+		let items = data
+			.items
+			.iter()
+			.flatten()
+			.flat_map(|value| value.to_le_bytes())
+			.collect::<Vec<_>>();
+
+		section(
+			b"TAG0",
+			&[
+				section(b"SDKV", b"2014"),
+				section(b"DATA", &data.data),
+				section(b"TYPE", &type_section()),
+				section(b"INDX", &section(b"ITEM", &items)),
+			]
+			.concat(),
+		)
+	}
+
+	// Build a version 1300 skeleton binary around the provided tagfile.
+	fn sklb(tagfile: &[u8], layers: &[(u32, &[i16])]) -> Vec<u8> {
+		const HEADER_SIZE: u32 = 40;
+
+		let mut layer_data = vec![];
+		let mut offsets = vec![];
+		let table_size = 6 + layers.len() * 2;
+		for (id, bones) in layers {
+			offsets.extend(
+				u16::try_from(table_size + layer_data.len())
+					.unwrap()
+					.to_le_bytes(),
+			);
+			layer_data.extend(id.to_le_bytes());
+			layer_data.extend(u16::try_from(bones.len()).unwrap().to_le_bytes());
+			layer_data.extend(bones.iter().flat_map(|bone| bone.to_le_bytes()));
+		}
+		let layers = [
+			&b"hpla"[..],
+			&u16::try_from(layers.len()).unwrap().to_le_bytes(),
+			&offsets,
+			&layer_data,
+		]
+		.concat();
+		let skeleton_offset = HEADER_SIZE + u32::try_from(layers.len()).unwrap();
+
+		[
+			&b"blks0031"[..],
+			&HEADER_SIZE.to_le_bytes(),
+			&skeleton_offset.to_le_bytes(),
+			&[0; 24],
+			&layers,
+			tagfile,
+		]
+		.concat()
+	}
+
+	fn decode(parents: &[i16], layers: &[(u32, &[i16])]) -> crate::error::Result<Skeleton> {
+		let binary = SkeletonBinary::read(Cursor::new(sklb(&tagfile(parents), layers))).unwrap();
+		binary.havok_skeleton()
+	}
+
+	#[test]
+	fn decode_skeleton() {
+		let skeleton = decode(&[-1, 0, 1, 0], &[(7, &[3, 1])]).unwrap();
+		assert_eq!(skeleton.name(), "skeleton");
+
+		let bones = skeleton.bones();
+		assert_eq!(bones.len(), 4);
+		assert_eq!(bones[2].name(), "bone_2");
+		assert_eq!(
+			bones.iter().map(|bone| bone.parent()).collect::<Vec<_>>(),
+			[None, Some(0), Some(1), Some(0)]
+		);
+		assert!(bones[0].lock_translation());
+		assert!(!bones[1].lock_translation());
+		assert_eq!(
+			bones[3].transform(),
+			Transform {
+				translation: [3., 0., 0.],
+				rotation: [0., 0., 0., 1.],
+				scale: [1., 1., 1.],
+			}
+		);
+
+		let layers = skeleton.layers();
+		assert_eq!(layers.len(), 1);
+		assert_eq!(layers[0].id(), 7);
+		assert_eq!(layers[0].bones(), &[3, 1]);
+	}
+
+	#[test]
+	fn invalid_parents() {
+		// Self reference, forward reference (which may form a cycle), and invalid negative.
+		assert!(decode(&[-1, 1], &[]).is_err());
+		assert!(decode(&[1, 0], &[]).is_err());
+		assert!(decode(&[-1, -2], &[]).is_err());
+	}
+
+	#[test]
+	fn invalid_layer_bones() {
+		assert!(decode(&[-1, 0], &[(1, &[2])]).is_err());
+		assert!(decode(&[-1, 0], &[(1, &[-1])]).is_err());
+	}
+}
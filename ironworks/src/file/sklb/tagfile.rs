@@ -0,0 +1,634 @@
+// Reader for Havok binary tagfiles (`TAG0`), as embedded in .sklb files.
+//
+// Tagfiles consist of a tree of sections, each prefixed with a big-endian size
+// and flags, followed by a four-character tag. The `TYPE` section describes
+// the layout of every type used by the file, `INDX` lists the items (objects
+// and arrays) present in the `DATA` blob, and `DATA` contains the raw,
+// little-endian, in-memory representation of those items.
+
+use crate::error::{Error, ErrorValue, Result};
+
+const SECTION_HEADER_SIZE: usize = 8;
+
+// Type kinds, stored in the low bits of the sub type flags.
+const KIND_MASK: u32 = 0x1F;
+const KIND_VOID: u32 = 0;
+const KIND_OPAQUE: u32 = 1;
+const KIND_BOOL: u32 = 2;
+const KIND_STRING: u32 = 3;
+const KIND_INT: u32 = 4;
+const KIND_FLOAT: u32 = 5;
+const KIND_POINTER: u32 = 6;
+const KIND_RECORD: u32 = 7;
+const KIND_ARRAY: u32 = 8;
+const KIND_TUPLE: u32 = 9;
+
+const INT_SIGNED: u32 = 0x200;
+const INT_SIZE_8: u32 = 0x4000;
+const INT_SIZE_16: u32 = 0x8000;
+const INT_SIZE_32: u32 = 0x10000;
+
+// Optional properties of type bodies.
+const BODY_SUB_TYPE_FLAGS: u32 = 0x01;
+const BODY_POINTER: u32 = 0x02;
+const BODY_VERSION: u32 = 0x04;
+const BODY_BYTE_SIZE: u32 = 0x08;
+const BODY_ABSTRACT_VALUE: u32 = 0x10;
+const BODY_MEMBERS: u32 = 0x20;
+const BODY_INTERFACES: u32 = 0x40;
+const BODY_UNKNOWN: u32 = 0x80;
+
+const ITEM_SIZE: usize = 12;
+
+fn invalid(message: impl Into<String>) -> Error {
+	Error::Invalid(ErrorValue::Other("Havok tagfile".into()), message.into())
+}
+
+/// A decoded value within a tagfile.
+#[derive(Debug)]
+pub enum Value {
+	None,
+	Bool(bool),
+	Integer(i64),
+	Float(f32),
+	String(String),
+	/// Index of the item pointed to, if any.
+	Pointer(Option<usize>),
+	Array(Vec<Value>),
+	Tuple(Vec<Value>),
+	Record(Record),
+}
+
+impl Value {
+	pub fn as_record(&self) -> Result<&Record> {
+		match self {
+			Self::Record(record) => Ok(record),
+			other => Err(invalid(format!("Expected record, got {other:?}."))),
+		}
+	}
+
+	pub fn as_array(&self) -> Result<&[Value]> {
+		match self {
+			Self::Array(values) => Ok(values),
+			other => Err(invalid(format!("Expected array, got {other:?}."))),
+		}
+	}
+
+	pub fn as_string(&self) -> Result<&str> {
+		match self {
+			Self::String(string) => Ok(string),
+			other => Err(invalid(format!("Expected string, got {other:?}."))),
+		}
+	}
+
+	pub fn as_integer(&self) -> Result<i64> {
+		match self {
+			Self::Integer(value) => Ok(*value),
+			other => Err(invalid(format!("Expected integer, got {other:?}."))),
+		}
+	}
+
+	pub fn as_bool(&self) -> Result<bool> {
+		match self {
+			Self::Bool(value) => Ok(*value),
+			other => Err(invalid(format!("Expected bool, got {other:?}."))),
+		}
+	}
+
+	pub fn as_pointer(&self) -> Result<Option<usize>> {
+		match self {
+			Self::Pointer(item) => Ok(*item),
+			other => Err(invalid(format!("Expected pointer, got {other:?}."))),
+		}
+	}
+
+	/// Flatten all floating point values contained within this value, in order.
+	/// Useful for vector and matrix types, which may be nested tuples and records.
+	pub fn floats(&self) -> Vec<f32> {
+		match self {
+			Self::Float(value) => vec![*value],
+			Self::Tuple(values) | Self::Array(values) => {
+				values.iter().flat_map(Value::floats).collect()
+			}
+			Self::Record(record) => record
+				.fields
+				.iter()
+				.flat_map(|(_, value)| value.floats())
+				.collect(),
+			_ => vec![],
+		}
+	}
+}
+
+/// An instance of a record (class) type.
+#[derive(Debug)]
+pub struct Record {
+	pub type_name: String,
+	fields: Vec<(String, Value)>,
+}
+
+impl Record {
+	pub fn field(&self, name: &str) -> Result<&Value> {
+		self.fields
+			.iter()
+			.find(|(field_name, _)| field_name == name)
+			.map(|(_, value)| value)
+			.ok_or_else(|| invalid(format!("{} has no field {name:?}.", self.type_name)))
+	}
+}
+
+#[derive(Clone, Debug, Default)]
+struct Type {
+	name: String,
+	parent: usize,
+	sub_type_flags: u32,
+	pointer: usize,
+	byte_size: u32,
+	members: Vec<Member>,
+}
+
+#[derive(Clone, Debug)]
+struct Member {
+	name: String,
+	offset: u32,
+	type_index: usize,
+}
+
+#[derive(Debug)]
+struct Item {
+	type_index: usize,
+	offset: u32,
+	count: u32,
+}
+
+#[derive(Debug)]
+pub struct Tagfile {
+	data: Vec<u8>,
+	types: Vec<Type>,
+	items: Vec<Item>,
+}
+
+impl Tagfile {
+	pub fn read(buffer: &[u8]) -> Result<Self> {
+		let root = sections(buffer)?
+			.into_iter()
+			.find(|(tag, _)| tag == b"TAG0")
+			.ok_or_else(|| invalid("Missing TAG0 section."))?
+			.1;
+
+		let mut data = None;
+		let mut types = None;
+		let mut items = None;
+
+		for (tag, content) in sections(root)? {
+			match &tag {
+				b"DATA" => data = Some(content.to_vec()),
+				b"TYPE" => types = Some(read_types(content)?),
+				b"INDX" => items = Some(read_items(content)?),
+				_ => {}
+			}
+		}
+
+		Ok(Self {
+			data: data.ok_or_else(|| invalid("Missing DATA section."))?,
+			types: types.ok_or_else(|| invalid("Missing TYPE section."))?,
+			items: items.ok_or_else(|| invalid("Missing INDX section."))?,
+		})
+	}
+
+	/// Read the root object of the tagfile.
+	pub fn root(&self) -> Result<Value> {
+		self.item(1)
+	}
+
+	/// Read the object stored in the item at the given index.
+	pub fn item(&self, index: usize) -> Result<Value> {
+		let item = self.get_item(index)?;
+		self.read_value(item.type_index, item.offset.try_into().unwrap())
+	}
+
+	fn get_item(&self, index: usize) -> Result<&Item> {
+		self.items
+			.get(index)
+			.ok_or_else(|| invalid(format!("Item {index} out of bounds.")))
+	}
+
+	fn get_type(&self, index: usize) -> Result<&Type> {
+		self.types
+			.get(index)
+			.ok_or_else(|| invalid(format!("Type {index} out of bounds.")))
+	}
+
+	// Walk a type and its parents, returning the first property that is set.
+	fn inherited<T>(&self, index: usize, get: impl Fn(&Type) -> Option<T>) -> Result<Option<T>> {
+		let mut index = index;
+		while index != 0 {
+			let kind = self.get_type(index)?;
+			if let Some(value) = get(kind) {
+				return Ok(Some(value));
+			}
+			index = kind.parent;
+		}
+		Ok(None)
+	}
+
+	fn sub_type_flags(&self, index: usize) -> Result<u32> {
+		Ok(self
+			.inherited(index, |kind| {
+				Some(kind.sub_type_flags).filter(|flags| *flags != 0)
+			})?
+			.unwrap_or(0))
+	}
+
+	fn pointer_type(&self, index: usize) -> Result<usize> {
+		self.inherited(index, |kind| {
+			Some(kind.pointer).filter(|pointer| *pointer != 0)
+		})?
+		.ok_or_else(|| invalid(format!("Type {index} has no element type.")))
+	}
+
+	fn byte_size(&self, index: usize) -> Result<usize> {
+		let size = self
+			.inherited(index, |kind| Some(kind.byte_size).filter(|size| *size != 0))?
+			.ok_or_else(|| invalid(format!("Type {index} has no size.")))?;
+		Ok(size.try_into().unwrap())
+	}
+
+	fn members(&self, index: usize) -> Result<Vec<Member>> {
+		let mut chain = vec![];
+		let mut index = index;
+		while index != 0 {
+			let kind = self.get_type(index)?;
+			chain.push(kind);
+			index = kind.parent;
+		}
+
+		// Parent members are laid out before those of the child.
+		Ok(chain
+			.into_iter()
+			.rev()
+			.flat_map(|kind| kind.members.iter().cloned())
+			.collect())
+	}
+
+	fn bytes<const N: usize>(&self, offset: usize) -> Result<[u8; N]> {
+		self.data
+			.get(offset..offset + N)
+			.and_then(|bytes| bytes.try_into().ok())
+			.ok_or_else(|| invalid(format!("Data read at {offset:#x} out of bounds.")))
+	}
+
+	// Pointer-like values store the index of their target item.
+	fn item_reference(&self, offset: usize) -> Result<Option<usize>> {
+		let index = u32::from_le_bytes(self.bytes(offset)?);
+		Ok(match index {
+			0 => None,
+			index => Some(index.try_into().unwrap()),
+		})
+	}
+
+	fn read_value(&self, type_index: usize, offset: usize) -> Result<Value> {
+		let flags = self.sub_type_flags(type_index)?;
+
+		let value = match flags & KIND_MASK {
+			KIND_VOID | KIND_OPAQUE => Value::None,
+
+			KIND_BOOL => {
+				let size = self.byte_size(type_index).unwrap_or(1);
+				let bytes = self.data.get(offset..offset + size);
+				Value::Bool(bytes.is_some_and(|bytes| bytes.iter().any(|byte| *byte != 0)))
+			}
+
+			KIND_INT => {
+				let signed = flags & INT_SIGNED != 0;
+				Value::Integer(match flags {
+					flags if flags & INT_SIZE_8 != 0 => {
+						let [byte] = self.bytes(offset)?;
+						match signed {
+							true => (byte as i8).into(),
+							false => byte.into(),
+						}
+					}
+					flags if flags & INT_SIZE_16 != 0 => {
+						let bytes = self.bytes(offset)?;
+						match signed {
+							true => i16::from_le_bytes(bytes).into(),
+							false => u16::from_le_bytes(bytes).into(),
+						}
+					}
+					flags if flags & INT_SIZE_32 != 0 => {
+						let bytes = self.bytes(offset)?;
+						match signed {
+							true => i32::from_le_bytes(bytes).into(),
+							false => u32::from_le_bytes(bytes).into(),
+						}
+					}
+					_ => i64::from_le_bytes(self.bytes(offset)?),
+				})
+			}
+
+			KIND_FLOAT => match self.byte_size(type_index)? {
+				4 => Value::Float(f32::from_le_bytes(self.bytes(offset)?)),
+				8 => Value::Float(f64::from_le_bytes(self.bytes(offset)?) as f32),
+				size => return Err(invalid(format!("Unsupported float size {size}."))),
+			},
+
+			KIND_STRING => match self.item_reference(offset)? {
+				None => Value::String(String::new()),
+				Some(index) => {
+					let item = self.get_item(index)?;
+					let start = usize::try_from(item.offset).unwrap();
+					let end = start + usize::try_from(item.count).unwrap();
+					let bytes = self
+						.data
+						.get(start..end)
+						.ok_or_else(|| invalid("String out of bounds."))?;
+					let length = bytes
+						.iter()
+						.position(|byte| *byte == 0)
+						.unwrap_or(bytes.len());
+					Value::String(String::from_utf8_lossy(&bytes[..length]).into_owned())
+				}
+			},
+
+			KIND_POINTER => Value::Pointer(self.item_reference(offset)?),
+
+			KIND_RECORD => {
+				let fields = self
+					.members(type_index)?
+					.into_iter()
+					.map(|member| {
+						let offset = offset + usize::try_from(member.offset).unwrap();
+						Ok((member.name, self.read_value(member.type_index, offset)?))
+					})
+					.collect::<Result<Vec<_>>>()?;
+
+				Value::Record(Record {
+					type_name: self.get_type(type_index)?.name.clone(),
+					fields,
+				})
+			}
+
+			KIND_ARRAY => match self.item_reference(offset)? {
+				None => Value::Array(vec![]),
+				Some(index) => {
+					let item = self.get_item(index)?;
+					let values = self.read_sequence(
+						item.type_index,
+						item.offset.try_into().unwrap(),
+						item.count.try_into().unwrap(),
+					)?;
+					Value::Array(values)
+				}
+			},
+
+			KIND_TUPLE => {
+				let count = usize::try_from(flags >> 8).unwrap();
+				let element_type = self.pointer_type(type_index)?;
+				Value::Tuple(self.read_sequence(element_type, offset, count)?)
+			}
+
+			kind => return Err(invalid(format!("Unknown type kind {kind}."))),
+		};
+
+		Ok(value)
+	}
+
+	fn read_sequence(&self, type_index: usize, offset: usize, count: usize) -> Result<Vec<Value>> {
+		let stride = self.byte_size(type_index)?;
+		(0..count)
+			.map(|index| self.read_value(type_index, offset + index * stride))
+			.collect()
+	}
+}
+
+// Split a buffer into its constituent sections.
+fn sections(buffer: &[u8]) -> Result<Vec<([u8; 4], &[u8])>> {
+	let mut sections = vec![];
+	let mut remaining = buffer;
+
+	while remaining.len() >= SECTION_HEADER_SIZE {
+		let header = u32::from_be_bytes(remaining[0..4].try_into().unwrap());
+		// The top two bits of the header are flags.
+		let size = usize::try_from(header & 0x3FFFFFFF).unwrap();
+		if size < SECTION_HEADER_SIZE || size > remaining.len() {
+			return Err(invalid(format!("Invalid section size {size}.")));
+		}
+
+		let tag = remaining[4..8].try_into().unwrap();
+		sections.push((tag, &remaining[SECTION_HEADER_SIZE..size]));
+		remaining = &remaining[size..];
+	}
+
+	Ok(sections)
+}
+
+fn read_strings(content: &[u8]) -> Vec<String> {
+	let mut strings = content
+		.split(|byte| *byte == 0)
+		.map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+		.collect::<Vec<_>>();
+	// Trailing null (and any padding) produces empty entries at the end.
+	while strings.last().is_some_and(|string| string.is_empty()) {
+		strings.pop();
+	}
+	strings
+}
+
+fn read_types(content: &[u8]) -> Result<Vec<Type>> {
+	let sections = sections(content)?;
+	let section = |tag: &[u8; 4]| {
+		sections
+			.iter()
+			.find(|(section_tag, _)| section_tag == tag)
+			.map(|(_, content)| *content)
+			.ok_or_else(|| invalid(format!("Missing {} section.", String::from_utf8_lossy(tag))))
+	};
+
+	let type_strings = read_strings(section(b"TSTR")?);
+	let field_strings = read_strings(section(b"FSTR")?);
+	let string = |strings: &[String], index: u64| {
+		strings
+			.get(usize::try_from(index).unwrap())
+			.cloned()
+			.ok_or_else(|| invalid(format!("String {index} out of bounds.")))
+	};
+
+	// Type names. Index 0 is reserved as a null type.
+	let mut reader = Reader::new(section(b"TNAM")?);
+	let type_count = usize::try_from(reader.packed()?).unwrap();
+	let mut types = vec![Type::default(); type_count];
+	for kind in types.iter_mut().skip(1) {
+		kind.name = string(&type_strings, reader.packed()?)?;
+		let template_count = reader.packed()?;
+		for _ in 0..template_count {
+			reader.packed()?;
+			reader.packed()?;
+		}
+	}
+
+	// Type bodies.
+	let mut reader = Reader::new(section(b"TBOD")?);
+	while !reader.is_empty() {
+		let index = usize::try_from(reader.packed()?).unwrap();
+		if index == 0 {
+			continue;
+		}
+
+		let parent = usize::try_from(reader.packed()?).unwrap();
+		let body_flags = u32::try_from(reader.packed()?).unwrap();
+
+		let kind = types
+			.get_mut(index)
+			.ok_or_else(|| invalid(format!("Type body {index} out of bounds.")))?;
+		kind.parent = parent;
+
+		if body_flags & BODY_SUB_TYPE_FLAGS != 0 {
+			kind.sub_type_flags = u32::try_from(reader.packed()?).unwrap();
+		}
+		if body_flags & BODY_POINTER != 0 {
+			kind.pointer = usize::try_from(reader.packed()?).unwrap();
+		}
+		if body_flags & BODY_VERSION != 0 {
+			reader.packed()?;
+		}
+		if body_flags & BODY_BYTE_SIZE != 0 {
+			kind.byte_size = u32::try_from(reader.packed()?).unwrap();
+			// Alignment.
+			reader.packed()?;
+		}
+		if body_flags & BODY_ABSTRACT_VALUE != 0 {
+			reader.packed()?;
+		}
+		if body_flags & BODY_MEMBERS != 0 {
+			let member_count = reader.packed()?;
+			for _ in 0..member_count {
+				let name = string(&field_strings, reader.packed()?)?;
+				// Member flags.
+				reader.packed()?;
+				let offset = u32::try_from(reader.packed()?).unwrap();
+				let type_index = usize::try_from(reader.packed()?).unwrap();
+				kind.members.push(Member {
+					name,
+					offset,
+					type_index,
+				});
+			}
+		}
+		if body_flags & BODY_INTERFACES != 0 {
+			let interface_count = reader.packed()?;
+			for _ in 0..interface_count {
+				reader.packed()?;
+				reader.packed()?;
+			}
+		}
+		if body_flags & BODY_UNKNOWN != 0 {
+			reader.packed()?;
+		}
+	}
+
+	Ok(types)
+}
+
+fn read_items(content: &[u8]) -> Result<Vec<Item>> {
+	let items = sections(content)?
+		.into_iter()
+		.find(|(tag, _)| tag == b"ITEM")
+		.ok_or_else(|| invalid("Missing ITEM section."))?
+		.1;
+
+	let items = items
+		.chunks_exact(ITEM_SIZE)
+		.map(|chunk| {
+			let value = |index: usize| {
+				u32::from_le_bytes(chunk[index * 4..index * 4 + 4].try_into().unwrap())
+			};
+			// The top byte of the first value contains item flags.
+			Item {
+				type_index: usize::try_from(value(0) & 0xFFFFFF).unwrap(),
+				offset: value(1),
+				count: value(2),
+			}
+		})
+		.collect();
+
+	Ok(items)
+}
+
+struct Reader<'a> {
+	data: &'a [u8],
+	position: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn new(data: &'a [u8]) -> Self {
+		Self { data, position: 0 }
+	}
+
+	fn is_empty(&self) -> bool {
+		self.position >= self.data.len()
+	}
+
+	fn byte(&mut self) -> Result<u8> {
+		let byte = *self
+			.data
+			.get(self.position)
+			.ok_or_else(|| invalid("Unexpected end of section."))?;
+		self.position += 1;
+		Ok(byte)
+	}
+
+	// Read a variable-length integer. The leading bits of the first byte
+	// determine the number of bytes used, with the value stored big-endian.
+	fn packed(&mut self) -> Result<u64> {
+		let first = self.byte()?;
+		if first & 0x80 == 0 {
+			return Ok(first.into());
+		}
+
+		let (extra_bytes, mask) = match first >> 3 {
+			0x10..=0x17 => (1, 0x3FFF),
+			0x18..=0x1B => (2, 0x1FFFFF),
+			0x1C => (3, 0x7FFFFFF),
+			0x1D => (4, 0x7FFFFFFFF),
+			0x1E => (7, 0x7FFFFFFFFFFFFFF),
+			_ => (8, u64::MAX),
+		};
+
+		let mut value = u64::from(first);
+		for _ in 0..extra_bytes {
+			value = (value << 8) | u64::from(self.byte()?);
+		}
+		Ok(value & mask)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn packed_integers() {
+		let mut reader = Reader::new(&[0x05, 0x81, 0x02, 0xC1, 0x02, 0x03]);
+		assert_eq!(reader.packed().unwrap(), 5);
+		assert_eq!(reader.packed().unwrap(), 0x102);
+		assert_eq!(reader.packed().unwrap(), 0x10203);
+		assert!(reader.is_empty());
+	}
+
+	#[test]
+	fn nested_sections() {
+		let buffer = [
+			0x40, 0, 0, 20, b'T', b'A', b'G', b'0', //
+			0, 0, 0, 12, b'S', b'D', b'K', b'V', b'2', b'0', b'1', b'4',
+		];
+		let root = sections(&buffer).unwrap();
+		assert_eq!(root.len(), 1);
+		assert_eq!(&root[0].0, b"TAG0");
+
+		let children = sections(root[0].1).unwrap();
+		assert_eq!(children.len(), 1);
+		assert_eq!(&children[0].0, b"SDKV");
+		assert_eq!(children[0].1, b"2014");
+	}
+}
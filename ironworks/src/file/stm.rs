@@ -0,0 +1,257 @@
+//! Structs and utilities for parsing .stm files.
+
+use std::fmt;
+
+use binrw::{binread, until_eof, BinRead};
+use half::f16;
+
+use crate::{
+	error::{Error, ErrorValue, Result},
+	FileStream,
+};
+
+use super::file::File;
+
+/// Number of stains represented within each template.
+pub const STAIN_COUNT: usize = 128;
+
+/// Staining templates, providing per-stain colour set values used when dyeing
+/// materials. Typically found at `chara/base_material/stainingtemplate.stm`.
+#[binread]
+#[br(little)]
+pub struct StainingTemplate {
+	#[br(temp)]
+	_magic: u32,
+	#[br(temp)]
+	entry_count: u16,
+	#[br(temp)]
+	_unknown1: u16,
+
+	#[br(count = entry_count)]
+	keys: Vec<u16>,
+
+	// Offsets are in u16 units, relative to the start of the data block.
+	#[br(count = entry_count)]
+	offsets: Vec<u16>,
+
+	#[br(parse_with = until_eof)]
+	data: Vec<u16>,
+}
+
+impl File for StainingTemplate {
+	fn read(mut stream: impl FileStream) -> Result<Self> {
+		Ok(<Self as BinRead>::read(&mut stream)?)
+	}
+}
+
+impl StainingTemplate {
+	/// Iterator over the IDs of templates contained in this file.
+	pub fn template_ids(&self) -> impl Iterator<Item = u16> + '_ {
+		self.keys.iter().copied()
+	}
+
+	/// Get the template with the specified ID.
+	pub fn template(&self, id: u16) -> Result<Template> {
+		let error_value = || ErrorValue::Other(format!("staining template {id}"));
+
+		let index = self
+			.keys
+			.iter()
+			.position(|key| *key == id)
+			.ok_or_else(|| Error::NotFound(error_value()))?;
+		let offset = usize::from(self.offsets[index]);
+
+		// Each template is prefixed with the end offsets of its five value arrays,
+		// relative to the end of the prefix.
+		let data = self.data.get(offset..).unwrap_or(&[]);
+		if data.len() < 5 {
+			return Err(Error::Invalid(
+				error_value(),
+				"Template header is truncated.".into(),
+			));
+		}
+		let (ends, data) = data.split_at(5);
+
+		let mut start = 0;
+		let arrays = ends
+			.iter()
+			.map(|&end| {
+				let end = usize::from(end);
+				let array = data.get(start..end).ok_or_else(|| {
+					Error::Invalid(
+						error_value(),
+						format!("Template array {start}..{end} is out of bounds."),
+					)
+				});
+				start = end;
+				array
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		let invalid = |message: String| Error::Invalid(error_value(), message);
+		Ok(Template {
+			diffuse: read_array(arrays[0]).map_err(invalid)?,
+			specular: read_array(arrays[1]).map_err(invalid)?,
+			emissive: read_array(arrays[2]).map_err(invalid)?,
+			gloss: read_array(arrays[3]).map_err(invalid)?,
+			specular_strength: read_array(arrays[4]).map_err(invalid)?,
+		})
+	}
+}
+
+impl fmt::Debug for StainingTemplate {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("StainingTemplate")
+			.field("templates", &self.keys)
+			.finish()
+	}
+}
+
+trait Value: Copy {
+	const SIZE: usize;
+	fn from_raw(raw: &[u16]) -> Self;
+}
+
+impl Value for f32 {
+	const SIZE: usize = 1;
+	fn from_raw(raw: &[u16]) -> Self {
+		f16::from_bits(raw[0]).to_f32()
+	}
+}
+
+impl Value for [f32; 3] {
+	const SIZE: usize = 3;
+	fn from_raw(raw: &[u16]) -> Self {
+		[0, 1, 2].map(|index| f16::from_bits(raw[index]).to_f32())
+	}
+}
+
+// Arrays are stored in one of three forms, distinguished by their length:
+// - A single value, shared by all stains.
+// - One value per stain.
+// - A palette of values, followed by a byte index into that palette per stain.
+fn read_array<T: Value>(raw: &[u16]) -> Result<Vec<Option<T>>, String> {
+	let values = raw
+		.chunks_exact(T::SIZE)
+		.map(T::from_raw)
+		.collect::<Vec<_>>();
+
+	let array = match raw.len() {
+		0 => vec![None; STAIN_COUNT],
+
+		length if length == T::SIZE => vec![Some(values[0]); STAIN_COUNT],
+
+		length if length >= T::SIZE * STAIN_COUNT => {
+			values.into_iter().take(STAIN_COUNT).map(Some).collect()
+		}
+
+		length => {
+			// Indices are one byte per stain, packed into the trailing u16s.
+			let index_length = STAIN_COUNT / 2;
+			if length < index_length || !(length - index_length).is_multiple_of(T::SIZE) {
+				return Err(format!("Unexpected template array length {length}."));
+			}
+
+			let (palette, indices) = raw.split_at(length - index_length);
+			indices
+				.iter()
+				.flat_map(|pair| pair.to_le_bytes())
+				.map(|index| match index {
+					0 | u8::MAX => None,
+					index => palette
+						.chunks_exact(T::SIZE)
+						.nth(usize::from(index - 1))
+						.map(T::from_raw),
+				})
+				.collect()
+		}
+	};
+
+	Ok(array)
+}
+
+/// A single staining template, containing values for every stain.
+#[derive(Debug)]
+pub struct Template {
+	diffuse: Vec<Option<[f32; 3]>>,
+	specular: Vec<Option<[f32; 3]>>,
+	emissive: Vec<Option<[f32; 3]>>,
+	gloss: Vec<Option<f32>>,
+	specular_strength: Vec<Option<f32>>,
+}
+
+impl Template {
+	/// Get the values for the specified stain ID. Stain IDs are 1-indexed, with
+	/// `0` representing an undyed item.
+	pub fn stain(&self, stain: u8) -> Option<Stain> {
+		let index = usize::from(stain).checked_sub(1)?;
+		if index >= STAIN_COUNT {
+			return None;
+		}
+
+		Some(Stain {
+			diffuse: self.diffuse[index],
+			specular: self.specular[index],
+			emissive: self.emissive[index],
+			gloss: self.gloss[index],
+			specular_strength: self.specular_strength[index],
+		})
+	}
+}
+
+/// Colour set values for a single stain within a template. Values that are not
+/// specified by the template are `None`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stain {
+	/// Diffuse colour, as linear RGB.
+	pub diffuse: Option<[f32; 3]>,
+	/// Specular colour, as linear RGB.
+	pub specular: Option<[f32; 3]>,
+	/// Emissive colour, as linear RGB.
+	pub emissive: Option<[f32; 3]>,
+	/// Glossiness of the surface.
+	pub gloss: Option<f32>,
+	/// Strength of the specular highlight.
+	pub specular_strength: Option<f32>,
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn half(value: f32) -> u16 {
+		f16::from_f32(value).to_bits()
+	}
+
+	#[test]
+	fn single_value() {
+		let array = read_array::<f32>(&[half(2.0)]).unwrap();
+		assert_eq!(array.len(), STAIN_COUNT);
+		assert!(array.iter().all(|value| *value == Some(2.0)));
+	}
+
+	#[test]
+	fn palette_values() {
+		let mut raw = vec![half(1.0), half(0.5)];
+		let mut indices = [0u8; STAIN_COUNT];
+		indices[0] = 2;
+		indices[1] = 1;
+		indices[2] = u8::MAX;
+		raw.extend(
+			indices
+				.chunks_exact(2)
+				.map(|pair| u16::from_le_bytes([pair[0], pair[1]])),
+		);
+
+		let array = read_array::<f32>(&raw).unwrap();
+		assert_eq!(array[0], Some(0.5));
+		assert_eq!(array[1], Some(1.0));
+		assert_eq!(array[2], None);
+		assert_eq!(array[3], None);
+	}
+
+	#[test]
+	fn invalid_length() {
+		assert!(read_array::<[f32; 3]>(&[0; 4]).is_err());
+	}
+}
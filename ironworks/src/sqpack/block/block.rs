@@ -1,6 +1,6 @@
 use std::io::{self, Read, Seek, SeekFrom, Take};
 
-use binrw::{binread, BinRead};
+use binrw::{binread, BinReaderExt, Endian};
 use either::Either;
 use flate2::read::DeflateDecoder;
 
@@ -8,7 +8,6 @@ const MAX_COMPRESSED_BLOCK_SIZE: u32 = 16_000;
 
 #[binread]
 #[derive(Debug)]
-#[br(little)]
 pub struct BlockHeader {
 	pub size: u32,
 	// unknown1: u32,
@@ -17,11 +16,16 @@ pub struct BlockHeader {
 	pub decompressed_size: u32,
 }
 
-pub fn read_block<R: Read + Seek>(reader: &mut R, offset: u32) -> io::Result<BlockPayload<R>> {
+pub fn read_block<R: Read + Seek>(
+	reader: &mut R,
+	offset: u32,
+	endian: Endian,
+) -> io::Result<BlockPayload<R>> {
 	// Seek to the block and read its header so we know how much to expect in the rest of the block.
 	reader.seek(SeekFrom::Start(offset.into()))?;
-	let block_header =
-		BlockHeader::read(reader).map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+	let block_header = reader
+		.read_type::<BlockHeader>(endian)
+		.map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
 
 	Ok(BlockPayload::new(
 		reader,
@@ -5,4 +5,7 @@ mod shared;
 mod standard;
 mod texture;
 
-pub use file::File;
+pub use {
+	file::File,
+	shared::{FileKind, Header},
+};
@@ -1,8 +1,8 @@
 use binrw::binread;
 
+// Endianness is dependent on the platform of the containing SqPack files.
 #[binread]
 #[derive(Debug)]
-#[br(little)]
 pub struct Header {
 	pub size: u32,
 	pub kind: FileKind,
@@ -15,7 +15,7 @@ pub struct Header {
 
 #[binread]
 #[derive(Debug)]
-#[br(little, repr = u32)]
+#[br(repr = u32)]
 pub enum FileKind {
 	Empty = 1,
 	Standard,
@@ -1,6 +1,6 @@
 use std::io::{Read, Seek, SeekFrom};
 
-use binrw::{binread, BinRead, VecArgs};
+use binrw::{binread, BinReaderExt, Endian, VecArgs};
 
 use crate::{
 	error::Result,
@@ -11,17 +11,21 @@ use super::shared::Header;
 
 #[binread]
 #[derive(Debug)]
-#[br(little)]
 struct BlockInfo {
 	offset: u32,
 	_input_size: u16,
 	output_size: u16,
 }
 
-pub fn read<R: Read + Seek>(mut reader: R, offset: u32, header: Header) -> Result<BlockStream<R>> {
+pub fn read<R: Read + Seek>(
+	mut reader: R,
+	offset: u32,
+	header: Header,
+	endian: Endian,
+) -> Result<BlockStream<R>> {
 	// Eagerly read the block info.
-	let blocks = <Vec<BlockInfo>>::read_args(
-		&mut reader,
+	let blocks = reader.read_type_args::<Vec<BlockInfo>>(
+		endian,
 		VecArgs {
 			count: header.block_count.try_into().unwrap(),
 			inner: (),
@@ -35,7 +39,7 @@ pub fn read<R: Read + Seek>(mut reader: R, offset: u32, header: Header) -> Resul
 
 		let header_offset = offset + info.offset;
 		reader.seek(SeekFrom::Start(header_offset.into()))?;
-		let header = BlockHeader::read(&mut reader)?;
+		let header = reader.read_type::<BlockHeader>(endian)?;
 
 		Ok(BlockMetadata {
 			input_offset: (header_offset + header.size).try_into().unwrap(),
@@ -5,7 +5,7 @@ use getset::CopyGetters;
 
 use crate::{
 	error::{Error, ErrorValue, Result},
-	sqpack::Resource,
+	sqpack::{Platform, Resource},
 };
 
 use super::{index1::Index1, index2::Index2, shared::FileMetadata};
@@ -23,6 +23,8 @@ pub struct Location {
 	/// Estimated size of the target file, if known. This will typically err on
 	/// the larger side, as files commonly have some amount of padding at the end.
 	size: Option<u32>,
+	/// Platform the containing SqPack files were built for.
+	platform: Platform,
 }
 
 #[derive(Debug)]
@@ -62,6 +64,7 @@ impl<R: Resource> Index<R> {
 					data_file: meta.data_file_id,
 					offset: meta.offset,
 					size,
+					platform: chunk.platform(),
 				})),
 			}
 		});
@@ -148,4 +151,11 @@ impl IndexChunk {
 			Self::Index2(_index) => todo!("index2"),
 		}
 	}
+
+	fn platform(&self) -> Platform {
+		match self {
+			Self::Index1(index) => index.platform(),
+			Self::Index2(index) => index.platform(),
+		}
+	}
 }
@@ -2,7 +2,10 @@ use std::{collections::BTreeSet, io::SeekFrom};
 
 use binrw::binread;
 
-use crate::error::{Error, ErrorValue, Result};
+use crate::{
+	error::{Error, ErrorValue, Result},
+	sqpack::Platform,
+};
 
 use super::{
 	crc::crc32,
@@ -11,7 +14,6 @@ use super::{
 
 #[binread]
 #[derive(Debug)]
-#[br(little)]
 struct Entry {
 	hash: u64,
 	#[br(pad_after = 4)]
@@ -30,12 +32,20 @@ pub struct Index1 {
 	#[br(temp)]
 	sqpack_header: SqPackHeader,
 
-	#[br(temp, seek_before = SeekFrom::Start(sqpack_header.size.into()))]
+	#[br(calc = sqpack_header.platform)]
+	platform: Platform,
+
+	#[br(
+		temp,
+		seek_before = SeekFrom::Start(sqpack_header.size.into()),
+		is_big = platform.is_big_endian(),
+	)]
 	index_header: IndexHeader,
 
 	#[br(
 		seek_before = SeekFrom::Start(index_header.index_data.offset.into()),
 		count = index_header.index_data.size / Entry::SIZE,
+		is_big = platform.is_big_endian(),
 	)]
 	indexes: Vec<Entry>,
 
@@ -46,23 +56,37 @@ pub struct Index1 {
 	offsets: BTreeSet<(u8, u32)>,
 }
 
+/// Calculate the Index1 hash of the path.
+pub fn path_hash(path: &str) -> Result<u64> {
+	let hashed_segments = path
+		.rsplitn(2, '/')
+		.map(|segment| crc32(segment.as_bytes()))
+		.collect::<Vec<_>>();
+
+	match hashed_segments[..] {
+		[file, directory] => Ok((directory as u64) << 32 | file as u64),
+		_ => Err(Error::Invalid(
+			ErrorValue::Path(path.into()),
+			"Paths must contain at least two segments.".into(),
+		)),
+	}
+}
+
 impl Index1 {
+	/// The platform the index was built for.
+	pub fn platform(&self) -> Platform {
+		self.platform
+	}
+
+	/// Iterate over the hashes and metadata of all entries in the index.
+	pub fn entries(&self) -> impl Iterator<Item = (u64, &FileMetadata)> {
+		self.indexes
+			.iter()
+			.map(|entry| (entry.hash, &entry.file_metadata))
+	}
+
 	pub fn find(&self, path: &str) -> Result<(FileMetadata, Option<u32>)> {
-		// Calculate the Index1 hash of the path
-		let hashed_segments = path
-			.rsplitn(2, '/')
-			.map(|segment| crc32(segment.as_bytes()))
-			.collect::<Vec<_>>();
-
-		let hash = match hashed_segments[..] {
-			[file, directory] => (directory as u64) << 32 | file as u64,
-			_ => {
-				return Err(Error::Invalid(
-					ErrorValue::Path(path.into()),
-					"Paths must contain at least two segments.".into(),
-				))
-			}
-		};
+		let hash = path_hash(path)?;
 
 		// Look for a matching entry in the index table
 		// TODO: hashmap this probably
@@ -1,5 +1,24 @@
-use binrw::BinRead;
+use binrw::binread;
 
-// todo
-#[derive(BinRead, Debug)]
-pub struct Index2 {}
+use crate::sqpack::Platform;
+
+use super::shared::SqPackHeader;
+
+// todo: entries
+#[binread]
+#[derive(Debug)]
+#[br(little)]
+pub struct Index2 {
+	#[br(temp)]
+	sqpack_header: SqPackHeader,
+
+	#[br(calc = sqpack_header.platform)]
+	platform: Platform,
+}
+
+impl Index2 {
+	/// The platform the index was built for.
+	pub fn platform(&self) -> Platform {
+		self.platform
+	}
+}
@@ -5,3 +5,6 @@ mod index2;
 mod shared;
 
 pub use index::{Index, Location};
+#[cfg(any(feature = "zipatch", test))]
+pub use index1::path_hash;
+pub use index1::Index1;
@@ -2,19 +2,25 @@ use std::fmt;
 
 use binrw::BinRead;
 
+use crate::sqpack::Platform;
+
 #[derive(BinRead, Debug)]
 #[br(little, magic = b"SqPack\0\0")]
 pub struct SqPackHeader {
-	_platform_id: u8,
+	#[br(try_map = |raw: u8| Platform::try_from(raw))]
+	pub platform: Platform,
 	// unknown: [u8; 3],
-	#[br(pad_before = 3)]
+	#[br(pad_before = 3, is_big = platform.is_big_endian())]
 	pub size: u32,
+	#[br(is_big = platform.is_big_endian())]
 	_version: u32,
+	#[br(is_big = platform.is_big_endian())]
 	_kind: u32,
 }
 
+// Endianness of the following structures is inherited from the platform of the
+// containing file.
 #[derive(BinRead, Debug)]
-#[br(little)]
 pub struct IndexHeader {
 	_size: u32,
 	_version: u32,
@@ -30,7 +36,6 @@ pub struct IndexHeader {
 }
 
 #[derive(BinRead, Debug)]
-#[br(little)]
 pub struct Section {
 	pub offset: u32,
 	pub size: u32,
@@ -50,7 +55,7 @@ impl fmt::Debug for Digest {
 #[derive(BinRead, Clone, Debug)]
 #[br(map = Self::read)]
 pub struct FileMetadata {
-	is_synonym: bool,
+	pub is_synonym: bool,
 	pub data_file_id: u8,
 	pub offset: u32,
 }
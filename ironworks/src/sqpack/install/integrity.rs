@@ -0,0 +1,610 @@
+use std::{
+	collections::BTreeMap,
+	fs,
+	io::{self, BufReader, Read, Seek, SeekFrom},
+	num::NonZeroUsize,
+	path::{Path, PathBuf},
+	sync::Mutex,
+	thread,
+};
+
+use binrw::{BinRead, BinReaderExt, Endian};
+
+use crate::{
+	error::Result,
+	sqpack::{
+		file::{File, FileKind, Header},
+		index::Index1,
+		Platform,
+	},
+	utility::TakeSeekableExt,
+};
+
+use super::Install;
+
+// Files within .dat files are aligned to 128-byte blocks.
+const BLOCK_SIZE: u64 = 1 << 7;
+
+/// A file referenced by an index that could not be read successfully.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFailure {
+	/// Path of the .dat file containing the file.
+	pub dat: PathBuf,
+	/// Offset of the file within the .dat file.
+	pub offset: u64,
+	/// Index1 hashes of all index entries referencing the file.
+	pub hashes: Vec<u64>,
+	/// Description of the failure.
+	pub reason: String,
+}
+
+/// A region of a .dat file that is not referenced by any index entry, and is
+/// not marked as empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanedRegion {
+	/// Path of the .dat file containing the region.
+	pub dat: PathBuf,
+	/// Offset of the start of the region.
+	pub offset: u64,
+	/// Size of the region, in bytes.
+	pub size: u64,
+}
+
+/// A .dat file that could not be checked in full, such as one that cannot be
+/// opened or has a truncated header. Failures of individual files within the
+/// .dat file are still reported, where possible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatFailure {
+	/// Path of the .dat file.
+	pub path: PathBuf,
+	/// Description of the failure.
+	pub reason: String,
+}
+
+/// An index file that could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexFailure {
+	/// Path of the index file.
+	pub path: PathBuf,
+	/// Description of the failure.
+	pub reason: String,
+}
+
+/// Results of an integrity check over an install.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IntegrityReport {
+	/// Number of distinct files that were checked.
+	pub files_checked: usize,
+	/// Files that failed to read.
+	pub failures: Vec<FileFailure>,
+	/// Regions of .dat files that are not referenced by any index.
+	pub orphaned_regions: Vec<OrphanedRegion>,
+	/// .dat files that are referenced by an index, but do not exist.
+	pub missing_dat_files: Vec<PathBuf>,
+	/// .dat files that exist, but could not be checked in full.
+	pub dat_failures: Vec<DatFailure>,
+	/// Index files that could not be read.
+	pub index_failures: Vec<IndexFailure>,
+}
+
+impl IntegrityReport {
+	/// Whether the check found no problems with the install.
+	pub fn is_ok(&self) -> bool {
+		self.failures.is_empty()
+			&& self.orphaned_regions.is_empty()
+			&& self.missing_dat_files.is_empty()
+			&& self.dat_failures.is_empty()
+			&& self.index_failures.is_empty()
+	}
+}
+
+/// Checker validating that every file referenced by an install's indexes can be
+/// read, and that .dat files do not contain unreferenced data.
+#[derive(Debug)]
+pub struct IntegrityChecker<'a> {
+	install: &'a Install,
+	parallelism: NonZeroUsize,
+}
+
+// Files within a single .dat file, keyed by offset, with the hashes referencing them.
+type DatFiles = BTreeMap<u64, Vec<u64>>;
+
+#[derive(Default)]
+struct DatReport {
+	files_checked: usize,
+	failures: Vec<FileFailure>,
+	orphaned_regions: Vec<OrphanedRegion>,
+	missing: bool,
+	failure: Option<String>,
+}
+
+impl<'a> IntegrityChecker<'a> {
+	pub(super) fn new(install: &'a Install) -> Self {
+		Self {
+			install,
+			parallelism: thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+		}
+	}
+
+	/// Set the number of .dat files that will be checked concurrently. Defaults
+	/// to the available parallelism of the system.
+	pub fn with_parallelism(mut self, parallelism: NonZeroUsize) -> Self {
+		self.parallelism = parallelism;
+		self
+	}
+
+	/// Run the integrity check.
+	pub fn check(&self) -> Result<IntegrityReport> {
+		let mut report = IntegrityReport::default();
+
+		// Collect the files referenced by every index, grouped by their .dat file.
+		let mut dats = BTreeMap::<PathBuf, DatFiles>::new();
+		for (repository, category, chunk, path) in self.install.index_files()? {
+			let index = match read_index(&path) {
+				Ok(index) => index,
+				Err(reason) => {
+					report.index_failures.push(IndexFailure { path, reason });
+					continue;
+				}
+			};
+
+			// Synonym entries point to the synonym table rather than file data.
+			for (hash, metadata) in index.entries().filter(|(_, meta)| !meta.is_synonym) {
+				let dat = self.install.build_file_path(
+					repository,
+					category,
+					chunk,
+					&format!("dat{}", metadata.data_file_id),
+				)?;
+				dats.entry(dat)
+					.or_default()
+					.entry(metadata.offset.into())
+					.or_default()
+					.push(hash);
+			}
+		}
+
+		let (present, missing): (Vec<_>, Vec<_>) =
+			dats.into_iter().partition(|(path, _)| path.exists());
+		report.missing_dat_files = missing.into_iter().map(|(path, _)| path).collect();
+
+		// Check each .dat file on a pool of workers.
+		let queue = Mutex::new(present.into_iter());
+		let results = Mutex::new(Vec::new());
+		thread::scope(|scope| {
+			for _ in 0..self.parallelism.get() {
+				scope.spawn(|| loop {
+					let next = queue.lock().unwrap().next();
+					let (path, files) = match next {
+						Some(job) => job,
+						None => break,
+					};
+					let result = check_dat(&path, &files, self.install.platform);
+					results.lock().unwrap().push((path, result));
+				});
+			}
+		});
+
+		let mut results = results.into_inner().unwrap();
+		results.sort_by(|a, b| a.0.cmp(&b.0));
+		for (path, dat) in results {
+			report.files_checked += dat.files_checked;
+			report.failures.extend(dat.failures);
+			report.orphaned_regions.extend(dat.orphaned_regions);
+
+			// Files can be removed between listing and checking.
+			if dat.missing {
+				report.missing_dat_files.push(path);
+			} else if let Some(reason) = dat.failure {
+				report.dat_failures.push(DatFailure { path, reason });
+			}
+		}
+		report.missing_dat_files.sort();
+
+		Ok(report)
+	}
+}
+
+fn read_index(path: &Path) -> Result<Index1, String> {
+	let buffer = fs::read(path).map_err(|error| error.to_string())?;
+	Index1::read(&mut io::Cursor::new(buffer)).map_err(|error| error.to_string())
+}
+
+fn check_dat(path: &Path, files: &DatFiles, platform: Platform) -> DatReport {
+	let mut report = DatReport::default();
+
+	let file = match fs::File::open(path) {
+		Ok(file) => file,
+		Err(error) if error.kind() == io::ErrorKind::NotFound => {
+			report.missing = true;
+			return report;
+		}
+		Err(error) => {
+			report.failure = Some(error.to_string());
+			return report;
+		}
+	};
+
+	if let Err(reason) = scan_dat(
+		&mut BufReader::new(file),
+		path,
+		files,
+		platform,
+		&mut report,
+	) {
+		report.failure = Some(reason);
+	}
+
+	report
+}
+
+fn scan_dat<R: Read + Seek>(
+	reader: &mut R,
+	path: &Path,
+	files: &DatFiles,
+	platform: Platform,
+	report: &mut DatReport,
+) -> Result<(), String> {
+	let length = reader
+		.seek(SeekFrom::End(0))
+		.map_err(|error| error.to_string())?;
+
+	let mut extents = vec![];
+
+	let mut offsets = files.keys().copied().peekable();
+	while let Some(offset) = offsets.next() {
+		// Files can't extend past the next referenced file.
+		let limit = offsets.peek().copied().unwrap_or(length).min(length);
+		report.files_checked += 1;
+
+		let result = match offset < length {
+			true => {
+				let mut read_file = || {
+					reader.seek(SeekFrom::Start(offset))?;
+					let reader = (&mut *reader).take_seekable(limit - offset)?;
+					Ok(check_file(&mut Tracked::new(reader), platform))
+				};
+				read_file().unwrap_or_else(|error: io::Error| Err(error.to_string()))
+			}
+			false => Err(format!(
+				"offset is beyond the end of the file ({length} bytes)"
+			)),
+		};
+
+		match result {
+			Ok(size) => extents.push((offset, offset + align(size))),
+			Err(reason) => {
+				report.failures.push(FileFailure {
+					dat: path.to_path_buf(),
+					offset,
+					hashes: files[&offset].clone(),
+					reason,
+				});
+				// Without a readable file, the extent is unknown - assume it fills
+				// the space available to it.
+				extents.push((offset, limit));
+			}
+		}
+	}
+
+	// Anything between the known extents that isn't marked as empty is orphaned.
+	let endian = platform.endian();
+	let mut position =
+		data_start(reader, endian).map_err(|error| format!("invalid dat header: {error}"))?;
+	for (start, end) in extents.into_iter().chain([(length, length)]) {
+		if start > position {
+			let orphans = find_orphans(reader, endian, path, position, start)
+				.map_err(|error| format!("failed to scan for orphaned data: {error}"))?;
+			report.orphaned_regions.extend(orphans);
+		}
+		position = position.max(end);
+	}
+
+	Ok(())
+}
+
+// Read the file at the start of the reader, returning the number of bytes it
+// occupies in the .dat file.
+fn check_file<R: Read + Seek>(reader: &mut Tracked<R>, platform: Platform) -> Result<u64, String> {
+	let header = reader
+		.read_type::<Header>(platform.endian())
+		.map_err(|error| format!("invalid header: {error}"))?;
+
+	// Empty files store their raw data directly after the header.
+	if let FileKind::Empty = header.kind {
+		return Ok(u64::from(header.size) + u64::from(header.raw_file_size));
+	}
+
+	reader
+		.seek(SeekFrom::Start(0))
+		.map_err(|error| error.to_string())?;
+	let mut file =
+		File::with_platform(&mut *reader, platform).map_err(|error| error.to_string())?;
+	let size = io::copy(&mut file, &mut io::sink())
+		.map_err(|error| format!("failed to decompress blocks: {error}"))?;
+	drop(file);
+
+	if size != u64::from(header.raw_file_size) {
+		return Err(format!(
+			"decompressed to {size} bytes, expected {}",
+			header.raw_file_size
+		));
+	}
+
+	Ok(reader.end)
+}
+
+// .dat files start with a SqPack header, followed by a data header. The size
+// of each is stored within themselves.
+fn data_start<R: Read + Seek>(reader: &mut R, endian: Endian) -> Result<u64> {
+	reader.seek(SeekFrom::Start(12))?;
+	let sqpack_size = reader.read_type::<u32>(endian)?;
+	reader.seek(SeekFrom::Start(sqpack_size.into()))?;
+	let data_size = reader.read_type::<u32>(endian)?;
+	Ok(u64::from(sqpack_size) + u64::from(data_size))
+}
+
+fn find_orphans<R: Read + Seek>(
+	reader: &mut R,
+	endian: Endian,
+	path: &Path,
+	start: u64,
+	end: u64,
+) -> Result<Vec<OrphanedRegion>> {
+	let mut orphans = vec![];
+	let mut position = start;
+	while position < end {
+		// Regions freed by the patcher are marked as a single empty entry, with
+		// a header containing the number of blocks it spans.
+		reader.seek(SeekFrom::Start(position))?;
+		let header = reader.read_type::<[u32; 4]>(endian).ok();
+		match header {
+			Some([size, 0, 0, blocks]) if u64::from(size) == BLOCK_SIZE => {
+				position += (u64::from(blocks) + 1) * BLOCK_SIZE;
+			}
+			_ => {
+				orphans.push(OrphanedRegion {
+					dat: path.to_path_buf(),
+					offset: position,
+					size: end - position,
+				});
+				break;
+			}
+		}
+	}
+
+	Ok(orphans)
+}
+
+fn align(size: u64) -> u64 {
+	(size + BLOCK_SIZE - 1) & !(BLOCK_SIZE - 1)
+}
+
+// Reader adapter recording the furthest position read from the inner reader.
+struct Tracked<R> {
+	inner: R,
+	position: u64,
+	end: u64,
+}
+
+impl<R> Tracked<R> {
+	fn new(inner: R) -> Self {
+		Self {
+			inner,
+			position: 0,
+			end: 0,
+		}
+	}
+}
+
+impl<R: Read> Read for Tracked<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let count = self.inner.read(buf)?;
+		self.position += u64::try_from(count).unwrap();
+		self.end = self.end.max(self.position);
+		Ok(count)
+	}
+}
+
+impl<R: Seek> Seek for Tracked<R> {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		self.position = self.inner.seek(pos)?;
+		Ok(self.position)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::io::Cursor;
+
+	use super::*;
+
+	struct TestInstall(tempfile::TempDir);
+
+	impl TestInstall {
+		fn new() -> Self {
+			let directory = tempfile::tempdir().unwrap();
+			fs::create_dir_all(directory.path().join("game/sqpack/ffxiv")).unwrap();
+			Self(directory)
+		}
+
+		fn path(&self) -> &Path {
+			self.0.path()
+		}
+
+		fn write(&self, file: &str, data: &[u8]) -> PathBuf {
+			let path = self.path().join("game/sqpack/ffxiv").join(file);
+			fs::write(&path, data).unwrap();
+			path
+		}
+	}
+
+	// Index entries are (hash, dat file, offset).
+	fn index(entries: &[(u64, u32, u32)]) -> Vec<u8> {
+		let mut bytes = vec![0; 2048];
+		bytes[..8].copy_from_slice(b"SqPack\0\0");
+		bytes[12..16].copy_from_slice(&1024u32.to_le_bytes());
+		let data_size = u32::try_from(entries.len() * 16).unwrap();
+		bytes[1032..1036].copy_from_slice(&2048u32.to_le_bytes());
+		bytes[1036..1040].copy_from_slice(&data_size.to_le_bytes());
+
+		for (hash, dat, offset) in entries {
+			bytes.extend_from_slice(&hash.to_le_bytes());
+			bytes.extend_from_slice(&(dat << 1 | offset >> 3).to_le_bytes());
+			bytes.extend_from_slice(&[0; 4]);
+		}
+		bytes
+	}
+
+	// A .dat file with 1kb SqPack and data headers.
+	fn dat() -> Vec<u8> {
+		let mut bytes = vec![0; 2048];
+		bytes[12..16].copy_from_slice(&1024u32.to_le_bytes());
+		bytes[1024..1028].copy_from_slice(&1024u32.to_le_bytes());
+		bytes
+	}
+
+	// A standard file with a single uncompressed block, reporting `raw_size` as
+	// the size of the file.
+	fn file(data: &[u8], raw_size: u32) -> Vec<u8> {
+		let size = u32::try_from(data.len()).unwrap();
+		let mut bytes = vec![];
+		for value in [128, 2, raw_size, 0, 0, 1, 0] {
+			bytes.extend_from_slice(&u32::to_le_bytes(value));
+		}
+		bytes.extend_from_slice(&u16::try_from(16 + size).unwrap().to_le_bytes());
+		bytes.extend_from_slice(&u16::try_from(size).unwrap().to_le_bytes());
+		bytes.resize(128, 0);
+
+		for value in [16, 0, 32_000, size] {
+			bytes.extend_from_slice(&u32::to_le_bytes(value));
+		}
+		bytes.extend_from_slice(data);
+		bytes.resize(
+			align(bytes.len().try_into().unwrap()).try_into().unwrap(),
+			0,
+		);
+		bytes
+	}
+
+	#[test]
+	fn check_install() {
+		let install = TestInstall::new();
+
+		// dat0 contains a valid file, an orphaned block, and a corrupt file.
+		let mut dat0 = dat();
+		dat0.extend(file(&[1; 100], 100));
+		assert_eq!(dat0.len(), 2304);
+		dat0.extend([0xFF; 128]);
+		dat0.extend(file(&[2; 100], 200));
+		let dat0 = install.write("0a0000.win32.dat0", &dat0);
+		// dat2 is too short to contain its own header.
+		let dat2 = install.write("0a0000.win32.dat2", &[0; 8]);
+
+		install.write(
+			"0a0000.win32.index",
+			&index(&[(1, 0, 2048), (2, 0, 2432), (3, 1, 2048), (4, 2, 2048)]),
+		);
+		let broken_index = install.write("040000.win32.index", &[0; 16]);
+
+		let report = Install::at(install.path())
+			.integrity_checker()
+			.with_parallelism(NonZeroUsize::new(2).unwrap())
+			.check()
+			.unwrap();
+
+		assert!(!report.is_ok());
+		assert_eq!(report.files_checked, 3);
+
+		let failures = report
+			.failures
+			.iter()
+			.map(|failure| (&failure.dat, failure.offset, &failure.hashes[..]))
+			.collect::<Vec<_>>();
+		assert_eq!(failures, [(&dat0, 2432, &[2][..]), (&dat2, 2048, &[4][..])]);
+		assert_eq!(
+			report.failures[0].reason,
+			"decompressed to 100 bytes, expected 200"
+		);
+
+		assert_eq!(
+			report.orphaned_regions,
+			[OrphanedRegion {
+				dat: dat0,
+				offset: 2304,
+				size: 128,
+			}]
+		);
+		assert_eq!(
+			report.missing_dat_files,
+			[install.path().join("game/sqpack/ffxiv/0a0000.win32.dat1")]
+		);
+
+		// Dats that can't be read in full don't abort the rest of the check.
+		assert_eq!(report.dat_failures.len(), 1);
+		assert_eq!(report.dat_failures[0].path, dat2);
+		assert!(report.dat_failures[0]
+			.reason
+			.starts_with("invalid dat header"));
+
+		assert_eq!(report.index_failures.len(), 1);
+		assert_eq!(report.index_failures[0].path, broken_index);
+	}
+
+	#[test]
+	fn check_valid_install() {
+		let install = TestInstall::new();
+
+		let mut dat0 = dat();
+		dat0.extend(file(&[1; 100], 100));
+		dat0.extend(file(&[2; 300], 300));
+		install.write("0a0000.win32.dat0", &dat0);
+		// Files referenced by multiple index entries are only checked once.
+		install.write(
+			"0a0000.win32.index",
+			&index(&[(1, 0, 2048), (2, 0, 2304), (3, 0, 2304)]),
+		);
+
+		let report = Install::at(install.path())
+			.integrity_checker()
+			.check()
+			.unwrap();
+
+		assert!(report.is_ok(), "{report:?}");
+		assert_eq!(report.files_checked, 2);
+	}
+
+	#[test]
+	fn check_file_sizes() {
+		let check =
+			|data: Vec<u8>| check_file(&mut Tracked::new(Cursor::new(data)), Platform::Win32);
+
+		// Files occupy their header, block header, and payload.
+		assert_eq!(check(file(&[1; 100], 100)), Ok(128 + 16 + 100));
+		assert!(check(file(&[1; 100], 50))
+			.unwrap_err()
+			.starts_with("decompressed to 100 bytes"));
+		assert!(check(vec![0; 8]).unwrap_err().starts_with("invalid header"));
+	}
+
+	#[test]
+	fn orphans_skip_empty_blocks() {
+		let mut data = vec![0u8; 1024];
+		// Empty entry spanning 2 blocks at 0, unmarked data from 256 onwards.
+		data[0..4].copy_from_slice(&128u32.to_le_bytes());
+		data[12..16].copy_from_slice(&1u32.to_le_bytes());
+		data[256] = 0xFF;
+
+		let path = PathBuf::from("test.dat0");
+		let orphans = find_orphans(&mut Cursor::new(data), Endian::Little, &path, 0, 1024).unwrap();
+
+		assert_eq!(
+			orphans,
+			vec![OrphanedRegion {
+				dat: path,
+				offset: 256,
+				size: 768,
+			}]
+		);
+	}
+}
@@ -1,3 +1,6 @@
+mod integrity;
+mod search;
+
 use std::{
 	ffi::OsStr,
 	fs,
@@ -10,28 +13,20 @@ use crate::{
 	utility::{TakeSeekable, TakeSeekableExt},
 };
 
-use super::{Location, Resource};
-
-const TRY_PATHS: &[&str] = &[
-	r"C:\SquareEnix\FINAL FANTASY XIV - A Realm Reborn",
-	r"C:\Program Files (x86)\Steam\steamapps\common\FINAL FANTASY XIV Online",
-	r"C:\Program Files (x86)\Steam\steamapps\common\FINAL FANTASY XIV - A Realm Reborn",
-	r"C:\Program Files (x86)\FINAL FANTASY XIV - A Realm Reborn",
-	r"C:\Program Files (x86)\SquareEnix\FINAL FANTASY XIV - A Realm Reborn",
-];
+use super::{Location, Platform, Resource};
 
-const WSL_PREFIX: &[&str] = &["/mnt", "c"];
+pub use {
+	integrity::{
+		DatFailure, FileFailure, IndexFailure, IntegrityChecker, IntegrityReport, OrphanedRegion,
+	},
+	search::{
+		InstallSearch, SearchAttempt, SearchOutcome, SearchResult, SearchSource,
+		INSTALL_PATH_VARIABLE,
+	},
+};
 
 const SQPACK_PATH: &[&str] = &["game", "sqpack"];
 
-#[allow(dead_code)]
-#[derive(Debug)]
-enum Platform {
-	Win32 = 0,
-	PS3 = 1,
-	PS4 = 2,
-}
-
 /// SqPack resource for reading game data from an on-disk FFXIV installation.
 #[derive(Debug)]
 pub struct Install {
@@ -42,12 +37,16 @@ pub struct Install {
 
 impl Install {
 	/// Search for a FFXIV install in common locations, configuring a resource
-	/// instance with the found install, if any.
+	/// instance with the found install, if any. See [`InstallSearch`] for
+	/// configuration and diagnostics of the search.
 	pub fn search() -> Option<Self> {
-		Some(Self::at(&find_install()?))
+		InstallSearch::new().search().install
 	}
 
 	/// Configure a resource instance with an installation of FFXIV at the specified path.
+	///
+	/// The platform of the installation is detected from the files on disk,
+	/// falling back to Win32 if it cannot be determined.
 	pub fn at(path: &Path) -> Self {
 		let sqpack_path = path
 			.iter()
@@ -55,14 +54,31 @@ impl Install {
 			.collect::<PathBuf>();
 
 		let repositories = find_repositories(&sqpack_path);
+		let platform = detect_platform(&sqpack_path, &repositories).unwrap_or(Platform::Win32);
 
 		Self {
 			path: sqpack_path,
 			repositories,
-			platform: Platform::Win32,
+			platform,
 		}
 	}
 
+	/// Override the platform of the installation.
+	pub fn with_platform(mut self, platform: Platform) -> Self {
+		self.platform = platform;
+		self
+	}
+
+	/// The platform of the installation.
+	pub fn platform(&self) -> Platform {
+		self.platform
+	}
+
+	/// Create a checker to validate the integrity of the files within this install.
+	pub fn integrity_checker(&self) -> IntegrityChecker<'_> {
+		IntegrityChecker::new(self)
+	}
+
 	fn build_file_path(
 		&self,
 		repository: u8,
@@ -70,11 +86,7 @@ impl Install {
 		chunk: u8,
 		extension: &str,
 	) -> Result<PathBuf> {
-		let platform = match self.platform {
-			Platform::Win32 => "win32",
-			Platform::PS3 => todo!("PS3 platform"),
-			Platform::PS4 => todo!("PS4 platform"),
-		};
+		let platform = self.platform.name();
 
 		let file_name = format!("{category:02x}{repository:02x}{chunk:02x}.{platform}.{extension}");
 
@@ -87,6 +99,39 @@ impl Install {
 		Ok(file_path)
 	}
 
+	// List the index files within the install as (repository, category, chunk, path).
+	fn index_files(&self) -> Result<Vec<(u8, u8, u8, PathBuf)>> {
+		let suffix = format!(".{}.index", self.platform.name());
+
+		let mut files = vec![];
+		for (repository, name) in self.repositories.iter().enumerate() {
+			let name = match name {
+				Some(name) => name,
+				None => continue,
+			};
+
+			for entry in fs::read_dir(self.path.join(name))? {
+				let path = entry?.path();
+				let ids = path
+					.file_name()
+					.and_then(|file_name| file_name.to_str()?.strip_suffix(&suffix))
+					.filter(|ids| ids.len() == 6)
+					.and_then(|ids| {
+						let id =
+							|start: usize| u8::from_str_radix(ids.get(start..start + 2)?, 16).ok();
+						Some((id(0)?, id(4)?))
+					});
+
+				if let Some((category, chunk)) = ids {
+					files.push((repository.try_into().unwrap(), category, chunk, path));
+				}
+			}
+		}
+
+		files.sort();
+		Ok(files)
+	}
+
 	fn get_repository_name(&self, repository: u8) -> Result<&String> {
 		self.repositories
 			.get(usize::from(repository))
@@ -144,22 +189,6 @@ impl Resource for Install {
 	}
 }
 
-fn find_install() -> Option<PathBuf> {
-	TRY_PATHS
-		.iter()
-		.flat_map(|path| {
-			[
-				PathBuf::from(path),
-				WSL_PREFIX
-					.iter()
-					.copied()
-					.chain(path.split('\\').skip(1))
-					.collect::<PathBuf>(),
-			]
-		})
-		.find(|path| path.exists())
-}
-
 fn find_repositories(path: &Path) -> Vec<Option<String>> {
 	(0..=9)
 		.map(|index| {
@@ -173,6 +202,20 @@ fn find_repositories(path: &Path) -> Vec<Option<String>> {
 		.collect()
 }
 
+// Look for the platform name in the file names of the first available repository.
+fn detect_platform(path: &Path, repositories: &[Option<String>]) -> Option<Platform> {
+	let repository = repositories.iter().flatten().next()?;
+
+	fs::read_dir(path.join(repository))
+		.ok()?
+		.filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+		.find_map(|file_name| {
+			Platform::ALL
+				.into_iter()
+				.find(|platform| file_name.contains(&format!(".{}.", platform.name())))
+		})
+}
+
 fn read_index(path: PathBuf) -> Result<io::Cursor<Vec<u8>>> {
 	// Read the entire index into memory before returning - we typically need
 	// the full dataset anyway, and working directly on a File causes significant
@@ -185,3 +228,24 @@ fn read_index(path: PathBuf) -> Result<io::Cursor<Vec<u8>>> {
 	})?;
 	Ok(io::Cursor::new(buffer))
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn platform_detection() {
+		let directory = tempfile::tempdir().unwrap();
+		let sqpack_path = directory.path().join("game").join("sqpack");
+		fs::create_dir_all(sqpack_path.join("ffxiv")).unwrap();
+		fs::write(sqpack_path.join("ffxiv").join("0a0000.ps3.index"), []).unwrap();
+
+		let install = Install::at(directory.path());
+
+		assert_eq!(install.platform(), Platform::PS3);
+		assert_eq!(
+			install.build_file_path(0, 0x0a, 0, "dat0").unwrap(),
+			sqpack_path.join("ffxiv").join("0a0000.ps3.dat0")
+		);
+	}
+}
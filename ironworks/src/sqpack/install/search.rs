@@ -0,0 +1,294 @@
+use std::{
+	env,
+	ffi::OsStr,
+	path::{Path, PathBuf},
+};
+
+use super::{Install, SQPACK_PATH};
+
+/// Environment variable that, when set, overrides install discovery with the
+/// specified path.
+pub const INSTALL_PATH_VARIABLE: &str = "IRONWORKS_FFXIV_INSTALL";
+
+const TRY_PATHS: &[&str] = &[
+	r"C:\SquareEnix\FINAL FANTASY XIV - A Realm Reborn",
+	r"C:\Program Files (x86)\Steam\steamapps\common\FINAL FANTASY XIV Online",
+	r"C:\Program Files (x86)\Steam\steamapps\common\FINAL FANTASY XIV - A Realm Reborn",
+	r"C:\Program Files (x86)\FINAL FANTASY XIV - A Realm Reborn",
+	r"C:\Program Files (x86)\SquareEnix\FINAL FANTASY XIV - A Realm Reborn",
+];
+
+const WSL_PREFIX: &[&str] = &["/mnt", "c"];
+
+// Paths relative to the user's home directory.
+const XLCORE_PATH: &[&str] = &[".xlcore", "ffxiv"];
+const WINE_PREFIXES: &[&[&str]] = &[&[".wine"], &[".xlcore", "wineprefix"]];
+const LUTRIS_PREFIXES: &[&[&str]] = &[&["Games", "final-fantasy-xiv-online"]];
+const STEAM_ROOTS: &[&[&str]] = &[
+	&[".steam", "steam"],
+	&[".local", "share", "Steam"],
+	&[
+		".var",
+		"app",
+		"com.valvesoftware.Steam",
+		".local",
+		"share",
+		"Steam",
+	],
+];
+
+const STEAM_COMMON_PATHS: &[&str] = &[
+	"FINAL FANTASY XIV Online",
+	"FINAL FANTASY XIV - A Realm Reborn",
+];
+const STEAM_APP_ID: &str = "39210";
+
+/// Location an install search candidate was derived from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchSource {
+	/// The [`INSTALL_PATH_VARIABLE`] environment variable, or an explicit override.
+	Override,
+	/// A default Windows install location.
+	Windows,
+	/// A Windows install location, accessed from within WSL.
+	Wsl,
+	/// The default game directory of XIVLauncher.Core.
+	XivLauncherCore,
+	/// A Windows install location within a wine prefix.
+	WinePrefix,
+	/// A Windows install location within a Lutris-managed wine prefix.
+	Lutris,
+	/// A Steam library.
+	Steam,
+	/// A Windows install location within a Steam Proton prefix.
+	SteamCompatData,
+}
+
+/// Result of checking a single install search candidate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchOutcome {
+	/// The candidate path does not exist.
+	Missing,
+	/// The candidate path exists, but does not contain a `game/sqpack` directory.
+	NoSqPack,
+	/// The candidate path contains an install.
+	Found,
+}
+
+/// A candidate path checked during an install search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchAttempt {
+	/// Where the candidate was derived from.
+	pub source: SearchSource,
+	/// Path of the candidate install.
+	pub path: PathBuf,
+	/// Result of checking the candidate.
+	pub outcome: SearchOutcome,
+}
+
+/// Result of an install search.
+#[derive(Debug)]
+pub struct SearchResult {
+	/// The install that was found, if any.
+	pub install: Option<Install>,
+	/// Candidates that were checked, in order. The search stops at the first
+	/// candidate containing an install.
+	pub attempts: Vec<SearchAttempt>,
+}
+
+/// Configurable search for FFXIV installs in common locations.
+///
+/// By default, the override path is read from [`INSTALL_PATH_VARIABLE`], and
+/// the home directory from the `HOME` environment variable.
+#[derive(Debug, Clone)]
+pub struct InstallSearch {
+	override_path: Option<PathBuf>,
+	home: Option<PathBuf>,
+}
+
+impl InstallSearch {
+	/// Create a search configured from the environment.
+	pub fn new() -> Self {
+		Self {
+			override_path: env::var_os(INSTALL_PATH_VARIABLE)
+				.filter(|value| !value.is_empty())
+				.map(PathBuf::from),
+			home: env::var_os("HOME")
+				.filter(|value| !value.is_empty())
+				.map(PathBuf::from),
+		}
+	}
+
+	/// Set the override path. When set, it is the only candidate checked.
+	pub fn with_override(mut self, path: Option<PathBuf>) -> Self {
+		self.override_path = path;
+		self
+	}
+
+	/// Set the home directory used to find Linux launcher and prefix locations.
+	pub fn with_home(mut self, home: Option<PathBuf>) -> Self {
+		self.home = home;
+		self
+	}
+
+	/// List the candidates that will be checked, in order.
+	pub fn candidates(&self) -> Vec<(SearchSource, PathBuf)> {
+		if let Some(path) = &self.override_path {
+			return vec![(SearchSource::Override, path.clone())];
+		}
+
+		let mut candidates = vec![];
+
+		for path in TRY_PATHS {
+			if cfg!(windows) {
+				candidates.push((SearchSource::Windows, PathBuf::from(path)));
+			} else {
+				let path = WSL_PREFIX.iter().copied().chain(windows_segments(path));
+				candidates.push((SearchSource::Wsl, path.collect()));
+			}
+		}
+
+		let home = match &self.home {
+			Some(home) => home,
+			None => return candidates,
+		};
+
+		candidates.push((SearchSource::XivLauncherCore, join(home, XLCORE_PATH)));
+
+		for prefix in WINE_PREFIXES {
+			candidates.extend(prefix_paths(SearchSource::WinePrefix, &join(home, prefix)));
+		}
+
+		for prefix in LUTRIS_PREFIXES {
+			candidates.extend(prefix_paths(SearchSource::Lutris, &join(home, prefix)));
+		}
+
+		for root in STEAM_ROOTS {
+			let steamapps = join(home, root).join("steamapps");
+			for path in STEAM_COMMON_PATHS {
+				candidates.push((SearchSource::Steam, steamapps.join("common").join(path)));
+			}
+
+			let prefix = join(&steamapps, &["compatdata", STEAM_APP_ID, "pfx"]);
+			candidates.extend(prefix_paths(SearchSource::SteamCompatData, &prefix));
+		}
+
+		candidates
+	}
+
+	/// Run the search, stopping at the first candidate containing an install.
+	pub fn search(&self) -> SearchResult {
+		let mut attempts = vec![];
+
+		for (source, path) in self.candidates() {
+			let outcome = check_candidate(&path);
+			attempts.push(SearchAttempt {
+				source,
+				path: path.clone(),
+				outcome,
+			});
+
+			if outcome == SearchOutcome::Found {
+				return SearchResult {
+					install: Some(Install::at(&path)),
+					attempts,
+				};
+			}
+		}
+
+		SearchResult {
+			install: None,
+			attempts,
+		}
+	}
+}
+
+impl Default for InstallSearch {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+fn check_candidate(path: &Path) -> SearchOutcome {
+	if !path.exists() {
+		return SearchOutcome::Missing;
+	}
+
+	match join(path, SQPACK_PATH).is_dir() {
+		true => SearchOutcome::Found,
+		false => SearchOutcome::NoSqPack,
+	}
+}
+
+// Build the paths of the default Windows install locations within a wine prefix.
+fn prefix_paths(
+	source: SearchSource,
+	prefix: &Path,
+) -> impl Iterator<Item = (SearchSource, PathBuf)> + '_ {
+	TRY_PATHS.iter().map(move |path| {
+		let path =
+			windows_segments(path).fold(prefix.join("drive_c"), |path, segment| path.join(segment));
+		(source, path)
+	})
+}
+
+// Segments of a Windows path, excluding the drive.
+fn windows_segments(path: &str) -> impl Iterator<Item = &str> {
+	path.split('\\').skip(1)
+}
+
+fn join(base: &Path, segments: &[&str]) -> PathBuf {
+	base.iter().chain(segments.iter().map(OsStr::new)).collect()
+}
+
+#[cfg(test)]
+mod test {
+	use std::fs;
+
+	use super::*;
+
+	#[test]
+	fn search_fake_tree() {
+		let directory = tempfile::tempdir().unwrap();
+		let home = directory.path().to_path_buf();
+		let install = home
+			.join(".local/share/Steam/steamapps/compatdata")
+			.join(STEAM_APP_ID)
+			.join("pfx/drive_c/Program Files (x86)/SquareEnix/FINAL FANTASY XIV - A Realm Reborn");
+		fs::create_dir_all(install.join("game/sqpack/ffxiv")).unwrap();
+		// An XIVLauncher.Core directory without any game files.
+		fs::create_dir_all(home.join(".xlcore/ffxiv")).unwrap();
+
+		let search = InstallSearch::new()
+			.with_override(None)
+			.with_home(Some(home.clone()));
+		let result = search.search();
+
+		assert!(result.install.is_some());
+		let last = result.attempts.last().unwrap();
+		assert_eq!(last.source, SearchSource::SteamCompatData);
+		assert_eq!(last.path, install);
+		assert!(result.attempts.iter().any(|attempt| {
+			attempt.source == SearchSource::XivLauncherCore
+				&& attempt.outcome == SearchOutcome::NoSqPack
+		}));
+	}
+
+	#[test]
+	fn override_is_exclusive() {
+		let path = PathBuf::from("/nonexistent/ffxiv");
+		let result = InstallSearch::new()
+			.with_override(Some(path.clone()))
+			.search();
+
+		assert!(result.install.is_none());
+		assert_eq!(
+			result.attempts,
+			vec![SearchAttempt {
+				source: SearchSource::Override,
+				path,
+				outcome: SearchOutcome::Missing,
+			}]
+		);
+	}
+}
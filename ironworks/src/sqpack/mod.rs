@@ -4,6 +4,7 @@ mod block;
 mod file;
 mod index;
 mod install;
+mod platform;
 mod resource;
 mod sqpack;
 
@@ -11,14 +12,114 @@ pub use {
 	block::{BlockMetadata, BlockPayload, BlockStream},
 	file::File,
 	index::Location,
-	install::Install,
+	install::{
+		DatFailure, FileFailure, IndexFailure, Install, InstallSearch, IntegrityChecker,
+		IntegrityReport, OrphanedRegion, SearchAttempt, SearchOutcome, SearchResult, SearchSource,
+		INSTALL_PATH_VARIABLE,
+	},
+	platform::Platform,
 	resource::Resource,
 	sqpack::SqPack,
 };
 
+#[cfg(feature = "zipatch")]
+pub(crate) use index::path_hash;
+
 #[cfg(test)]
 mod test {
-	use super::*;
+	use std::io::{Cursor, Read};
+
+	use binrw::BinRead;
+
+	use crate::error::{Error, ErrorValue, Result};
+
+	use super::{
+		index::{path_hash, Index1},
+		*,
+	};
+
+	// PS3 data is stored big-endian throughout, aside from the SqPack magic and
+	// platform ID.
+	fn ps3_index(hash: u64, offset: u32) -> Vec<u8> {
+		let mut bytes = vec![0; 2048];
+		bytes[..8].copy_from_slice(b"SqPack\0\0");
+		bytes[8] = 1;
+		bytes[12..16].copy_from_slice(&1024u32.to_be_bytes());
+		bytes[1032..1036].copy_from_slice(&2048u32.to_be_bytes());
+		bytes[1036..1040].copy_from_slice(&16u32.to_be_bytes());
+
+		bytes.extend_from_slice(&hash.to_be_bytes());
+		bytes.extend_from_slice(&(offset >> 3).to_be_bytes());
+		bytes.extend_from_slice(&[0; 4]);
+		bytes
+	}
+
+	// A standard file with a single uncompressed block.
+	fn ps3_file(data: &[u8]) -> Vec<u8> {
+		let size = u32::try_from(data.len()).unwrap();
+		let mut bytes = vec![];
+		for value in [128, 2, size, 0, 0, 1, 0] {
+			bytes.extend_from_slice(&u32::to_be_bytes(value));
+		}
+		bytes.extend_from_slice(&u16::try_from(16 + size).unwrap().to_be_bytes());
+		bytes.extend_from_slice(&u16::try_from(size).unwrap().to_be_bytes());
+		bytes.resize(128, 0);
+
+		for value in [16, 0, 32_000, size] {
+			bytes.extend_from_slice(&u32::to_be_bytes(value));
+		}
+		bytes.extend_from_slice(data);
+		bytes
+	}
+
+	struct Ps3Resource {
+		index: Vec<u8>,
+		dat: Vec<u8>,
+	}
+
+	impl Resource for Ps3Resource {
+		fn version(&self, _repository: u8) -> Result<String> {
+			Ok("test".into())
+		}
+
+		type Index = Cursor<Vec<u8>>;
+		fn index(&self, _repository: u8, _category: u8, chunk: u8) -> Result<Self::Index> {
+			match chunk {
+				0 => Ok(Cursor::new(self.index.clone())),
+				_ => Err(Error::NotFound(ErrorValue::Other(format!("chunk {chunk}")))),
+			}
+		}
+
+		type Index2 = Cursor<Vec<u8>>;
+		fn index2(&self, _repository: u8, _category: u8, chunk: u8) -> Result<Self::Index2> {
+			Err(Error::NotFound(ErrorValue::Other(format!("chunk {chunk}"))))
+		}
+
+		type File = Cursor<Vec<u8>>;
+		fn file(&self, _repository: u8, _category: u8, location: Location) -> Result<Self::File> {
+			assert_eq!(location.platform(), Platform::PS3);
+			let offset = usize::try_from(location.offset()).unwrap();
+			Ok(Cursor::new(self.dat[offset..].to_vec()))
+		}
+	}
+
+	#[test]
+	fn ps3_data() {
+		let path = "exd/root.exl";
+		let hash = path_hash(path).unwrap();
+		let index = ps3_index(hash, 2048);
+		let dat = [vec![0; 2048], ps3_file(b"EXLT")].concat();
+
+		let index1 = Index1::read(&mut Cursor::new(index.clone())).unwrap();
+		assert_eq!(index1.platform(), Platform::PS3);
+		let (metadata, _) = index1.find(path).unwrap();
+		assert_eq!((metadata.data_file_id, metadata.offset), (0, 2048));
+
+		let sqpack = SqPack::new(Ps3Resource { index, dat });
+		let mut data = vec![];
+		sqpack.file(path).unwrap().read_to_end(&mut data).unwrap();
+		assert_eq!(data, b"EXLT");
+	}
 
 	#[test]
 	fn test_send() {
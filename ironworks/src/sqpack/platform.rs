@@ -0,0 +1,55 @@
+use binrw::Endian;
+
+use crate::error::{Error, ErrorValue};
+
+/// Platform that a set of SqPack files was built for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+	/// Windows, and all platforms sharing its data, such as macOS and Linux.
+	Win32,
+	/// PlayStation 3. Data for this platform is stored big-endian.
+	PS3,
+	/// PlayStation 4 and 5.
+	PS4,
+}
+
+impl Platform {
+	pub(crate) const ALL: [Self; 3] = [Self::Win32, Self::PS3, Self::PS4];
+
+	/// The name of the platform, as used in SqPack file names, i.e. `000000.win32.index`.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Win32 => "win32",
+			Self::PS3 => "ps3",
+			Self::PS4 => "ps4",
+		}
+	}
+
+	pub(crate) fn endian(&self) -> Endian {
+		match self {
+			Self::PS3 => Endian::Big,
+			Self::Win32 | Self::PS4 => Endian::Little,
+		}
+	}
+
+	pub(crate) fn is_big_endian(&self) -> bool {
+		matches!(self, Self::PS3)
+	}
+}
+
+impl TryFrom<u8> for Platform {
+	type Error = Error;
+
+	// IDs as stored in the header of SqPack files.
+	fn try_from(value: u8) -> Result<Self, Self::Error> {
+		match value {
+			0 => Ok(Self::Win32),
+			1 => Ok(Self::PS3),
+			2 => Ok(Self::PS4),
+			other => Err(Error::Invalid(
+				ErrorValue::Other("SqPack platform".into()),
+				format!("unknown platform ID {other}"),
+			)),
+		}
+	}
+}
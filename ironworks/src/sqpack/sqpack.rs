@@ -79,10 +79,11 @@ impl<R: sqpack::Resource> SqPack<R> {
 			.find(&path)?;
 
 		// Build a File representation.
+		let platform = location.platform();
 		let dat = self.resource.file(repository, category, location)?;
 
 		// TODO: Cache files? Tempted to say it's the IW struct's responsibility. Is it even possible here with streams?
-		File::new(dat)
+		File::with_platform(dat, platform)
 	}
 
 	fn path_metadata(&self, path: &str) -> Result<(u8, u8)> {
@@ -0,0 +1,13 @@
+use crate::error::{Error, ErrorValue, Result};
+
+/// Check that a section size will fit in the integer type used to store it on
+/// disk, to avoid silently truncating lengths when writing files.
+pub fn check_size<T: TryFrom<usize>>(name: &str, size: usize) -> Result<()> {
+	match T::try_from(size) {
+		Ok(_) => Ok(()),
+		Err(_) => Err(Error::Invalid(
+			ErrorValue::Other(name.into()),
+			format!("size {size} does not fit in {}", std::any::type_name::<T>()),
+		)),
+	}
+}
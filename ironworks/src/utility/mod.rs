@@ -1,7 +1,11 @@
+#[cfg(any(feature = "mdl", feature = "mtrl"))]
+mod check_size;
 mod hash_map_cache;
 mod option_cache;
 mod take_seekable;
 
+#[cfg(any(feature = "mdl", feature = "mtrl"))]
+pub use check_size::check_size;
 pub use {
 	hash_map_cache::{HashMapCache, HashMapCacheExt},
 	option_cache::{OptionCache, OptionCacheExt},
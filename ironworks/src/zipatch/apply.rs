@@ -0,0 +1,626 @@
+use std::{
+	fs,
+	io::{self, Read, Seek, SeekFrom, Write},
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	error::{Error, ErrorValue, Result},
+	file::patch::{
+		AddCommand, ApplyChunk, Chunk, DeleteCommand, ExpandCommand, FileOperation,
+		FileOperationCommand, HeaderFileKind, HeaderKind, HeaderUpdateCommand, OptionKind,
+		SqPackChunk, SqPackFile, TargetPlatform, ZiPatch as ZiPatchFile,
+	},
+	sqpack,
+};
+
+// SqPack data is laid out in 128-byte blocks.
+const BLOCK_SIZE: u64 = 1 << 7;
+
+const ZEROES: [u8; 4096] = [0; 4096];
+
+/// Progress through the application of a patch file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ApplyProgress {
+	/// Number of chunks that have been fully applied.
+	pub chunks: usize,
+	/// Number of bytes written to the target installation.
+	pub bytes_written: u64,
+}
+
+/// Applies ZiPatch files to an on-disk game installation, performing the
+/// operations described by each chunk within the patch.
+#[derive(Debug)]
+pub struct PatchApplier {
+	game_directory: PathBuf,
+	resume: ApplyProgress,
+	version: Option<(u8, String)>,
+}
+
+impl PatchApplier {
+	/// Create an applier targeting the specified game directory. This is the
+	/// directory containing `ffxivgame.ver` and the `sqpack` folder.
+	pub fn new(game_directory: impl Into<PathBuf>) -> Self {
+		Self {
+			game_directory: game_directory.into(),
+			resume: ApplyProgress::default(),
+			version: None,
+		}
+	}
+
+	/// Resume a previously interrupted application. Chunks reported as applied in
+	/// the provided progress will be skipped.
+	pub fn resume_from(mut self, progress: ApplyProgress) -> Self {
+		self.resume = progress;
+		self
+	}
+
+	/// Set the version of the repository the patch belongs to. Once the patch
+	/// has been fully applied, the repository's `.ver` file will be updated to
+	/// this version and verified, matching the behavior of the official patcher.
+	pub fn with_version(mut self, repository: u8, version: impl Into<String>) -> Self {
+		self.version = Some((repository, version.into()));
+		self
+	}
+
+	/// Apply the provided patch to the game directory.
+	pub fn apply(&self, patch: &ZiPatchFile) -> Result<ApplyProgress> {
+		self.apply_with_progress(patch, |_| {})
+	}
+
+	/// Apply the provided patch to the game directory, calling `on_progress` after
+	/// each chunk has been applied. The reported progress can be passed to
+	/// [`resume_from`](Self::resume_from) to continue an interrupted application.
+	pub fn apply_with_progress(
+		&self,
+		patch: &ZiPatchFile,
+		mut on_progress: impl FnMut(ApplyProgress),
+	) -> Result<ApplyProgress> {
+		let mut state = ApplyState {
+			patch,
+			platform: "win32",
+			ignore_missing: false,
+			progress: self.resume,
+		};
+
+		let mut complete = false;
+		for (index, chunk) in patch.chunks().enumerate() {
+			let chunk = chunk?;
+
+			// Chunks prior to the resume point have already been applied, however
+			// some of them configure state that later chunks depend on.
+			let skip = index < self.resume.chunks;
+
+			match chunk {
+				Chunk::Apply(chunk) => state.apply_option(&chunk),
+				Chunk::SqPack(SqPackChunk::TargetInfo(command)) => {
+					state.platform = platform_name(command.platform())?;
+				}
+				Chunk::EndOfFile => {
+					complete = true;
+					break;
+				}
+				chunk if !skip => self.apply_chunk(&mut state, chunk)?,
+				_ => {}
+			}
+
+			if !skip {
+				state.progress.chunks = index + 1;
+				on_progress(state.progress);
+			}
+		}
+
+		// Incomplete patches leave the installation between versions.
+		if let (true, Some((repository, version))) = (complete, &self.version) {
+			open_file(&self.version_path(*repository), true)?.write_all(version.as_bytes())?;
+			self.verify_version(*repository, version)?;
+		}
+
+		Ok(state.progress)
+	}
+
+	/// Verify that the `.ver` file of the specified repository matches the
+	/// expected version. Versions do not include the leading patch kind character
+	/// present in patch file names.
+	pub fn verify_version(&self, repository: u8, expected: &str) -> Result<()> {
+		let path = self.version_path(repository);
+		let actual = fs::read_to_string(&path)?;
+		if actual.trim() != expected {
+			return Err(Error::Invalid(
+				ErrorValue::Path(path.to_string_lossy().into_owned()),
+				format!("expected version {expected}, found {}", actual.trim()),
+			));
+		}
+
+		Ok(())
+	}
+
+	fn version_path(&self, repository: u8) -> PathBuf {
+		match repository {
+			0 => self.game_directory.join("ffxivgame.ver"),
+			repository => {
+				let name = repository_name(repository);
+				self.game_directory
+					.join("sqpack")
+					.join(&name)
+					.join(format!("{name}.ver"))
+			}
+		}
+	}
+
+	fn apply_chunk(&self, state: &mut ApplyState, chunk: Chunk) -> Result<()> {
+		match chunk {
+			Chunk::AddDirectory(chunk) => {
+				fs::create_dir_all(self.game_directory.join(chunk.path()))?;
+			}
+
+			Chunk::DeleteDirectory(chunk) => {
+				let result = fs::remove_dir(self.game_directory.join(chunk.path()));
+				state.check_missing(result)?;
+			}
+
+			Chunk::SqPack(SqPackChunk::Add(command)) => self.apply_add(state, &command)?,
+			Chunk::SqPack(SqPackChunk::Delete(command)) => self.apply_delete(state, &command)?,
+			Chunk::SqPack(SqPackChunk::Expand(command)) => self.apply_expand(state, &command)?,
+			Chunk::SqPack(SqPackChunk::HeaderUpdate(command)) => {
+				self.apply_header_update(state, &command)?
+			}
+			Chunk::SqPack(SqPackChunk::FileOperation(command)) => {
+				self.apply_file_operation(state, &command)?
+			}
+
+			// Remaining chunks are metadata, or unused by the game's own patcher.
+			_ => {}
+		}
+
+		Ok(())
+	}
+
+	fn apply_add(&self, state: &mut ApplyState, command: &AddCommand) -> Result<()> {
+		let path = self.sqpack_path(state, command.file(), HeaderFileKind::Dat);
+		let mut file = open_file(&path, false)?;
+		file.seek(SeekFrom::Start(command.target_offset().into()))?;
+
+		let mut stream = state.patch.stream().lock().unwrap();
+		stream.seek(SeekFrom::Start(command.source_offset()))?;
+		let written = io::copy(
+			&mut (&mut *stream).take(command.data_size().into()),
+			&mut file,
+		)?;
+		drop(stream);
+
+		write_zeroes(&mut file, command.delete_size().into())?;
+
+		state.progress.bytes_written += written + u64::from(command.delete_size());
+		Ok(())
+	}
+
+	fn apply_delete(&self, state: &mut ApplyState, command: &DeleteCommand) -> Result<()> {
+		let path = self.sqpack_path(state, command.file(), HeaderFileKind::Dat);
+		let mut file = open_file(&path, false)?;
+		write_empty_blocks(
+			&mut file,
+			command.target_offset().into(),
+			command.delete_size().into(),
+		)?;
+		state.progress.bytes_written += u64::from(command.delete_size());
+		Ok(())
+	}
+
+	fn apply_expand(&self, state: &mut ApplyState, command: &ExpandCommand) -> Result<()> {
+		let path = self.sqpack_path(state, command.file(), HeaderFileKind::Dat);
+		let mut file = open_file(&path, false)?;
+		write_empty_blocks(
+			&mut file,
+			command.target_offset().into(),
+			command.delete_size().into(),
+		)?;
+		state.progress.bytes_written += u64::from(command.delete_size());
+		Ok(())
+	}
+
+	fn apply_header_update(
+		&self,
+		state: &mut ApplyState,
+		command: &HeaderUpdateCommand,
+	) -> Result<()> {
+		let path = self.sqpack_path(state, command.file(), command.file_kind());
+		let mut file = open_file(&path, false)?;
+
+		// The version header sits at the start of the file, with the dat/index
+		// header directly following it.
+		let target_offset = match command.header_kind() {
+			HeaderKind::Version => 0,
+			HeaderKind::Data | HeaderKind::Index => 1024,
+		};
+		file.seek(SeekFrom::Start(target_offset))?;
+
+		let mut stream = state.patch.stream().lock().unwrap();
+		stream.seek(SeekFrom::Start(command.offset()))?;
+		let written = io::copy(&mut (&mut *stream).take(command.size().into()), &mut file)?;
+
+		state.progress.bytes_written += written;
+		Ok(())
+	}
+
+	fn apply_file_operation(
+		&self,
+		state: &mut ApplyState,
+		command: &FileOperationCommand,
+	) -> Result<()> {
+		let path = self.game_directory.join(command.path().to_string());
+
+		match command.operation() {
+			FileOperation::AddFile(blocks) => {
+				// Writes starting at the beginning of a file replace it entirely.
+				let mut file = open_file(&path, command.target_offset() == 0)?;
+				file.seek(SeekFrom::Start(command.target_offset()))?;
+
+				let mut stream = state.patch.stream().lock().unwrap();
+				for block in blocks {
+					stream.seek(SeekFrom::Start(block.offset()))?;
+					let mut payload = sqpack::BlockPayload::new(
+						&mut *stream,
+						block.compressed_size(),
+						block.decompressed_size(),
+					);
+					state.progress.bytes_written += io::copy(&mut payload, &mut file)?;
+				}
+			}
+
+			FileOperation::DeleteFile => {
+				let result = fs::remove_file(&path);
+				state.check_missing(result)?;
+			}
+
+			FileOperation::MakeDirTree => fs::create_dir_all(&path)?,
+
+			FileOperation::RemoveAll => {
+				let repository = u8::try_from(command.repository_id()).map_err(|_| {
+					Error::Invalid(
+						ErrorValue::Other("patch file operation".into()),
+						format!("repository ID {} is out of range", command.repository_id()),
+					)
+				})?;
+				let name = repository_name(repository);
+				for folder in ["sqpack", "movie"] {
+					remove_all(&self.game_directory.join(folder).join(&name))?;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	fn sqpack_path(&self, state: &ApplyState, file: SqPackFile, kind: HeaderFileKind) -> PathBuf {
+		let repository = u8::try_from(file.sub_id() >> 8).unwrap();
+		let extension = match (kind, file.file_id()) {
+			(HeaderFileKind::Dat, id) => format!("dat{id}"),
+			(HeaderFileKind::Index, 0) => "index".to_string(),
+			(HeaderFileKind::Index, id) => format!("index{id}"),
+		};
+
+		self.game_directory
+			.join("sqpack")
+			.join(repository_name(repository))
+			.join(format!(
+				"{:02x}{:04x}.{}.{extension}",
+				file.main_id(),
+				file.sub_id(),
+				state.platform
+			))
+	}
+}
+
+struct ApplyState<'a> {
+	patch: &'a ZiPatchFile,
+	platform: &'static str,
+	ignore_missing: bool,
+	progress: ApplyProgress,
+}
+
+impl ApplyState<'_> {
+	fn apply_option(&mut self, chunk: &ApplyChunk) {
+		match chunk.option() {
+			OptionKind::IgnoreMissing => self.ignore_missing = chunk.value() != 0,
+			// Mismatches are not checked by the applier.
+			OptionKind::IgnoreMismatch => {}
+		}
+	}
+
+	fn check_missing(&self, result: io::Result<()>) -> Result<()> {
+		match result {
+			Err(error) if error.kind() == io::ErrorKind::NotFound && self.ignore_missing => Ok(()),
+			other => Ok(other?),
+		}
+	}
+}
+
+fn platform_name(platform: TargetPlatform) -> Result<&'static str> {
+	match platform {
+		TargetPlatform::Win32 => Ok("win32"),
+		TargetPlatform::Ps3 => Ok("ps3"),
+		TargetPlatform::Ps4 => Ok("ps4"),
+		other => Err(Error::Invalid(
+			ErrorValue::Other("patch target platform".into()),
+			format!("unsupported platform {other:?}"),
+		)),
+	}
+}
+
+fn repository_name(repository: u8) -> String {
+	match repository {
+		0 => "ffxiv".into(),
+		other => format!("ex{other}"),
+	}
+}
+
+fn open_file(path: &Path, truncate: bool) -> Result<fs::File> {
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent)?;
+	}
+
+	let file = fs::OpenOptions::new()
+		.read(true)
+		.write(true)
+		.create(true)
+		.truncate(truncate)
+		.open(path)?;
+
+	Ok(file)
+}
+
+fn write_zeroes(writer: &mut impl Write, size: u64) -> io::Result<()> {
+	let mut remaining = size;
+	while remaining > 0 {
+		let length = remaining.min(ZEROES.len().try_into().unwrap());
+		writer.write_all(&ZEROES[..usize::try_from(length).unwrap()])?;
+		remaining -= length;
+	}
+	Ok(())
+}
+
+// Blank out a region of a dat file, marking it as a single empty block entry.
+fn write_empty_blocks(file: &mut (impl Write + Seek), offset: u64, size: u64) -> io::Result<()> {
+	if size == 0 {
+		return Ok(());
+	}
+
+	file.seek(SeekFrom::Start(offset))?;
+	write_zeroes(file, size)?;
+
+	// Regions smaller than a single block have no room for a block header.
+	let block_count = u32::try_from(size / BLOCK_SIZE).unwrap();
+	if block_count == 0 {
+		return Ok(());
+	}
+
+	let header = [u32::try_from(BLOCK_SIZE).unwrap(), 0, 0, block_count - 1, 0];
+
+	file.seek(SeekFrom::Start(offset))?;
+	for value in header {
+		file.write_all(&value.to_le_bytes())?;
+	}
+
+	Ok(())
+}
+
+// Remove all files for a repository, retaining those that the game's own
+// patcher does not consider part of the repository's data.
+fn remove_all(path: &Path) -> Result<()> {
+	if !path.is_dir() {
+		return Ok(());
+	}
+
+	for entry in fs::read_dir(path)? {
+		let path = entry?.path();
+		if !path.is_file() {
+			continue;
+		}
+
+		let file_name = path
+			.file_name()
+			.and_then(|name| name.to_str())
+			.unwrap_or_default();
+		let keep = file_name.ends_with(".var")
+			|| matches!(
+				file_name,
+				"00000.bk2" | "00001.bk2" | "00002.bk2" | "00003.bk2"
+			);
+
+		if !keep {
+			fs::remove_file(&path)?;
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use std::io::Cursor;
+
+	use crate::file::{
+		patch::{FileHeaderChunk, PatchKind, ZiPatchWriter},
+		File,
+	};
+
+	use super::{super::version::test::patch, *};
+
+	const DAT: &str = "sqpack/ffxiv/0a0000.win32.dat0";
+	const INDEX: &str = "sqpack/ffxiv/0a0000.win32.index";
+
+	fn sqpack_file() -> SqPackFile {
+		SqPackFile::new(0x0a, 0, 0)
+	}
+
+	fn apply(directory: &Path, bytes: Vec<u8>) -> Result<ApplyProgress> {
+		PatchApplier::new(directory).apply(&ZiPatchFile::read(Cursor::new(bytes))?)
+	}
+
+	fn empty_patch(complete: bool) -> ZiPatchFile {
+		let mut writer = ZiPatchWriter::new(Cursor::new(vec![])).unwrap();
+		writer
+			.write_chunk(&Chunk::FileHeader(FileHeaderChunk::new(
+				PatchKind::Diff,
+				0,
+				None,
+			)))
+			.unwrap();
+		let mut bytes = writer.finish().unwrap().into_inner();
+		if !complete {
+			// Size, tag, and CRC of the end of file chunk.
+			bytes.truncate(bytes.len() - 12);
+		}
+		ZiPatchFile::read(Cursor::new(bytes)).unwrap()
+	}
+
+	#[test]
+	fn writes_version() {
+		let directory = tempfile::tempdir().unwrap();
+		PatchApplier::new(directory.path())
+			.with_version(1, "2023.01.01.0000.0000")
+			.apply(&empty_patch(true))
+			.unwrap();
+
+		let path = directory.path().join("sqpack/ex1/ex1.ver");
+		assert_eq!(fs::read_to_string(path).unwrap(), "2023.01.01.0000.0000");
+
+		let applier = PatchApplier::new(directory.path());
+		assert!(applier.verify_version(1, "2023.01.01.0000.0000").is_ok());
+		assert!(applier.verify_version(1, "2022.01.01.0000.0000").is_err());
+		assert!(applier.verify_version(0, "2023.01.01.0000.0000").is_err());
+	}
+
+	#[test]
+	fn incomplete_skips_version() {
+		let directory = tempfile::tempdir().unwrap();
+		let result = PatchApplier::new(directory.path())
+			.with_version(0, "2023.01.01.0000.0000")
+			.apply(&empty_patch(false));
+		assert!(result.is_err());
+		assert!(!directory.path().join("ffxivgame.ver").exists());
+	}
+
+	#[test]
+	fn empty_blocks_partial() {
+		let mut cursor = Cursor::new(vec![0xFFu8; 128]);
+		write_empty_blocks(&mut cursor, 0, 64).unwrap();
+
+		let buffer = cursor.into_inner();
+		assert!(buffer[..64].iter().all(|byte| *byte == 0));
+		assert!(buffer[64..].iter().all(|byte| *byte == 0xFF));
+	}
+
+	#[test]
+	fn empty_blocks() {
+		let mut cursor = Cursor::new(vec![0xFFu8; 512]);
+		write_empty_blocks(&mut cursor, 128, 256).unwrap();
+
+		let buffer = cursor.into_inner();
+		assert!(buffer[..128].iter().all(|byte| *byte == 0xFF));
+		assert_eq!(&buffer[128..132], &128u32.to_le_bytes());
+		assert_eq!(&buffer[140..144], &1u32.to_le_bytes());
+		assert!(buffer[148..384].iter().all(|byte| *byte == 0));
+		assert!(buffer[384..].iter().all(|byte| *byte == 0xFF));
+	}
+
+	#[test]
+	fn apply_file_operations() {
+		let directory = tempfile::tempdir().unwrap();
+		apply(
+			directory.path(),
+			patch(|writer| {
+				writer.add_file(INDEX, 0, 0, &[1; 2048])?;
+				writer.add_file("sqpack/ffxiv/0a0000.win32.index2", 0, 0, &[1; 16])?;
+				// Writes at a non-zero offset update the existing file in place.
+				writer.add_file(INDEX, 0, 1024, &[2; 16])?;
+				writer.delete_file("sqpack/ffxiv/0a0000.win32.index2", 0)
+			}),
+		)
+		.unwrap();
+
+		let index = fs::read(directory.path().join(INDEX)).unwrap();
+		assert_eq!(index.len(), 2048);
+		assert!(index[..1024].iter().all(|byte| *byte == 1));
+		assert!(index[1024..1040].iter().all(|byte| *byte == 2));
+		assert!(index[1040..].iter().all(|byte| *byte == 1));
+		assert!(!directory
+			.path()
+			.join("sqpack/ffxiv/0a0000.win32.index2")
+			.exists());
+	}
+
+	#[test]
+	fn apply_sqpack_commands() {
+		let directory = tempfile::tempdir().unwrap();
+		let dat = directory.path().join(DAT);
+		fs::create_dir_all(dat.parent().unwrap()).unwrap();
+		fs::write(&dat, [0xFF; 1024]).unwrap();
+		fs::write(directory.path().join(INDEX), [0xFF; 2048]).unwrap();
+
+		let progress = apply(
+			directory.path(),
+			patch(|writer| {
+				writer.add(sqpack_file(), 128, &[1; 128], 128)?;
+				writer.write_chunk(&Chunk::SqPack(SqPackChunk::Delete(DeleteCommand::new(
+					sqpack_file(),
+					384,
+					256,
+				))))?;
+				writer.write_chunk(&Chunk::SqPack(SqPackChunk::Expand(ExpandCommand::new(
+					sqpack_file(),
+					1024,
+					128,
+				))))?;
+				writer.header_update(
+					HeaderFileKind::Index,
+					HeaderKind::Index,
+					sqpack_file(),
+					&[2; 1024],
+				)
+			}),
+		)
+		.unwrap();
+		assert_eq!(progress.chunks, 4);
+
+		let dat = fs::read(&dat).unwrap();
+		assert_eq!(dat.len(), 1152);
+		assert!(dat[..128].iter().all(|byte| *byte == 0xFF));
+		assert!(dat[128..256].iter().all(|byte| *byte == 1));
+		assert!(dat[256..384].iter().all(|byte| *byte == 0));
+
+		// Deleted and expanded regions are marked as empty blocks.
+		for (offset, blocks) in [(384, 2), (1024, 1)] {
+			let header = dat[offset..offset + 20]
+				.chunks_exact(4)
+				.map(|value| u32::from_le_bytes(value.try_into().unwrap()))
+				.collect::<Vec<_>>();
+			assert_eq!(header, [128, 0, 0, blocks - 1, 0]);
+		}
+		assert!(dat[404..640].iter().all(|byte| *byte == 0));
+		assert!(dat[640..1024].iter().all(|byte| *byte == 0xFF));
+
+		let index = fs::read(directory.path().join(INDEX)).unwrap();
+		assert!(index[..1024].iter().all(|byte| *byte == 0xFF));
+		assert!(index[1024..].iter().all(|byte| *byte == 2));
+	}
+
+	#[test]
+	fn apply_remove_all() {
+		let directory = tempfile::tempdir().unwrap();
+		let repository = directory.path().join("sqpack/ex1");
+		fs::create_dir_all(&repository).unwrap();
+		for file in ["020100.win32.dat0", "ex1.ver.var", "ex1.ver"] {
+			fs::write(repository.join(file), []).unwrap();
+		}
+
+		apply(directory.path(), patch(|writer| writer.remove_all(1))).unwrap();
+		assert!(!repository.join("020100.win32.dat0").exists());
+		assert!(!repository.join("ex1.ver").exists());
+		assert!(repository.join("ex1.ver.var").exists());
+
+		let result = apply(directory.path(), patch(|writer| writer.remove_all(256)));
+		assert!(matches!(result, Err(Error::Invalid(..))));
+	}
+}
@@ -0,0 +1,793 @@
+use std::{
+	collections::BTreeSet,
+	fs,
+	io::{self, BufReader, Read, Seek, SeekFrom, Write},
+	ops::Range,
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	error::{Error, ErrorValue, Result},
+	file::patch::{
+		Chunk, DeleteCommand, ExpandCommand, FileHeaderChunk, FileHeaderV3, HeaderFileKind,
+		HeaderKind, PatchKind, SqPackChunk, SqPackFile, TargetInfoCommand, TargetPlatform,
+		TargetRegion, ZiPatchWriter, MAX_BLOCK_SIZE,
+	},
+	sqpack::Platform,
+};
+
+// Files are compared, and written, in units of a single patch block.
+const COMPARE_SIZE: u64 = MAX_BLOCK_SIZE as u64;
+
+// Official patches split file writes into commands of at most this many bytes.
+const MAX_COMMAND_SIZE: u64 = 1_600_000;
+
+// SqPack files start with two 1kb headers, and store data in 128 byte blocks.
+const HEADER_SIZE: u64 = 1024;
+const SQPACK_BLOCK_SIZE: u64 = 128;
+
+/// Builds a ZiPatch DIFF that updates one SqPack directory to match another.
+///
+/// Modified SqPack files are updated in place with SqPack commands - headers
+/// are replaced, changed dat blocks are added, and blocks that have been
+/// emptied are deleted or expanded. Index data is written as file operations.
+///
+/// Other files that have been added or removed are written or deleted in
+/// full. Files that have been modified only have the regions that differ
+/// written, unless the file has shrunk or changed at its start, in which case
+/// it is replaced.
+#[derive(Debug)]
+pub struct DiffBuilder {
+	old: PathBuf,
+	new: PathBuf,
+	platform: Option<Platform>,
+}
+
+impl DiffBuilder {
+	/// Create a builder comparing two `sqpack` directories. Paths within the
+	/// resulting patch are relative to the game directory containing `sqpack`.
+	///
+	/// The platform targeted by the patch is detected from the names of files in
+	/// the new directory, defaulting to [`Platform::Win32`].
+	pub fn new(old: impl Into<PathBuf>, new: impl Into<PathBuf>) -> Self {
+		Self {
+			old: old.into(),
+			new: new.into(),
+			platform: None,
+		}
+	}
+
+	/// Set the platform targeted by the patch, skipping detection.
+	#[must_use]
+	pub fn with_platform(mut self, platform: Platform) -> Self {
+		self.platform = Some(platform);
+		self
+	}
+
+	/// Write a patch updating the old directory to match the new directory.
+	pub fn build<W: Write>(&self, writer: W) -> Result<W> {
+		let old = list_files(&self.old)?;
+		let new = list_files(&self.new)?;
+		let platform = self
+			.platform
+			.or_else(|| detect_platform(&new))
+			.unwrap_or(Platform::Win32);
+
+		let commands = self.commands(&old, &new, platform)?;
+
+		let count = |kind: CommandKind| {
+			let count = commands
+				.iter()
+				.filter(|command| command.kind() == kind)
+				.map(Command::chunk_count)
+				.sum::<u64>();
+			u32::try_from(count).unwrap()
+		};
+		let [add, delete, expand, header, file] = [
+			CommandKind::Add,
+			CommandKind::Delete,
+			CommandKind::Expand,
+			CommandKind::Header,
+			CommandKind::File,
+		]
+		.map(count);
+		let entry_files = commands
+			.iter()
+			.map(|command| &command.path)
+			.collect::<BTreeSet<_>>()
+			.len();
+
+		let mut writer = ZiPatchWriter::new(writer)?;
+		writer.write_chunk(&Chunk::FileHeader(FileHeaderChunk::new(
+			PatchKind::Diff,
+			entry_files.try_into().unwrap(),
+			Some(FileHeaderV3::new(
+				add + delete + expand + header + file + 1,
+				add,
+				delete,
+				expand,
+				header,
+				file,
+			)),
+		)))?;
+		writer.write_chunk(&Chunk::SqPack(SqPackChunk::TargetInfo(
+			TargetInfoCommand::new(target_platform(platform), TargetRegion::Global, false, 0),
+		)))?;
+
+		let mut open = None;
+		for command in commands {
+			write_command(&mut writer, &self.new, &mut open, &command)?;
+		}
+
+		writer.finish()
+	}
+
+	fn commands(
+		&self,
+		old: &BTreeSet<PathBuf>,
+		new: &BTreeSet<PathBuf>,
+		platform: Platform,
+	) -> Result<Vec<Command>> {
+		let mut commands = vec![];
+		for path in old.union(new) {
+			let command = |operation| Command {
+				path: path.clone(),
+				operation,
+			};
+
+			match (old.contains(path), new.contains(path)) {
+				(true, false) => commands.push(command(Operation::DeleteFile)),
+				(false, true) => {
+					let length = fs::metadata(self.new.join(path))?.len();
+					commands.push(command(Operation::WriteFile(0..length)));
+				}
+				_ => {
+					let operations = match sqpack_file(path, platform) {
+						Some(target) => self.sqpack_operations(path, target)?,
+						None => None,
+					};
+					let operations = match operations {
+						Some(operations) => operations,
+						None => self
+							.changed_ranges(path)?
+							.unwrap_or_default()
+							.into_iter()
+							.map(Operation::WriteFile)
+							.collect(),
+					};
+					commands.extend(operations.into_iter().map(command));
+				}
+			}
+		}
+
+		Ok(commands)
+	}
+
+	// Build SqPack commands updating a SqPack file in place. Files that can't be
+	// updated in place, such as those that have shrunk, return `None`.
+	fn sqpack_operations(
+		&self,
+		path: &Path,
+		(file, file_kind): (SqPackFile, HeaderFileKind),
+	) -> Result<Option<Vec<Operation>>> {
+		let old_length = fs::metadata(self.old.join(path))?.len();
+		let new_length = fs::metadata(self.new.join(path))?.len();
+
+		// Dat commands address whole blocks, and cannot represent unaligned files.
+		let aligned =
+			matches!(file_kind, HeaderFileKind::Index) || new_length % SQPACK_BLOCK_SIZE == 0;
+		let in_place = old_length >= HEADER_SIZE * 2
+			&& new_length >= old_length
+			&& aligned
+			&& u32::try_from(new_length).is_ok();
+		if !in_place {
+			return Ok(None);
+		}
+
+		let body_kind = match file_kind {
+			HeaderFileKind::Dat => HeaderKind::Data,
+			HeaderFileKind::Index => HeaderKind::Index,
+		};
+
+		let mut operations = vec![];
+		for (index, header_kind) in [HeaderKind::Version, body_kind].into_iter().enumerate() {
+			let start = HEADER_SIZE * u64::try_from(index).unwrap();
+			let changed = self.compare(path, start..start + HEADER_SIZE, HEADER_SIZE)?;
+			if !changed.is_empty() {
+				operations.push(Operation::Header {
+					file,
+					file_kind,
+					header_kind,
+				});
+			}
+		}
+
+		let body = HEADER_SIZE * 2..new_length;
+		match file_kind {
+			// Index data is not block structured, and is written with file operations.
+			HeaderFileKind::Index => operations.extend(
+				self.compare(path, body, COMPARE_SIZE)?
+					.into_iter()
+					.map(Operation::WriteFile),
+			),
+
+			HeaderFileKind::Dat => {
+				let mut new_file = fs::File::open(self.new.join(path))?;
+				for range in self.compare(path, body, SQPACK_BLOCK_SIZE)? {
+					let mut data = vec![0; usize::try_from(range.end - range.start).unwrap()];
+					new_file.seek(SeekFrom::Start(range.start))?;
+					new_file.read_exact(&mut data)?;
+
+					let operation =
+						match (data == empty_blocks(data.len()), range.start >= old_length) {
+							(true, true) => Operation::Expand { file, range },
+							(true, false) => Operation::Delete { file, range },
+							(false, _) => Operation::Add { file, range },
+						};
+					operations.push(operation);
+				}
+			}
+		}
+
+		Ok(Some(operations))
+	}
+
+	fn changed_ranges(&self, path: &Path) -> Result<Option<Vec<Range<u64>>>> {
+		let old_length = fs::metadata(self.old.join(path))?.len();
+		let new_length = fs::metadata(self.new.join(path))?.len();
+		let ranges = self.compare(path, 0..new_length, COMPARE_SIZE)?;
+
+		// Writes at the start of a file truncate it, so shrunk files and changes
+		// to the first block require the file to be written in full.
+		let replace =
+			new_length < old_length || ranges.first().is_some_and(|range| range.start == 0);
+		let ranges = match replace {
+			true => vec![0..new_length],
+			false if ranges.is_empty() => return Ok(None),
+			false => ranges,
+		};
+
+		Ok(Some(ranges))
+	}
+
+	// Find the ranges of the new file within `range` that differ from the old
+	// file, comparing in units of `size` bytes.
+	fn compare(&self, path: &Path, range: Range<u64>, size: u64) -> Result<Vec<Range<u64>>> {
+		let open = |root: &Path| -> Result<_> {
+			let mut file = fs::File::open(root.join(path))?;
+			file.seek(SeekFrom::Start(range.start))?;
+			Ok(BufReader::new(file))
+		};
+		let mut old_reader = open(&self.old)?;
+		let mut new_reader = open(&self.new)?;
+
+		let buffer_size = usize::try_from(size).unwrap();
+		let mut old_buffer = vec![0; buffer_size];
+		let mut new_buffer = vec![0; buffer_size];
+
+		let mut ranges: Vec<Range<u64>> = vec![];
+		let mut offset = range.start;
+		while offset < range.end {
+			let length = usize::try_from(size.min(range.end - offset)).unwrap();
+			let old_read = read_block(&mut old_reader, &mut old_buffer[..length])?;
+			let new_read = read_block(&mut new_reader, &mut new_buffer[..length])?;
+			let end = offset + u64::try_from(new_read).unwrap();
+
+			if old_buffer[..old_read] != new_buffer[..new_read] {
+				match ranges.last_mut() {
+					Some(range) if range.end == offset => range.end = end,
+					_ => ranges.push(offset..end),
+				}
+			}
+
+			offset += size;
+		}
+
+		Ok(ranges)
+	}
+}
+
+struct Command {
+	path: PathBuf,
+	operation: Operation,
+}
+
+impl Command {
+	fn kind(&self) -> CommandKind {
+		match self.operation {
+			Operation::WriteFile(_) | Operation::DeleteFile => CommandKind::File,
+			Operation::Header { .. } => CommandKind::Header,
+			Operation::Add { .. } => CommandKind::Add,
+			Operation::Delete { .. } => CommandKind::Delete,
+			Operation::Expand { .. } => CommandKind::Expand,
+		}
+	}
+
+	// Number of chunks the command will be written as.
+	fn chunk_count(&self) -> u64 {
+		match &self.operation {
+			Operation::WriteFile(range) | Operation::Add { range, .. } => {
+				(range.end - range.start).div_ceil(MAX_COMMAND_SIZE).max(1)
+			}
+			_ => 1,
+		}
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CommandKind {
+	Add,
+	Delete,
+	Expand,
+	Header,
+	File,
+}
+
+enum Operation {
+	/// Write a range of the file with file operations.
+	WriteFile(Range<u64>),
+	/// Delete the file.
+	DeleteFile,
+	/// Replace one of the headers of a SqPack file.
+	Header {
+		file: SqPackFile,
+		file_kind: HeaderFileKind,
+		header_kind: HeaderKind,
+	},
+	/// Write a range of blocks to a SqPack dat file.
+	Add { file: SqPackFile, range: Range<u64> },
+	/// Mark a range of blocks within a SqPack dat file as empty.
+	Delete { file: SqPackFile, range: Range<u64> },
+	/// Extend a SqPack dat file with a range of empty blocks.
+	Expand { file: SqPackFile, range: Range<u64> },
+}
+
+fn write_command<W: Write>(
+	writer: &mut ZiPatchWriter<W>,
+	root: &Path,
+	open: &mut Option<(PathBuf, fs::File)>,
+	command: &Command,
+) -> Result<()> {
+	let path = &command.path;
+
+	// Ranges of SqPack commands are checked to fit within a u32 when planned.
+	let offset = |value: u64| u32::try_from(value).unwrap();
+
+	match &command.operation {
+		Operation::WriteFile(range) => {
+			write_range(writer, open_file(root, open, path)?, path, range.clone())?
+		}
+
+		Operation::DeleteFile => writer.delete_file(&patch_path(path), repository_id(path))?,
+
+		Operation::Header {
+			file: target,
+			file_kind,
+			header_kind,
+		} => {
+			let start = match header_kind {
+				HeaderKind::Version => 0,
+				HeaderKind::Data | HeaderKind::Index => HEADER_SIZE,
+			};
+			let mut data = [0; HEADER_SIZE as usize];
+			let file = open_file(root, open, path)?;
+			file.seek(SeekFrom::Start(start))?;
+			file.read_exact(&mut data)?;
+			writer.header_update(*file_kind, *header_kind, *target, &data)?;
+		}
+
+		Operation::Add {
+			file: target,
+			range,
+		} => {
+			let file = open_file(root, open, path)?;
+			file.seek(SeekFrom::Start(range.start))?;
+			let mut start = range.start;
+			while start < range.end {
+				let length = (range.end - start).min(MAX_COMMAND_SIZE);
+				let mut data = vec![0; usize::try_from(length).unwrap()];
+				file.read_exact(&mut data)?;
+				writer.add(*target, offset(start), &data, 0)?;
+				start += length;
+			}
+		}
+
+		Operation::Delete {
+			file: target,
+			range,
+		} => writer.write_chunk(&Chunk::SqPack(SqPackChunk::Delete(DeleteCommand::new(
+			*target,
+			offset(range.start),
+			offset(range.end - range.start),
+		))))?,
+
+		Operation::Expand {
+			file: target,
+			range,
+		} => writer.write_chunk(&Chunk::SqPack(SqPackChunk::Expand(ExpandCommand::new(
+			*target,
+			offset(range.start),
+			offset(range.end - range.start),
+		))))?,
+	}
+
+	Ok(())
+}
+
+// Commands for a single file are consecutive, so only one file is kept open.
+fn open_file<'a>(
+	root: &Path,
+	open: &'a mut Option<(PathBuf, fs::File)>,
+	path: &Path,
+) -> Result<&'a mut fs::File> {
+	let is_open = open
+		.as_ref()
+		.is_some_and(|(open_path, _)| open_path == path);
+	if !is_open {
+		*open = Some((path.to_path_buf(), fs::File::open(root.join(path))?));
+	}
+
+	let (_, file) = open.as_mut().unwrap();
+	Ok(file)
+}
+
+fn write_range<W: Write>(
+	writer: &mut ZiPatchWriter<W>,
+	file: &mut fs::File,
+	path: &Path,
+	range: Range<u64>,
+) -> Result<()> {
+	let patch_path = patch_path(path);
+	let repository_id = repository_id(path);
+
+	// Empty files still need a command to create them.
+	if range.is_empty() {
+		return writer.add_file(&patch_path, repository_id, range.start, &[]);
+	}
+
+	file.seek(SeekFrom::Start(range.start))?;
+	let mut offset = range.start;
+	while offset < range.end {
+		let length = (range.end - offset).min(MAX_COMMAND_SIZE);
+		let mut data = vec![0; usize::try_from(length).unwrap()];
+		file.read_exact(&mut data)?;
+		writer.add_file(&patch_path, repository_id, offset, &data)?;
+		offset += length;
+	}
+
+	Ok(())
+}
+
+// Empty regions of dat files are marked with a single block header spanning
+// the region, matching the data written when applying delete and expand commands.
+fn empty_blocks(length: usize) -> Vec<u8> {
+	let mut data = vec![0; length];
+	let block_count = u32::try_from(length / SQPACK_BLOCK_SIZE as usize).unwrap();
+	let header = [SQPACK_BLOCK_SIZE as u32, 0, 0, block_count - 1, 0];
+	for (index, value) in header.into_iter().enumerate() {
+		data[index * 4..index * 4 + 4].copy_from_slice(&value.to_le_bytes());
+	}
+	data
+}
+
+fn list_files(root: &Path) -> Result<BTreeSet<PathBuf>> {
+	let mut files = BTreeSet::new();
+	if !root.is_dir() {
+		return Err(Error::NotFound(ErrorValue::Path(
+			root.to_string_lossy().into_owned(),
+		)));
+	}
+
+	let mut pending = vec![root.to_path_buf()];
+	while let Some(directory) = pending.pop() {
+		for entry in fs::read_dir(directory)? {
+			let path = entry?.path();
+			if path.is_dir() {
+				pending.push(path);
+			} else {
+				files.insert(path.strip_prefix(root).unwrap().to_path_buf());
+			}
+		}
+	}
+
+	Ok(files)
+}
+
+fn read_block(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+	let mut read = 0;
+	while read < buffer.len() {
+		match reader.read(&mut buffer[read..])? {
+			0 => break,
+			count => read += count,
+		}
+	}
+	Ok(read)
+}
+
+fn patch_path(path: &Path) -> String {
+	let segments = path
+		.iter()
+		.map(|segment| segment.to_string_lossy())
+		.collect::<Vec<_>>();
+	format!("sqpack/{}", segments.join("/"))
+}
+
+// Repository folders are named `ffxiv` for the base game, and `exN` for expansions.
+fn repository_id(path: &Path) -> u16 {
+	path.iter()
+		.next()
+		.and_then(|folder| folder.to_str()?.strip_prefix("ex")?.parse().ok())
+		.unwrap_or(0)
+}
+
+// SqPack files are named `{main_id:02x}{sub_id:04x}.{platform}.{extension}`,
+// with the repository stored in the upper byte of the sub ID.
+fn sqpack_file(path: &Path, platform: Platform) -> Option<(SqPackFile, HeaderFileKind)> {
+	let name = path.file_name()?.to_str()?;
+	let (id, rest) = name.split_once('.')?;
+	let extension = rest.strip_prefix(platform.name())?.strip_prefix('.')?;
+
+	if id.len() != 6 {
+		return None;
+	}
+	let main_id = u16::from_str_radix(id.get(..2)?, 16).ok()?;
+	let sub_id = u16::from_str_radix(id.get(2..)?, 16).ok()?;
+	if sub_id >> 8 != repository_id(path) {
+		return None;
+	}
+
+	let (file_kind, file_id) = match extension {
+		"index" => (HeaderFileKind::Index, 0),
+		other => match (other.strip_prefix("dat"), other.strip_prefix("index")) {
+			(Some(id), _) => (HeaderFileKind::Dat, id.parse().ok()?),
+			(_, Some(id)) => (HeaderFileKind::Index, id.parse().ok()?),
+			_ => return None,
+		},
+	};
+
+	Some((SqPackFile::new(main_id, sub_id, file_id), file_kind))
+}
+
+fn detect_platform(files: &BTreeSet<PathBuf>) -> Option<Platform> {
+	files.iter().find_map(|path| {
+		Platform::ALL
+			.into_iter()
+			.find(|platform| sqpack_file(path, *platform).is_some())
+	})
+}
+
+fn target_platform(platform: Platform) -> TargetPlatform {
+	match platform {
+		Platform::Win32 => TargetPlatform::Win32,
+		Platform::PS3 => TargetPlatform::Ps3,
+		Platform::PS4 => TargetPlatform::Ps4,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use tempfile::TempDir;
+
+	use crate::{file::patch::ZiPatch as ZiPatchFile, file::File, zipatch::PatchApplier};
+
+	use super::*;
+
+	struct Directories(TempDir);
+
+	impl Directories {
+		fn new() -> Self {
+			Self(tempfile::tempdir().unwrap())
+		}
+
+		fn path(&self) -> &Path {
+			self.0.path()
+		}
+
+		fn write(&self, game: &str, path: &str, data: &[u8]) {
+			let path = self.path().join(game).join("sqpack").join(path);
+			fs::create_dir_all(path.parent().unwrap()).unwrap();
+			fs::write(path, data).unwrap();
+		}
+
+		fn read(&self, game: &str, path: &str) -> Option<Vec<u8>> {
+			fs::read(self.path().join(game).join("sqpack").join(path)).ok()
+		}
+	}
+
+	fn data(length: usize, seed: u8) -> Vec<u8> {
+		(0..length)
+			.map(|index| (index as u8).wrapping_mul(31).wrapping_add(seed))
+			.collect()
+	}
+
+	#[test]
+	fn diff_round_trip() {
+		let directories = Directories::new();
+
+		let unchanged = data(20_000, 1);
+		let old_modified = data(50_000, 2);
+		let mut new_modified = old_modified.clone();
+		new_modified[33_000..33_010].fill(0xFF);
+		new_modified.extend(data(10_000, 3));
+
+		directories.write("old", "ffxiv/000000.win32.dat0", &unchanged);
+		directories.write("new", "ffxiv/000000.win32.dat0", &unchanged);
+		directories.write("old", "ffxiv/0a0000.win32.dat0", &old_modified);
+		directories.write("new", "ffxiv/0a0000.win32.dat0", &new_modified);
+		directories.write("old", "ffxiv/0a0000.win32.index", &data(5_000, 4));
+		directories.write("new", "ffxiv/0a0000.win32.index", &data(3_000, 5));
+		directories.write("old", "ffxiv/removed.dat", &data(100, 6));
+		directories.write("new", "ex1/020000.win32.dat0", &data(2_000_000, 7));
+
+		let builder = DiffBuilder::new(
+			directories.path().join("old/sqpack"),
+			directories.path().join("new/sqpack"),
+		);
+		let bytes = builder.build(io::Cursor::new(vec![])).unwrap().into_inner();
+
+		let patch = ZiPatchFile::read(io::Cursor::new(bytes)).unwrap();
+		PatchApplier::new(directories.path().join("old"))
+			.apply(&patch)
+			.unwrap();
+
+		for path in [
+			"ffxiv/000000.win32.dat0",
+			"ffxiv/0a0000.win32.dat0",
+			"ffxiv/0a0000.win32.index",
+			"ex1/020000.win32.dat0",
+		] {
+			assert_eq!(
+				directories.read("old", path),
+				directories.read("new", path),
+				"{path}"
+			);
+		}
+		assert_eq!(directories.read("old", "ffxiv/removed.dat"), None);
+	}
+
+	#[test]
+	fn partial_writes() {
+		let directories = Directories::new();
+		let old = data(100_000, 1);
+		let mut new = old.clone();
+		new[40_000] = 0;
+		directories.write("old", "ffxiv/000000.win32.dat0", &old);
+		directories.write("new", "ffxiv/000000.win32.dat0", &new);
+
+		let builder = DiffBuilder::new(
+			directories.path().join("old/sqpack"),
+			directories.path().join("new/sqpack"),
+		);
+		let ranges = builder
+			.changed_ranges(Path::new("ffxiv/000000.win32.dat0"))
+			.unwrap();
+
+		assert_eq!(ranges, Some(vec![32_000..48_000]));
+	}
+
+	fn chunks(bytes: Vec<u8>) -> Vec<Chunk> {
+		let patch = ZiPatchFile::read(io::Cursor::new(bytes)).unwrap();
+		patch.chunks().collect::<Result<Vec<_>>>().unwrap()
+	}
+
+	#[test]
+	fn sqpack_commands() {
+		let directories = Directories::new();
+
+		let dat = |version: u8, body: &[u8]| [&[version; 1024][..], &[2; 1024], body].concat();
+		let old_body = data(1280, 1);
+		let mut new_body = old_body.clone();
+		new_body[256..384].fill(0xFF);
+		new_body[640..896].copy_from_slice(&empty_blocks(256));
+		new_body.extend(empty_blocks(384));
+		directories.write("old", "ffxiv/0a0000.win32.dat0", &dat(1, &old_body));
+		directories.write("new", "ffxiv/0a0000.win32.dat0", &dat(3, &new_body));
+
+		let index = |header: u8, body: &[u8]| [&[4; 1024][..], &[header; 1024], body].concat();
+		let old_body = data(1000, 2);
+		let mut new_body = old_body.clone();
+		new_body[500] ^= 0xFF;
+		directories.write("old", "ffxiv/0a0000.win32.index", &index(5, &old_body));
+		directories.write("new", "ffxiv/0a0000.win32.index", &index(6, &new_body));
+
+		let builder = DiffBuilder::new(
+			directories.path().join("old/sqpack"),
+			directories.path().join("new/sqpack"),
+		);
+		let bytes = builder.build(io::Cursor::new(vec![])).unwrap().into_inner();
+
+		let chunks = chunks(bytes.clone());
+		assert_eq!(chunks.len(), 9);
+		assert!(matches!(
+			&chunks[2],
+			Chunk::SqPack(SqPackChunk::HeaderUpdate(command))
+				if matches!(command.header_kind(), HeaderKind::Version)
+		));
+		assert!(matches!(
+			&chunks[3],
+			Chunk::SqPack(SqPackChunk::Add(command))
+				if (command.target_offset(), command.data_size()) == (2048 + 256, 128)
+		));
+		assert!(matches!(
+			&chunks[4],
+			Chunk::SqPack(SqPackChunk::Delete(command))
+				if (command.target_offset(), command.delete_size()) == (2048 + 640, 256)
+		));
+		assert!(matches!(
+			&chunks[5],
+			Chunk::SqPack(SqPackChunk::Expand(command))
+				if (command.target_offset(), command.delete_size()) == (2048 + 1280, 384)
+		));
+		assert!(matches!(
+			&chunks[6],
+			Chunk::SqPack(SqPackChunk::HeaderUpdate(command))
+				if matches!(command.header_kind(), HeaderKind::Index)
+		));
+		assert!(matches!(
+			&chunks[7],
+			Chunk::SqPack(SqPackChunk::FileOperation(command))
+				if (command.target_offset(), command.target_size()) == (2048, 1000)
+		));
+
+		let patch = ZiPatchFile::read(io::Cursor::new(bytes)).unwrap();
+		PatchApplier::new(directories.path().join("old"))
+			.apply(&patch)
+			.unwrap();
+		for path in ["ffxiv/0a0000.win32.dat0", "ffxiv/0a0000.win32.index"] {
+			assert_eq!(
+				directories.read("old", path),
+				directories.read("new", path),
+				"{path}"
+			);
+		}
+	}
+
+	#[test]
+	fn platform() {
+		let directories = Directories::new();
+		directories.write("new", "ffxiv/0a0000.ps3.dat0", &data(100, 1));
+		fs::create_dir_all(directories.path().join("old/sqpack")).unwrap();
+
+		let builder = DiffBuilder::new(
+			directories.path().join("old/sqpack"),
+			directories.path().join("new/sqpack"),
+		);
+		let target_platform = |builder: &DiffBuilder| {
+			let bytes = builder.build(io::Cursor::new(vec![])).unwrap().into_inner();
+			match &chunks(bytes)[1] {
+				Chunk::SqPack(SqPackChunk::TargetInfo(command)) => command.platform(),
+				other => panic!("unexpected chunk {other:?}"),
+			}
+		};
+
+		assert!(matches!(target_platform(&builder), TargetPlatform::Ps3));
+		let builder = builder.with_platform(Platform::PS4);
+		assert!(matches!(target_platform(&builder), TargetPlatform::Ps4));
+	}
+
+	#[test]
+	fn sqpack_file_names() {
+		let parse = |path: &str| {
+			sqpack_file(Path::new(path), Platform::Win32).map(|(file, kind)| {
+				(
+					file.main_id(),
+					file.sub_id(),
+					file.file_id(),
+					matches!(kind, HeaderFileKind::Dat),
+				)
+			})
+		};
+
+		assert_eq!(parse("ffxiv/0a0000.win32.dat3"), Some((0x0a, 0, 3, true)));
+		assert_eq!(parse("ffxiv/040000.win32.index"), Some((0x04, 0, 0, false)));
+		assert_eq!(
+			parse("ex2/020201.win32.index2"),
+			Some((0x02, 0x0201, 2, false))
+		);
+		assert_eq!(parse("ffxiv/0a0000.ps3.dat0"), None);
+		// Repositories must match the folder the file is stored in.
+		assert_eq!(parse("ffxiv/020201.win32.dat0"), None);
+		assert_eq!(parse("ffxiv/0a0000.win32.dat"), None);
+		assert_eq!(parse("ffxiv/removed.dat"), None);
+	}
+}
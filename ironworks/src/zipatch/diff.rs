@@ -0,0 +1,605 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{
+	error::{Error, ErrorValue, Result},
+	sqpack,
+};
+
+use super::{
+	lookup::{SqPackFileExtension, SqPackSpecifier},
+	reader::TargetRange,
+	version::{index_entries, Version, VersionSpecifier},
+	zipatch::ZiPatch,
+};
+
+/// Kind of change made to a SqPack index entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexChangeKind {
+	/// The entry is only present in the newer version.
+	Added,
+	/// The entry is only present in the older version.
+	Removed,
+	/// The entry is present in both versions, but either points to a different
+	/// location, or the data at its location was overwritten between the versions.
+	Modified,
+}
+
+/// A change to a single file entry within a SqPack index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexChange {
+	/// Repository containing the file.
+	pub repository: u8,
+	/// Category containing the file.
+	pub category: u8,
+	/// Chunk of the category containing the file.
+	pub chunk: u8,
+	/// Index1 hash of the file's path.
+	pub hash: u64,
+	/// The kind of change made to the entry.
+	pub kind: IndexChangeKind,
+}
+
+impl IndexChange {
+	/// Check if this change affects the file at the specified path.
+	pub fn matches(&self, path: &str) -> bool {
+		sqpack::path_hash(&path.to_lowercase()).is_ok_and(|hash| hash == self.hash)
+	}
+}
+
+impl ZiPatch {
+	/// List the SqPack index entries that differ between two versions.
+	pub fn diff(&self, from: VersionSpecifier, to: VersionSpecifier) -> Result<Vec<IndexChange>> {
+		diff_versions(&self.version(from), &self.version(to))
+	}
+
+	/// List the SqPack index entries changed by a single patch within the
+	/// specified repository.
+	pub fn diff_patch(&self, repository: u8, patch: &str) -> Result<Vec<IndexChange>> {
+		let (from, to) = self.patch_specifiers(repository, patch)?;
+		self.diff(from, to)
+	}
+
+	/// Compare the Excel databases of two versions. Only sheets with files that
+	/// have changed between the versions are compared row-by-row, in every
+	/// language the sheet contains data for.
+	#[cfg(feature = "excel")]
+	pub fn diff_excel(
+		&self,
+		from: VersionSpecifier,
+		to: VersionSpecifier,
+	) -> Result<crate::excel::ExcelDiff> {
+		use std::sync::Arc;
+
+		use crate::{excel::Excel, Ironworks};
+
+		let from = self.version(from);
+		let to = self.version(to);
+
+		// Excel data is only ever stored in the base repository.
+		const EXD_CATEGORY: u8 = 0x0a;
+		let changes = diff_versions(&from, &to)?
+			.into_iter()
+			.filter(|change| change.repository == 0 && change.category == EXD_CATEGORY)
+			.map(|change| change.hash)
+			.collect::<BTreeSet<_>>();
+
+		// Sheets are compared in every language they contain, so the database's
+		// default language has no effect on the result.
+		let excel = |version| {
+			Excel::new(Arc::new(
+				Ironworks::new().with_resource(sqpack::SqPack::new(version)),
+			))
+		};
+		let from = excel(from);
+		let to = excel(to);
+
+		let to_list = to.list()?;
+		let mut sheets = vec![];
+		for sheet in from.list()?.iter().filter(|sheet| to_list.has(sheet)) {
+			let paths = [from.sheet_paths(&sheet)?, to.sheet_paths(&sheet)?].concat();
+			let changed = paths.iter().any(|path| {
+				sqpack::path_hash(&path.to_lowercase()).is_ok_and(|hash| changes.contains(&hash))
+			});
+			if changed {
+				sheets.push(sheet.to_string());
+			}
+		}
+
+		from.diff_sheets(&to, sheets)
+	}
+
+	fn patch_specifiers(
+		&self,
+		repository: u8,
+		patch: &str,
+	) -> Result<(VersionSpecifier, VersionSpecifier)> {
+		let patches = &self.repository(repository)?.patches;
+		let index = patches
+			.iter()
+			.position(|candidate| candidate == patch)
+			.ok_or_else(|| Error::NotFound(ErrorValue::Other(format!("patch {patch}"))))?;
+
+		// The first patch in a repository is compared against nothing at all.
+		let from = index
+			.checked_sub(1)
+			.map(|previous| HashMap::from([(repository, patches[previous].clone())]))
+			.unwrap_or_default();
+		let to = HashMap::from([(repository, patch.to_string())]);
+
+		Ok((VersionSpecifier::exact(from), VersionSpecifier::exact(to)))
+	}
+}
+
+fn diff_versions(from: &Version, to: &Version) -> Result<Vec<IndexChange>> {
+	let mut changes = vec![];
+
+	for repository in from.repository_ids() {
+		// Versions always include a prefix of a repository's patches - only the
+		// patches between the two prefixes can have modified the indexes.
+		let counts = (from.patch_count(repository), to.patch_count(repository));
+		let range = counts.0.min(counts.1)..counts.0.max(counts.1);
+		if range.is_empty() {
+			continue;
+		}
+
+		// Index2 files are not read, however they are updated alongside the index1
+		// of the same chunk - any SqPack file touched marks the chunk for comparison.
+		let mut specifiers = BTreeSet::new();
+		let mut dat_writes = DatWrites::new();
+		for lookup in from.range_lookups(repository, range)? {
+			let lookup = lookup?;
+			specifiers.extend(
+				lookup
+					.writes
+					.keys()
+					.chain(lookup.index_updates.keys())
+					.map(|specifier| (specifier.category, specifier.chunk)),
+			);
+
+			// Files may be rewritten in place, leaving their index entry untouched.
+			for (specifier, writes) in &lookup.writes {
+				if !matches!(specifier.extension, SqPackFileExtension::Dat(_)) {
+					continue;
+				}
+				dat_writes
+					.entry(*specifier)
+					.or_default()
+					.extend(writes.iter().map(|write| match write.truncate {
+						true => (0, u64::MAX),
+						false => (write.target_offset, write.target_size),
+					}));
+			}
+		}
+
+		for (category, chunk) in specifiers {
+			let specifier = SqPackSpecifier {
+				repository,
+				category,
+				chunk,
+				extension: SqPackFileExtension::Index(1),
+			};
+			let old = read_entries(from, &specifier)?;
+			let new = read_entries(to, &specifier)?;
+			changes.extend(diff_entries(&specifier, &old, &new, &dat_writes));
+		}
+	}
+
+	Ok(changes)
+}
+
+fn read_entries(version: &Version, specifier: &SqPackSpecifier) -> Result<BTreeMap<u64, u32>> {
+	match version.read_index(specifier.repository, specifier.category, specifier.chunk, 1) {
+		Ok(index) => index_entries(index.get_ref()),
+		// An index missing from a version is treated as empty.
+		Err(Error::NotFound(_)) => Ok(BTreeMap::new()),
+		Err(error) => Err(error),
+	}
+}
+
+// Regions written to each .dat file, as offset and size pairs.
+type DatWrites = HashMap<SqPackSpecifier, Vec<(u64, u64)>>;
+
+fn diff_entries(
+	specifier: &SqPackSpecifier,
+	old: &BTreeMap<u64, u32>,
+	new: &BTreeMap<u64, u32>,
+	dat_writes: &DatWrites,
+) -> Vec<IndexChange> {
+	let change = |hash: u64, kind| IndexChange {
+		repository: specifier.repository,
+		category: specifier.category,
+		chunk: specifier.chunk,
+		hash,
+		kind,
+	};
+
+	// The size of files is not recorded in the index - a file is assumed to
+	// extend until the start of the next file in the same .dat.
+	let locations = new
+		.values()
+		.map(|&data| location(data))
+		.collect::<BTreeSet<_>>();
+	let overwritten = |data: u32| {
+		let (dat, offset) = location(data);
+		let range = TargetRange {
+			start: offset,
+			end: locations
+				.range((dat, offset + 1)..)
+				.next()
+				.filter(|(next_dat, _)| *next_dat == dat)
+				.map(|(_, next_offset)| *next_offset),
+		};
+
+		let dat_specifier = SqPackSpecifier {
+			extension: SqPackFileExtension::Dat(dat),
+			..*specifier
+		};
+		dat_writes.get(&dat_specifier).is_some_and(|writes| {
+			writes
+				.iter()
+				.any(|&(offset, size)| range.overlaps(offset, size))
+		})
+	};
+
+	let mut changes = vec![];
+	for (hash, location) in new {
+		match old.get(hash) {
+			None => changes.push(change(*hash, IndexChangeKind::Added)),
+			Some(old_location) if old_location != location || overwritten(*location) => {
+				changes.push(change(*hash, IndexChangeKind::Modified))
+			}
+			Some(_) => {}
+		}
+	}
+	for hash in old.keys().filter(|hash| !new.contains_key(hash)) {
+		changes.push(change(*hash, IndexChangeKind::Removed));
+	}
+
+	changes
+}
+
+// Decode the .dat file and byte offset from the data of an index1 entry.
+fn location(data: u32) -> (u8, u64) {
+	let dat = u8::try_from((data >> 1) & 0b111).unwrap();
+	(dat, u64::from(data & !0xF) * 8)
+}
+
+#[cfg(test)]
+mod test {
+	use crate::file::patch::{IndexUpdateKind, SqPackFile};
+
+	use super::{
+		super::version::test::{index, index_update, patch, Chain, CATEGORY},
+		*,
+	};
+
+	const INDEX: &str = "sqpack/ffxiv/0a0000.win32.index";
+
+	fn chain() -> Chain {
+		Chain::new(vec![
+			patch(|writer| writer.add_file(INDEX, 0, 0, &index(&[(1, 0x10), (3, 0x30)]))),
+			patch(|writer| {
+				index_update(writer, IndexUpdateKind::Add, 2, 2)?;
+				index_update(writer, IndexUpdateKind::Add, 3, 4)?;
+				index_update(writer, IndexUpdateKind::Delete, 1, 0)
+			}),
+			// Patches that don't touch indexes or the files they point to produce no changes.
+			patch(|writer| writer.add_file("sqpack/ffxiv/0a0000.win32.dat1", 0, 0, &[1; 128])),
+		])
+	}
+
+	fn summarise(changes: Vec<IndexChange>) -> Vec<(u64, IndexChangeKind)> {
+		changes
+			.into_iter()
+			.inspect(|change| {
+				assert_eq!(
+					(change.repository, change.category, change.chunk),
+					(0, CATEGORY, 0)
+				)
+			})
+			.map(|change| (change.hash, change.kind))
+			.collect()
+	}
+
+	fn exact(patch: &str) -> VersionSpecifier {
+		VersionSpecifier::exact(HashMap::from([(0, patch.to_string())]))
+	}
+
+	#[test]
+	fn diff_patches() {
+		let chain = chain();
+		let zipatch = &chain.zipatch;
+
+		assert_eq!(
+			summarise(zipatch.diff_patch(0, "D0000").unwrap()),
+			[(1, IndexChangeKind::Added), (3, IndexChangeKind::Added)]
+		);
+		assert_eq!(
+			summarise(zipatch.diff_patch(0, "D0001").unwrap()),
+			[
+				(2, IndexChangeKind::Added),
+				(3, IndexChangeKind::Modified),
+				(1, IndexChangeKind::Removed),
+			]
+		);
+		assert_eq!(zipatch.diff_patch(0, "D0002").unwrap(), []);
+		assert!(matches!(
+			zipatch.diff_patch(0, "D9999"),
+			Err(Error::NotFound(_))
+		));
+	}
+
+	#[test]
+	fn diff_versions() {
+		let chain = chain();
+		let zipatch = &chain.zipatch;
+
+		// Changes across multiple patches are collapsed to their net effect.
+		assert_eq!(
+			summarise(
+				zipatch
+					.diff(exact("D0000"), VersionSpecifier::latest())
+					.unwrap()
+			),
+			[
+				(2, IndexChangeKind::Added),
+				(3, IndexChangeKind::Modified),
+				(1, IndexChangeKind::Removed),
+			]
+		);
+		assert_eq!(
+			summarise(
+				zipatch
+					.diff(VersionSpecifier::latest(), exact("D0000"))
+					.unwrap()
+			),
+			[
+				(1, IndexChangeKind::Added),
+				(3, IndexChangeKind::Modified),
+				(2, IndexChangeKind::Removed),
+			]
+		);
+		assert_eq!(
+			zipatch
+				.diff(exact("D0001"), VersionSpecifier::latest())
+				.unwrap(),
+			[]
+		);
+	}
+
+	#[test]
+	fn entry_changes() {
+		let specifier = SqPackSpecifier {
+			repository: 0,
+			category: 1,
+			chunk: 0,
+			extension: SqPackFileExtension::Index(1),
+		};
+		let old = BTreeMap::from([(1, 0x10), (2, 0x20), (3, 0x30)]);
+		let new = BTreeMap::from([(2, 0x20), (3, 0x40), (4, 0x50)]);
+
+		let changes = diff_entries(&specifier, &old, &new, &DatWrites::new())
+			.into_iter()
+			.map(|change| (change.hash, change.kind))
+			.collect::<Vec<_>>();
+
+		assert_eq!(
+			changes,
+			vec![
+				(3, IndexChangeKind::Modified),
+				(4, IndexChangeKind::Added),
+				(1, IndexChangeKind::Removed),
+			]
+		);
+	}
+
+	#[test]
+	fn overwritten_entries() {
+		let specifier = SqPackSpecifier {
+			repository: 0,
+			category: 1,
+			chunk: 0,
+			extension: SqPackFileExtension::Index(1),
+		};
+		let dat = |id| SqPackSpecifier {
+			extension: SqPackFileExtension::Dat(id),
+			..specifier
+		};
+
+		// Entries at 0x80, 0x100, and 0x180 in dat0, and 0x80 in dat1.
+		let entries = BTreeMap::from([(1, 0x10), (2, 0x20), (3, 0x30), (4, 0x12)]);
+		let changes = |writes: DatWrites| {
+			diff_entries(&specifier, &entries, &entries, &writes)
+				.into_iter()
+				.inspect(|change| assert_eq!(change.kind, IndexChangeKind::Modified))
+				.map(|change| change.hash)
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(
+			changes(DatWrites::from([(dat(0), vec![(0x100, 0x80)])])),
+			[2]
+		);
+		assert_eq!(
+			changes(DatWrites::from([(dat(0), vec![(0xF0, 0x20)])])),
+			[1, 2]
+		);
+		// The last file in a dat extends to the end of the file.
+		assert_eq!(
+			changes(DatWrites::from([(dat(0), vec![(0x1000, 0x80)])])),
+			[3]
+		);
+		assert_eq!(changes(DatWrites::from([(dat(1), vec![(0, 0x80)])])), []);
+		assert_eq!(
+			changes(DatWrites::from([(dat(1), vec![(0, u64::MAX)])])),
+			[4]
+		);
+	}
+
+	#[test]
+	fn in_place_writes() {
+		let chain = Chain::new(vec![
+			patch(|writer| writer.add_file(INDEX, 0, 0, &index(&[(1, 0x10), (2, 0x20)]))),
+			patch(|writer| writer.add(SqPackFile::new(CATEGORY.into(), 0, 0), 0x100, &[1; 128], 0)),
+		]);
+
+		assert_eq!(
+			summarise(chain.zipatch.diff_patch(0, "D0001").unwrap()),
+			[(2, IndexChangeKind::Modified)]
+		);
+	}
+
+	#[test]
+	fn change_matches_path() {
+		let change = IndexChange {
+			repository: 0,
+			category: 0x0a,
+			chunk: 0,
+			hash: sqpack::path_hash("exd/root.exl").unwrap(),
+			kind: IndexChangeKind::Modified,
+		};
+
+		assert!(change.matches("exd/root.exl"));
+		assert!(change.matches("EXD/Root.exl"));
+		assert!(!change.matches("exd/item.exh"));
+	}
+
+	// Pack files into a SqPack category as uncompressed standard files, returning
+	// the category's index and dat contents. Dat data is appended to `dat`, and
+	// entries are added to those already present in `entries`.
+	#[cfg(feature = "excel")]
+	fn pack(
+		files: &[(String, Vec<u8>)],
+		entries: &mut BTreeMap<u64, u32>,
+		dat: &mut Vec<u8>,
+	) -> Vec<u8> {
+		const HEADER_SIZE: u32 = 128;
+		const UNCOMPRESSED_MARKER_SIZE: u32 = 32_000;
+
+		for (path, data) in files {
+			let offset = u32::try_from(dat.len()).unwrap();
+			entries.insert(
+				sqpack::path_hash(&path.to_lowercase()).unwrap(),
+				offset >> 3,
+			);
+
+			let size = u32::try_from(data.len()).unwrap();
+			let mut file = vec![];
+			for value in [HEADER_SIZE, 2, size, 0, 0, 1, 0] {
+				file.extend_from_slice(&value.to_le_bytes());
+			}
+			file.extend_from_slice(&u16::try_from(16 + size).unwrap().to_le_bytes());
+			file.extend_from_slice(&u16::try_from(size).unwrap().to_le_bytes());
+			file.resize(HEADER_SIZE as usize, 0);
+
+			for value in [16, 0, UNCOMPRESSED_MARKER_SIZE, size] {
+				file.extend_from_slice(&value.to_le_bytes());
+			}
+			file.extend_from_slice(data);
+			file.resize((file.len() + 0x7F) & !0x7F, 0);
+
+			dat.extend(file);
+		}
+
+		let entries = entries
+			.iter()
+			.map(|(&hash, &data)| (hash, data))
+			.collect::<Vec<_>>();
+		index(&entries)
+	}
+
+	#[cfg(feature = "excel")]
+	#[test]
+	fn diff_excel() {
+		use crate::{
+			excel::{
+				diff::test::{files, TestSheet},
+				Language, RowKey,
+			},
+			file::exh::ColumnKind,
+		};
+
+		const DAT: &str = "sqpack/ffxiv/0a0000.win32.dat0";
+		const COLUMNS: &[ColumnKind] = &[ColumnKind::UInt32];
+
+		let mut entries = BTreeMap::new();
+		let mut dat = vec![];
+		let old_index = pack(
+			&files(&[
+				TestSheet {
+					name: "A",
+					columns: COLUMNS,
+					rows: &[(0, &[1])],
+				},
+				TestSheet {
+					name: "B",
+					columns: COLUMNS,
+					rows: &[(0, &[1])],
+				},
+			]),
+			&mut entries,
+			&mut dat,
+		);
+		let old_dat = dat.clone();
+
+		// Only sheet B is repacked - sheet A's files are untouched by the patch.
+		let new_files = files(&[
+			TestSheet {
+				name: "A",
+				columns: COLUMNS,
+				rows: &[(0, &[1])],
+			},
+			TestSheet {
+				name: "B",
+				columns: COLUMNS,
+				rows: &[(0, &[2]), (1, &[3])],
+			},
+			TestSheet {
+				name: "C",
+				columns: COLUMNS,
+				rows: &[(0, &[1])],
+			},
+		])
+		.into_iter()
+		.filter(|(path, _)| !path.starts_with("exd/A"))
+		.collect::<Vec<_>>();
+		let new_index = pack(&new_files, &mut entries, &mut dat);
+		let new_dat = dat.split_off(old_dat.len());
+
+		let chain = Chain::new(vec![
+			patch(|writer| {
+				writer.add_file(INDEX, 0, 0, &old_index)?;
+				writer.add_file(DAT, 0, 0, &old_dat)
+			}),
+			patch(|writer| {
+				writer.add_file(INDEX, 0, 0, &new_index)?;
+				writer.add_file(DAT, 0, old_dat.len().try_into().unwrap(), &new_dat)
+			}),
+		]);
+
+		let diff = chain
+			.zipatch
+			.diff_excel(
+				VersionSpecifier::exact(HashMap::from([(0, "D0000".to_string())])),
+				VersionSpecifier::latest(),
+			)
+			.unwrap();
+
+		assert_eq!(diff.added_sheets, ["C"]);
+		assert!(diff.removed_sheets.is_empty());
+		assert_eq!(diff.changed_sheets.len(), 1);
+
+		let sheet = &diff.changed_sheets[0];
+		assert_eq!(sheet.sheet, "B");
+		assert_eq!(
+			sheet.added_rows,
+			[RowKey {
+				row_id: 1,
+				subrow_id: 0,
+				language: Language::None,
+			}]
+		);
+		assert_eq!(sheet.changed_rows.len(), 1);
+		assert_eq!(sheet.changed_rows[0].row.row_id, 0);
+	}
+}
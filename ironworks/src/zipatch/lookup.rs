@@ -1,28 +1,46 @@
 use std::{
 	collections::HashMap,
 	fs,
-	io::BufReader,
+	hash::Hash,
+	io::{BufReader, BufWriter, Read, Seek, Write},
+	ops::Range,
 	path::{Path, PathBuf},
+	process,
+	sync::atomic::{AtomicU64, Ordering},
+	time::UNIX_EPOCH,
 };
 
+use binrw::{binrw, BinRead, BinReaderExt, BinResult, BinWrite, BinWriterExt};
+
 use crate::{
 	error::{Error, ErrorValue, Result},
 	file::{
 		patch::{
-			AddCommand, Chunk, FileOperation, FileOperationCommand, SqPackChunk,
-			ZiPatch as ZiPatchFile,
+			Chunk, FileOperation, HeaderFileKind, HeaderKind, IndexUpdateKind, SqPackChunk,
+			SqPackFile, ZiPatch as ZiPatchFile,
 		},
 		File,
 	},
 };
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+// Bump this whenever the layout of persisted lookups changes.
+const LOOKUP_CACHE_VERSION: u32 = 3;
+
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SqPackFileExtension {
+	#[brw(magic = b'i')]
 	Index(u8),
+	#[brw(magic = b'd')]
 	Dat(u8),
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct SqPackSpecifier {
 	pub repository: u8,
 	pub category: u8,
@@ -30,20 +48,106 @@ pub struct SqPackSpecifier {
 	pub extension: SqPackFileExtension,
 }
 
+/// Data written to a file by a single patch command.
+#[binrw]
+#[brw(little)]
+#[derive(Debug)]
+pub struct FileChunk {
+	pub target_offset: u64,
+	pub target_size: u64,
+
+	// FileOperation::AddFile commands at offset 0 truncate the target file.
+	#[br(map = |value: u8| value != 0)]
+	#[bw(map = |value: &bool| u8::from(*value))]
+	pub truncate: bool,
+
+	#[br(temp)]
+	#[bw(calc = blocks.len().try_into().unwrap())]
+	block_count: u32,
+
+	#[br(count = block_count)]
+	pub blocks: Vec<FileBlock>,
+}
+
+/// Single contiguous block of data written by a file chunk.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Copy)]
+pub struct FileBlock {
+	pub source: BlockSource,
+	/// Size of the data written to the target file.
+	pub size: u32,
+}
+
+/// Source of the data written by a file block.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockSource {
+	/// A SqPack block payload stored in the patch file, which may be compressed.
+	#[brw(magic = 0u8)]
+	Block { offset: u64, compressed_size: u32 },
+	/// Uncompressed data stored in the patch file.
+	#[brw(magic = 1u8)]
+	Raw { offset: u64 },
+	/// Zero-filled data.
+	#[brw(magic = 2u8)]
+	Zeroes,
+	/// A run of empty SqPack blocks, starting with a single block header spanning
+	/// the run.
+	#[brw(magic = 3u8)]
+	EmptyBlocks,
+}
+
+/// Modification of a single index1 entry.
+#[binrw]
+#[brw(little)]
+#[derive(Debug)]
+pub struct IndexUpdate {
+	#[br(map = |value: u8| value != 0)]
+	#[bw(map = |value: &bool| u8::from(*value))]
+	pub delete: bool,
+	#[br(map = |value: u8| value != 0)]
+	#[bw(map = |value: &bool| u8::from(*value))]
+	pub is_synonym: bool,
+	pub data_file: u32,
+	pub hash: u64,
+	pub block_offset: u32,
+}
+
 #[derive(Debug)]
 pub struct PatchLookup {
 	pub path: PathBuf,
 
-	// TODO: consider storing a slightly more ergonomic struct instead of commands
-	pub add_operations: HashMap<SqPackSpecifier, Vec<FileOperationCommand>>,
-	// (specifier, offset)
-	pub add_commands: HashMap<(SqPackSpecifier, u32), AddCommand>,
+	// Keyed by the file being written, in patch order.
+	pub writes: HashMap<SqPackSpecifier, Vec<FileChunk>>,
+	// Keyed by the index file being updated, in patch order.
+	pub index_updates: HashMap<SqPackSpecifier, Vec<IndexUpdate>>,
 }
 
 impl PatchLookup {
 	pub fn new(path: &Path) -> Result<Self> {
 		read_lookup(path)
 	}
+
+	/// Read the lookup for the patch at `path`, using a previously persisted copy
+	/// at `cache_path` if it's still valid for the patch file. The cache will be
+	/// (re)built if it is missing or stale. Failure to write the cache is logged,
+	/// and does not prevent the lookup from being used.
+	pub fn with_cache(path: &Path, cache_path: &Path) -> Result<Self> {
+		let key = CacheKey::for_patch(path)?;
+
+		if let Some(lookup) = read_cache(path, cache_path, &key) {
+			return Ok(lookup);
+		}
+
+		let lookup = read_lookup(path)?;
+		if let Err(error) = write_cache(&lookup, cache_path, &key) {
+			log::warn!("Failed to write patch lookup cache {cache_path:?}: {error}");
+		}
+
+		Ok(lookup)
+	}
 }
 
 fn read_lookup(path: &Path) -> Result<PatchLookup> {
@@ -54,37 +158,120 @@ fn read_lookup(path: &Path) -> Result<PatchLookup> {
 	zipatch.chunks().try_fold(
 		PatchLookup {
 			path: path.to_owned(),
-			add_operations: Default::default(),
-			add_commands: Default::default(),
+			writes: Default::default(),
+			index_updates: Default::default(),
 		},
 		|mut lookup, chunk| -> Result<_> {
 			match chunk? {
-				Chunk::SqPack(SqPackChunk::FileOperation(command))
-					if is_sqpack_command(&command) =>
-				{
-					lookup
-						.add_operations
-						.entry(path_to_specifier(&command.path().to_string())?)
-						.or_insert_with(Vec::new)
-						.push(command)
+				Chunk::SqPack(SqPackChunk::FileOperation(command)) => {
+					let path = command.path().to_string();
+					if let (FileOperation::AddFile(blocks), true) =
+						(command.operation(), path.starts_with("sqpack/"))
+					{
+						let chunk = FileChunk {
+							target_offset: command.target_offset(),
+							target_size: command.target_size(),
+							truncate: command.target_offset() == 0,
+							blocks: blocks
+								.iter()
+								.map(|block| FileBlock {
+									source: BlockSource::Block {
+										offset: block.offset(),
+										compressed_size: block.compressed_size(),
+									},
+									size: block.decompressed_size(),
+								})
+								.collect(),
+						};
+
+						lookup.push_write(path_to_specifier(&path)?, chunk);
+					}
 				}
 
+				// Add commands write raw data, followed by a run of zeroes. Commands may
+				// overlap data written earlier in the patch - readers resolve overlaps by
+				// treating later writes as taking precedence.
 				Chunk::SqPack(SqPackChunk::Add(command)) => {
-					let file = command.file();
-					let specifier = SqPackSpecifier {
-						repository: (file.sub_id() >> 8).try_into().unwrap(),
-						category: file.main_id().try_into().unwrap(),
-						chunk: (file.sub_id() & 0xFF).try_into().unwrap(),
-						extension: SqPackFileExtension::Dat(file.file_id().try_into().unwrap()),
-					};
+					let specifier = file_to_specifier(&command.file(), HeaderFileKind::Dat)?;
+					lookup.push_write(
+						specifier,
+						sqpack_chunk(
+							command.target_offset(),
+							[
+								(
+									BlockSource::Raw {
+										offset: command.source_offset(),
+									},
+									command.data_size(),
+								),
+								(BlockSource::Zeroes, command.delete_size()),
+							],
+						),
+					);
+				}
 
-					let old_value = lookup
-						.add_commands
-						.insert((specifier, command.target_offset()), command);
+				// Delete and expand commands mark a region of the file as empty blocks.
+				Chunk::SqPack(SqPackChunk::Delete(command)) => {
+					let specifier = file_to_specifier(&command.file(), HeaderFileKind::Dat)?;
+					lookup.push_write(
+						specifier,
+						sqpack_chunk(
+							command.target_offset(),
+							[(BlockSource::EmptyBlocks, command.delete_size())],
+						),
+					);
+				}
 
-					if old_value.is_some() {
-						panic!("Assumption broken! Multiple chunks in one patch file writing to same offset.")
-					}
+				Chunk::SqPack(SqPackChunk::Expand(command)) => {
+					let specifier = file_to_specifier(&command.file(), HeaderFileKind::Dat)?;
+					lookup.push_write(
+						specifier,
+						sqpack_chunk(
+							command.target_offset(),
+							[(BlockSource::EmptyBlocks, command.delete_size())],
+						),
+					);
+				}
+
+				// The version header sits at the start of the file, with the dat/index
+				// header directly following it.
+				Chunk::SqPack(SqPackChunk::HeaderUpdate(command)) => {
+					let specifier = file_to_specifier(&command.file(), command.file_kind())?;
+					let target_offset = match command.header_kind() {
+						HeaderKind::Version => 0,
+						HeaderKind::Data | HeaderKind::Index => 1024,
+					};
+					lookup.push_write(
+						specifier,
+						sqpack_chunk(
+							target_offset,
+							[(
+								BlockSource::Raw {
+									offset: command.offset(),
+								},
+								command.size(),
+							)],
+						),
+					);
+				}
+
+				// Index updates target the .dat file that the entry points to - the
+				// modified entry lives in that category's index.
+				Chunk::SqPack(SqPackChunk::IndexUpdate(command)) => {
+					let file = command.file();
+					let index = SqPackFile::new(file.main_id(), file.sub_id(), 0);
+					let specifier = file_to_specifier(&index, HeaderFileKind::Index)?;
+					lookup
+						.index_updates
+						.entry(specifier)
+						.or_insert_with(Vec::new)
+						.push(IndexUpdate {
+							delete: matches!(command.kind(), IndexUpdateKind::Delete),
+							is_synonym: command.is_synonym(),
+							data_file: file.file_id(),
+							hash: command.file_hash(),
+							block_offset: command.block_offset(),
+						})
 				}
 
 				_ => {}
@@ -95,9 +282,48 @@ fn read_lookup(path: &Path) -> Result<PatchLookup> {
 	)
 }
 
-fn is_sqpack_command(command: &FileOperationCommand) -> bool {
-	matches!(command.operation(), FileOperation::AddFile(_))
-		&& command.path().to_string().starts_with("sqpack/")
+impl PatchLookup {
+	fn push_write(&mut self, specifier: SqPackSpecifier, chunk: FileChunk) {
+		self.writes.entry(specifier).or_default().push(chunk)
+	}
+}
+
+// Build the chunk written by a SqPack command, omitting any empty blocks.
+fn sqpack_chunk<const N: usize>(target_offset: u32, blocks: [(BlockSource, u32); N]) -> FileChunk {
+	FileChunk {
+		target_offset: target_offset.into(),
+		target_size: blocks.iter().map(|(_, size)| u64::from(*size)).sum(),
+		truncate: false,
+		blocks: blocks
+			.into_iter()
+			.filter(|(_, size)| *size > 0)
+			.map(|(source, size)| FileBlock { source, size })
+			.collect(),
+	}
+}
+
+// Index files are named `.index` for a file ID of 0, and `.index{id}` otherwise.
+fn file_to_specifier(file: &SqPackFile, kind: HeaderFileKind) -> Result<SqPackSpecifier> {
+	let invalid = || {
+		Error::Invalid(
+			ErrorValue::Other(format!("patch target {file:?}")),
+			"target IDs out of range".into(),
+		)
+	};
+
+	let file_id = || u8::try_from(file.file_id()).map_err(|_| invalid());
+	let extension = match (kind, file.file_id()) {
+		(HeaderFileKind::Dat, _) => SqPackFileExtension::Dat(file_id()?),
+		(HeaderFileKind::Index, 0) => SqPackFileExtension::Index(1),
+		(HeaderFileKind::Index, _) => SqPackFileExtension::Index(file_id()?),
+	};
+
+	Ok(SqPackSpecifier {
+		repository: (file.sub_id() >> 8).try_into().map_err(|_| invalid())?,
+		category: file.main_id().try_into().map_err(|_| invalid())?,
+		chunk: (file.sub_id() & 0xFF).try_into().map_err(|_| invalid())?,
+		extension,
+	})
 }
 
 fn path_to_specifier(path: &str) -> Result<SqPackSpecifier> {
@@ -115,12 +341,16 @@ fn path_to_specifier(path: &str) -> Result<SqPackSpecifier> {
 		.and_then(|osstr| osstr.to_str())
 		.ok_or_else(|| path_error(&path, "malformed file name"))?;
 
-	let category = u8::from_str_radix(&file_name[0..2], 16)
-		.map_err(|err| path_error(&path, &format!("{err}")))?;
-	let repository = u8::from_str_radix(&file_name[2..4], 16)
-		.map_err(|err| path_error(&path, &format!("{err}")))?;
-	let chunk = u8::from_str_radix(&file_name[4..6], 16)
-		.map_err(|err| path_error(&path, &format!("{err}")))?;
+	let parse_id = |range: Range<usize>| {
+		let id = file_name
+			.get(range)
+			.ok_or_else(|| path_error(&path, "malformed file name"))?;
+		u8::from_str_radix(id, 16).map_err(|err| path_error(&path, &format!("{err}")))
+	};
+
+	let category = parse_id(0..2)?;
+	let repository = parse_id(2..4)?;
+	let chunk = parse_id(4..6)?;
 
 	let extension = match path.extension().and_then(|osstr| osstr.to_str()) {
 		Some("index") => SqPackFileExtension::Index(1),
@@ -141,3 +371,255 @@ fn path_to_specifier(path: &str) -> Result<SqPackSpecifier> {
 		extension,
 	})
 }
+
+/// Metadata identifying the exact patch file a persisted lookup was built from.
+#[binrw]
+#[brw(little, magic = b"IWZPLKUP")]
+#[derive(Debug, PartialEq, Eq)]
+struct CacheKey {
+	version: u32,
+	size: u64,
+	modified: u64,
+}
+
+impl CacheKey {
+	fn for_patch(path: &Path) -> Result<Self> {
+		let metadata = fs::metadata(path)?;
+		let modified = metadata
+			.modified()?
+			.duration_since(UNIX_EPOCH)
+			.map(|duration| duration.as_nanos())
+			.unwrap_or(0);
+
+		Ok(Self {
+			version: LOOKUP_CACHE_VERSION,
+			size: metadata.len(),
+			modified: u64::try_from(modified).unwrap_or(u64::MAX),
+		})
+	}
+}
+
+// Any failure while reading the cache is treated as a miss - it'll be rebuilt
+// from the patch file and overwritten.
+fn read_cache(path: &Path, cache_path: &Path, key: &CacheKey) -> Option<PatchLookup> {
+	let mut reader = BufReader::new(fs::File::open(cache_path).ok()?);
+
+	let cached_key = reader.read_le::<CacheKey>().ok()?;
+	if cached_key != *key {
+		return None;
+	}
+
+	let read = |reader: &mut BufReader<fs::File>| -> BinResult<PatchLookup> {
+		Ok(PatchLookup {
+			path: path.to_owned(),
+			writes: read_map(reader, read_vec)?,
+			index_updates: read_map(reader, read_vec)?,
+		})
+	};
+
+	read(&mut reader).ok()
+}
+
+fn write_cache(lookup: &PatchLookup, cache_path: &Path, key: &CacheKey) -> Result<()> {
+	if let Some(parent) = cache_path.parent() {
+		fs::create_dir_all(parent)?;
+	}
+
+	// Write to a temporary file first, so a partially written cache is never
+	// picked up by a concurrent or later reader. The name is unique to this write,
+	// so concurrent writers (across or within processes) don't clobber each other.
+	let temp_path = temp_path(cache_path);
+	let result = write_cache_file(lookup, &temp_path, key)
+		.and_then(|_| Ok(fs::rename(&temp_path, cache_path)?));
+	if result.is_err() {
+		fs::remove_file(&temp_path).ok();
+	}
+
+	result
+}
+
+fn temp_path(cache_path: &Path) -> PathBuf {
+	let file_name = cache_path
+		.file_name()
+		.map(|name| name.to_string_lossy().into_owned())
+		.unwrap_or_default();
+	let count = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+	cache_path.with_file_name(format!("{file_name}.{}-{count}.tmp", process::id()))
+}
+
+fn write_cache_file(lookup: &PatchLookup, temp_path: &Path, key: &CacheKey) -> Result<()> {
+	let mut writer = BufWriter::new(fs::File::create(temp_path)?);
+
+	writer.write_le(key)?;
+	write_map(&mut writer, &lookup.writes, |writer, value| {
+		write_vec(writer, value)
+	})?;
+	write_map(&mut writer, &lookup.index_updates, |writer, value| {
+		write_vec(writer, value)
+	})?;
+
+	writer.flush()?;
+
+	Ok(())
+}
+
+fn read_map<R, K, V>(
+	reader: &mut R,
+	read_value: impl Fn(&mut R) -> BinResult<V>,
+) -> BinResult<HashMap<K, V>>
+where
+	R: Read + Seek,
+	K: BinRead<Args = ()> + Eq + Hash,
+{
+	let count = reader.read_le::<u32>()?;
+	(0..count)
+		.map(|_| Ok((reader.read_le::<K>()?, read_value(reader)?)))
+		.collect()
+}
+
+fn write_map<W, K, V>(
+	writer: &mut W,
+	map: &HashMap<K, V>,
+	write_value: impl Fn(&mut W, &V) -> BinResult<()>,
+) -> BinResult<()>
+where
+	W: Write + Seek,
+	K: BinWrite<Args = ()>,
+{
+	writer.write_le(&u32::try_from(map.len()).unwrap())?;
+	for (key, value) in map {
+		writer.write_le(key)?;
+		write_value(writer, value)?;
+	}
+	Ok(())
+}
+
+fn read_vec<R, T>(reader: &mut R) -> BinResult<Vec<T>>
+where
+	R: Read + Seek,
+	T: BinRead<Args = ()>,
+{
+	let count = reader.read_le::<u32>()?;
+	(0..count).map(|_| reader.read_le()).collect()
+}
+
+fn write_vec<W, T>(writer: &mut W, values: &[T]) -> BinResult<()>
+where
+	W: Write + Seek,
+	T: BinWrite<Args = ()>,
+{
+	writer.write_le(&u32::try_from(values.len()).unwrap())?;
+	for value in values {
+		writer.write_le(value)?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use std::io;
+
+	use crate::file::patch::ZiPatchWriter;
+
+	use super::*;
+
+	#[test]
+	fn cache_round_trip() {
+		let directory = tempfile::tempdir().unwrap();
+		let directory = directory.path();
+
+		// An empty patch is sufficient to exercise the cache key.
+		let patch_path = directory.join("D0000.patch");
+		let writer = ZiPatchWriter::new(io::Cursor::new(vec![])).unwrap();
+		let mut patch = writer.finish().unwrap().into_inner();
+		fs::write(&patch_path, &patch).unwrap();
+
+		let specifier = SqPackSpecifier {
+			repository: 0,
+			category: 1,
+			chunk: 2,
+			extension: SqPackFileExtension::Dat(3),
+		};
+		let lookup = PatchLookup {
+			path: patch_path.clone(),
+			writes: HashMap::from([(
+				specifier,
+				vec![
+					FileChunk {
+						target_offset: 128,
+						target_size: 256,
+						truncate: false,
+						blocks: vec![FileBlock {
+							source: BlockSource::Block {
+								offset: 1024,
+								compressed_size: 100,
+							},
+							size: 256,
+						}],
+					},
+					FileChunk {
+						target_offset: 512,
+						target_size: 256,
+						truncate: false,
+						blocks: vec![
+							FileBlock {
+								source: BlockSource::Raw { offset: 2048 },
+								size: 128,
+							},
+							FileBlock {
+								source: BlockSource::Zeroes,
+								size: 128,
+							},
+						],
+					},
+				],
+			)]),
+			index_updates: HashMap::new(),
+		};
+
+		let cache_path = directory.join("cache").join("D0000.lookup");
+		let key = CacheKey::for_patch(&patch_path).unwrap();
+		write_cache(&lookup, &cache_path, &key).unwrap();
+
+		let cached = PatchLookup::with_cache(&patch_path, &cache_path).unwrap();
+		let writes = &cached.writes[&specifier];
+		assert_eq!(
+			writes[0].blocks[0].source,
+			BlockSource::Block {
+				offset: 1024,
+				compressed_size: 100
+			}
+		);
+		assert_eq!(writes[1].blocks[1].source, BlockSource::Zeroes);
+
+		// Changing the patch invalidates the cache, rebuilding from the (empty) patch.
+		patch.extend_from_slice(&[0; 16]);
+		fs::write(&patch_path, &patch).unwrap();
+		let rebuilt = PatchLookup::with_cache(&patch_path, &cache_path).unwrap();
+		assert!(rebuilt.writes.is_empty());
+	}
+
+	#[test]
+	fn cache_write_failure() {
+		let directory = tempfile::tempdir().unwrap();
+		let directory = directory.path();
+
+		let patch_path = directory.join("D0000.patch");
+		let writer = ZiPatchWriter::new(fs::File::create(&patch_path).unwrap()).unwrap();
+		writer.finish().unwrap();
+
+		// The cache's parent is a file, so the cache can't be written.
+		let cache_path = patch_path.join("D0000.lookup");
+		let lookup = PatchLookup::with_cache(&patch_path, &cache_path).unwrap();
+		assert!(lookup.writes.is_empty());
+		assert!(!cache_path.exists());
+	}
+
+	#[test]
+	fn unique_temp_paths() {
+		let cache_path = Path::new("cache/D0000.lookup");
+		let (first, second) = (temp_path(cache_path), temp_path(cache_path));
+		assert_ne!(first, second);
+		assert_eq!(first.parent(), cache_path.parent());
+	}
+}
@@ -1,11 +1,18 @@
 //! Adapters to allow working with game data directly out of ZiPatch files.
 
+mod apply;
+mod create;
+mod diff;
 mod lookup;
+mod reader;
 mod repository;
 mod version;
 mod zipatch;
 
 pub use {
+	apply::{ApplyProgress, PatchApplier},
+	create::DiffBuilder,
+	diff::{IndexChange, IndexChangeKind},
 	repository::PatchRepository,
 	version::{Version, VersionSpecifier},
 	zipatch::ZiPatch,
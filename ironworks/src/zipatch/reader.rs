@@ -0,0 +1,382 @@
+use std::{
+	fs,
+	io::{self, BufReader, Read, Seek, SeekFrom},
+	sync::Arc,
+};
+
+use crate::{
+	error::{Error, ErrorValue, Result},
+	sqpack,
+};
+
+use super::lookup::{BlockSource, FileBlock, FileChunk, PatchLookup};
+
+const SQPACK_BLOCK_SIZE: u32 = 128;
+
+/// Byte range within a target file that is being read.
+#[derive(Debug, Clone, Copy)]
+pub struct TargetRange {
+	pub start: u64,
+	// If the size is unknown, assume the file could be infinitely long.
+	pub end: Option<u64>,
+}
+
+impl TargetRange {
+	pub fn overlaps(&self, offset: u64, size: u64) -> bool {
+		let before_end = self.end.map(|end| offset < end).unwrap_or(true);
+		let after_start = offset.saturating_add(size) > self.start;
+		after_start && before_end
+	}
+}
+
+/// A region of the target file, and the block of patch data it is read from.
+#[derive(Debug)]
+struct Segment {
+	start: u64,
+	end: u64,
+	patch: usize,
+	block_start: u64,
+	block: FileBlock,
+}
+
+/// Reader over a range of a file, composed from data written to it by one or
+/// more patches.
+#[derive(Debug)]
+pub struct FileReader {
+	patches: Vec<BufReader<fs::File>>,
+	range: TargetRange,
+	segments: Vec<Segment>,
+	end: u64,
+
+	position: u64,
+	cache: Option<((usize, u64), Vec<u8>)>,
+}
+
+impl FileReader {
+	/// Build a reader over the specified range, from the provided writes to the
+	/// target file. Sources are ordered newest first - data written by newer
+	/// patches, and later commands within a patch, takes precedence.
+	pub fn new(sources: &[(Arc<PatchLookup>, &[FileChunk])], range: TargetRange) -> Result<Self> {
+		let mut coverage = Coverage::default();
+		let mut segments = Vec::new();
+
+		for (patch, (_, writes)) in sources.iter().enumerate() {
+			for write in writes.iter().rev() {
+				let mut block_start = write.target_offset;
+				for block in &write.blocks {
+					let block_end = block_start
+						.checked_add(block.size.into())
+						.ok_or_else(|| out_of_range(block_start))?;
+
+					let start = block_start.max(range.start);
+					let end = range.end.map_or(block_end, |end| block_end.min(end));
+					if start < end {
+						segments.extend(coverage.insert(start, end).into_iter().map(
+							|(start, end)| Segment {
+								start,
+								end,
+								patch,
+								block_start,
+								block: *block,
+							},
+						));
+					}
+
+					block_start = block_end;
+				}
+			}
+		}
+
+		segments.sort_unstable_by_key(|segment| segment.start);
+		let end = segments.last().map_or(range.start, |segment| segment.end);
+
+		let patches = sources
+			.iter()
+			.map(|(lookup, _)| Ok(BufReader::new(fs::File::open(&lookup.path)?)))
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(Self {
+			patches,
+			range,
+			segments,
+			end,
+
+			position: range.start,
+			cache: None,
+		})
+	}
+
+	/// Check if any data was written to the range covered by this reader.
+	pub fn is_empty(&self) -> bool {
+		self.segments.is_empty()
+	}
+
+	fn read_segment(&mut self, index: usize, buf: &mut [u8]) -> io::Result<usize> {
+		let segment = &self.segments[index];
+		let offset = self.position - segment.block_start;
+		let length = usize::try_from(segment.end - self.position)
+			.unwrap_or(usize::MAX)
+			.min(buf.len());
+		let buf = &mut buf[..length];
+
+		match segment.block.source {
+			BlockSource::Block { .. } => {
+				let key = (segment.patch, segment.block_start);
+				let data = match &mut self.cache {
+					Some((cached, data)) if *cached == key => data,
+					cache => {
+						let data = block_data(&mut self.patches[segment.patch], &segment.block)
+							.map_err(io::Error::other)?;
+						&mut cache.insert((key, data)).1
+					}
+				};
+				// Offset is within the block, which is bounded by u32.
+				let offset = usize::try_from(offset).unwrap();
+				buf.copy_from_slice(&data[offset..offset + length]);
+				Ok(length)
+			}
+
+			BlockSource::Raw { offset: source } => {
+				let reader = &mut self.patches[segment.patch];
+				reader.seek(SeekFrom::Start(source + offset))?;
+				match reader.read(buf)? {
+					0 => Err(io::ErrorKind::UnexpectedEof.into()),
+					count => Ok(count),
+				}
+			}
+
+			BlockSource::Zeroes => {
+				buf.fill(0);
+				Ok(length)
+			}
+
+			BlockSource::EmptyBlocks => {
+				fill_empty_blocks(buf, segment.block.size, offset);
+				Ok(length)
+			}
+		}
+	}
+}
+
+impl Read for FileReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.position >= self.end || buf.is_empty() {
+			return Ok(0);
+		}
+
+		// Regions of the file that were never written read as zeroes.
+		let index = self
+			.segments
+			.partition_point(|segment| segment.end <= self.position);
+		let count = match self.segments.get(index) {
+			Some(segment) if segment.start <= self.position => self.read_segment(index, buf)?,
+			Some(segment) => {
+				let gap = usize::try_from(segment.start - self.position).unwrap_or(usize::MAX);
+				let length = gap.min(buf.len());
+				buf[..length].fill(0);
+				length
+			}
+			None => 0,
+		};
+
+		self.position += u64::try_from(count).unwrap();
+		Ok(count)
+	}
+}
+
+impl Seek for FileReader {
+	fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+		let (base, offset) = match position {
+			SeekFrom::Start(offset) => (0, i64::try_from(offset).ok()),
+			SeekFrom::Current(offset) => (self.position - self.range.start, Some(offset)),
+			SeekFrom::End(offset) => (self.end - self.range.start, Some(offset)),
+		};
+
+		let relative = offset
+			.and_then(|offset| base.checked_add_signed(offset))
+			.ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::InvalidInput,
+					"invalid seek to a negative or overflowing position",
+				)
+			})?;
+
+		self.position = self.range.start.checked_add(relative).ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"invalid seek to an overflowing position",
+			)
+		})?;
+
+		Ok(relative)
+	}
+}
+
+/// Write the data of a single file chunk into a buffer containing the target file.
+pub fn write_chunk(
+	reader: &mut BufReader<fs::File>,
+	chunk: &FileChunk,
+	buffer: &mut Vec<u8>,
+) -> Result<()> {
+	let mut position =
+		usize::try_from(chunk.target_offset).map_err(|_| out_of_range(chunk.target_offset))?;
+	if chunk.truncate {
+		buffer.truncate(position);
+	}
+
+	for block in &chunk.blocks {
+		let data = block_data(reader, block)?;
+		let end = position
+			.checked_add(data.len())
+			.ok_or_else(|| out_of_range(chunk.target_offset))?;
+		if buffer.len() < end {
+			buffer.resize(end, 0);
+		}
+		buffer[position..end].copy_from_slice(&data);
+		position = end;
+	}
+
+	Ok(())
+}
+
+// Read the full contents of a single block.
+fn block_data(reader: &mut BufReader<fs::File>, block: &FileBlock) -> Result<Vec<u8>> {
+	let size = usize::try_from(block.size).map_err(|_| out_of_range(block.size.into()))?;
+
+	let data = match block.source {
+		BlockSource::Block {
+			offset,
+			compressed_size,
+		} => {
+			reader.seek(SeekFrom::Start(offset))?;
+			let mut data = Vec::with_capacity(size);
+			sqpack::BlockPayload::new(reader, compressed_size, block.size)
+				.read_to_end(&mut data)?;
+			data
+		}
+
+		BlockSource::Raw { offset } => {
+			reader.seek(SeekFrom::Start(offset))?;
+			let mut data = Vec::with_capacity(size);
+			reader
+				.by_ref()
+				.take(block.size.into())
+				.read_to_end(&mut data)?;
+			data
+		}
+
+		BlockSource::Zeroes => vec![0; size],
+
+		BlockSource::EmptyBlocks => {
+			let mut data = vec![0; size];
+			fill_empty_blocks(&mut data, block.size, 0);
+			data
+		}
+	};
+
+	if data.len() != size {
+		return Err(Error::Invalid(
+			ErrorValue::Other("zipatch block".into()),
+			format!("expected {size} bytes, got {}", data.len()),
+		));
+	}
+
+	Ok(data)
+}
+
+// Fill a buffer with the data of a run of empty blocks of the specified size,
+// starting at `offset` within the run. This matches the data written when
+// applying delete and expand commands.
+fn fill_empty_blocks(buf: &mut [u8], size: u32, offset: u64) {
+	buf.fill(0);
+
+	// Regions smaller than a single block have no room for a block header.
+	let block_count = size / SQPACK_BLOCK_SIZE;
+	if block_count == 0 {
+		return;
+	}
+
+	let header = [SQPACK_BLOCK_SIZE, 0, 0, block_count - 1, 0]
+		.map(u32::to_le_bytes)
+		.concat();
+	if let Some(header) = usize::try_from(offset)
+		.ok()
+		.and_then(|offset| header.get(offset..))
+	{
+		let length = header.len().min(buf.len());
+		buf[..length].copy_from_slice(&header[..length]);
+	}
+}
+
+fn out_of_range(offset: u64) -> Error {
+	Error::Invalid(
+		ErrorValue::Other(format!("zipatch offset {offset}")),
+		"offset exceeds addressable range".into(),
+	)
+}
+
+/// Set of disjoint byte ranges that have been written.
+#[derive(Debug, Default)]
+struct Coverage {
+	ranges: Vec<(u64, u64)>,
+}
+
+impl Coverage {
+	/// Mark a range as written, returning the parts of it that were not
+	/// previously covered.
+	fn insert(&mut self, start: u64, end: u64) -> Vec<(u64, u64)> {
+		let first = self.ranges.partition_point(|range| range.1 < start);
+		let last = self.ranges.partition_point(|range| range.0 <= end);
+
+		let mut uncovered = Vec::new();
+		let mut position = start;
+		for &(covered_start, covered_end) in &self.ranges[first..last] {
+			if position < covered_start.min(end) {
+				uncovered.push((position, covered_start.min(end)));
+			}
+			position = position.max(covered_end);
+		}
+		if position < end {
+			uncovered.push((position, end));
+		}
+
+		// Merge the new range with any it touches.
+		let merged = self.ranges[first..last]
+			.iter()
+			.fold((start, end), |merged, range| {
+				(merged.0.min(range.0), merged.1.max(range.1))
+			});
+		self.ranges.splice(first..last, [merged]);
+
+		uncovered
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn coverage() {
+		let mut coverage = Coverage::default();
+		assert_eq!(coverage.insert(10, 20), [(10, 20)]);
+		assert_eq!(coverage.insert(30, 40), [(30, 40)]);
+		assert_eq!(coverage.insert(0, 50), [(0, 10), (20, 30), (40, 50)]);
+		assert_eq!(coverage.insert(5, 45), []);
+		assert_eq!(coverage.ranges, [(0, 50)]);
+	}
+
+	#[test]
+	fn empty_blocks() {
+		let mut data = [0xFF; 384];
+		fill_empty_blocks(&mut data, 384, 0);
+		assert_eq!(data[..4], 128u32.to_le_bytes());
+		assert_eq!(data[12..16], 2u32.to_le_bytes());
+		assert!(data[16..].iter().all(|byte| *byte == 0));
+
+		// Reads starting partway through the run only include the rest of the header.
+		let mut data = [0xFF; 8];
+		fill_empty_blocks(&mut data, 384, 10);
+		assert_eq!(data, [0, 0, 2, 0, 0, 0, 0, 0]);
+	}
+}
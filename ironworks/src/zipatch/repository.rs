@@ -12,14 +12,26 @@ pub struct PatchRepository {
 	/// The filesystem path to the folder containing the patch files.
 	pub base_directory: PathBuf,
 	/// List of patch names. This should _not_ include the `.patch` suffix.
+	/// Patches are expected to follow the FFXIV patch ordering - use
+	/// [`new`](Self::new) to build a repository from an unsorted list.
 	pub patches: Vec<String>,
 }
 
 impl PatchRepository {
+	/// Build a repository from a list of patch names within the specified folder.
+	/// Patches will be sorted following the FFXIV patch ordering.
+	pub fn new(base_directory: impl Into<PathBuf>, mut patches: Vec<String>) -> Self {
+		patches.sort_unstable_by(|a, b| sort_patches(a, b));
+		Self {
+			base_directory: base_directory.into(),
+			patches,
+		}
+	}
+
 	/// Read a patch repository from the specified path. Patches will be sorted
 	/// following the FFXIV patch ordering.
 	pub fn at(repository_path: &Path) -> Result<Self> {
-		let mut patches = fs::read_dir(repository_path)?
+		let patches = fs::read_dir(repository_path)?
 			.filter_map(|entry| {
 				let patch_path = match entry {
 					Err(err) => return Some(Err(err)),
@@ -39,15 +51,44 @@ impl PatchRepository {
 			})
 			.collect::<Result<Vec<_>, _>>()?;
 
-		patches.sort_unstable_by(|a, b| sort_patches(a, b));
+		Ok(Self::new(repository_path, patches))
+	}
 
-		Ok(Self {
-			base_directory: repository_path.to_owned(),
-			patches,
-		})
+	/// Iterate over the game versions available in this repository, in ascending
+	/// order. Versions are formatted as they would be in a `.ver` file, i.e.
+	/// `2022.10.13.0000.0000`.
+	pub fn versions(&self) -> impl Iterator<Item = &str> + '_ {
+		// Patches are sorted, so versions are already in ascending order.
+		let mut versions = self
+			.patches
+			.iter()
+			.map(|patch| patch_version(patch))
+			.collect::<Vec<_>>();
+		versions.dedup();
+		versions.into_iter()
+	}
+
+	/// Get the name of the last patch required to bring this repository up to
+	/// the specified game version, if any patches exist at or before it.
+	pub fn patch_for_version(&self, version: &str) -> Option<&str> {
+		self.patches
+			.iter()
+			.rev()
+			.find(|patch| patch_version(patch) <= version)
+			.map(String::as_str)
 	}
 }
 
+/// Get the game version that the named patch will update a repository to.
+pub fn patch_version(patch: &str) -> &str {
+	// Patch names are prefixed by a type character ([D]IFF/[H]IST), and HIST
+	// patches that have been split into parts carry a lowercase part suffix.
+	patch
+		.get(1..)
+		.unwrap_or_default()
+		.trim_end_matches(|char: char| char.is_ascii_lowercase())
+}
+
 fn sort_patches(a: &str, b: &str) -> Ordering {
 	match a[1..].cmp(&b[1..]) {
 		// The prefix "type" character is only ever [D]IFF or [H]IST - HIST always sorts first.
@@ -57,3 +98,73 @@ fn sort_patches(a: &str, b: &str) -> Ordering {
 		order => order,
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn repository(patches: &[&str]) -> PatchRepository {
+		PatchRepository::new(
+			PathBuf::new(),
+			patches.iter().map(|patch| patch.to_string()).collect(),
+		)
+	}
+
+	#[test]
+	fn versions() {
+		let repository = repository(&[
+			"D2017.07.11.0000.0001",
+			"H2017.06.06.0000.0001b",
+			"H2017.06.06.0000.0001a",
+			"D2017.06.06.0000.0001",
+		]);
+
+		assert_eq!(
+			repository.versions().collect::<Vec<_>>(),
+			vec!["2017.06.06.0000.0001", "2017.07.11.0000.0001"]
+		);
+	}
+
+	#[test]
+	fn sorts_patches() {
+		let repository = repository(&[
+			"H2017.06.06.0000.0001b",
+			"D2017.07.11.0000.0001",
+			"D2017.06.06.0000.0001",
+			"H2017.06.06.0000.0001a",
+		]);
+
+		assert_eq!(
+			repository.patches,
+			vec![
+				"D2017.06.06.0000.0001",
+				"H2017.06.06.0000.0001a",
+				"H2017.06.06.0000.0001b",
+				"D2017.07.11.0000.0001",
+			]
+		);
+	}
+
+	#[test]
+	fn patch_for_version() {
+		let repository = repository(&[
+			"H2017.06.06.0000.0001a",
+			"H2017.06.06.0000.0001b",
+			"D2017.07.11.0000.0001",
+		]);
+
+		assert_eq!(repository.patch_for_version("2017.01.01.0000.0000"), None);
+		assert_eq!(
+			repository.patch_for_version("2017.06.06.0000.0001"),
+			Some("H2017.06.06.0000.0001b")
+		);
+		assert_eq!(
+			repository.patch_for_version("2017.07.01.0000.0000"),
+			Some("H2017.06.06.0000.0001b")
+		);
+		assert_eq!(
+			repository.patch_for_version("2099.01.01.0000.0000"),
+			Some("D2017.07.11.0000.0001")
+		);
+	}
+}
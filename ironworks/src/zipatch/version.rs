@@ -1,33 +1,29 @@
 use std::{
-	collections::HashMap,
+	collections::{BTreeMap, HashMap},
 	fs,
-	io::{self, BufReader, Cursor, Seek, SeekFrom},
+	io::{BufReader, Cursor},
+	ops::Range,
 	sync::Arc,
 };
 
-use either::Either;
-
 use crate::{
 	error::{Error, ErrorValue, Result},
-	file::patch::{AddCommand, FileOperation, FileOperationCommand},
 	sqpack,
-	utility::{TakeSeekable, TakeSeekableExt},
 };
 
 use super::{
-	lookup::{PatchLookup, SqPackFileExtension, SqPackSpecifier},
-	repository::PatchRepository,
+	lookup::{FileChunk, IndexUpdate, PatchLookup, SqPackFileExtension, SqPackSpecifier},
+	reader::{write_chunk, FileReader, TargetRange},
+	repository::{patch_version, PatchRepository},
 	zipatch::LookupCache,
 };
 
-type FileReader =
-	Either<TakeSeekable<BufReader<fs::File>>, sqpack::BlockStream<BufReader<fs::File>>>;
-
 /// Specifier of a mapping between repositories and patches within them that
 /// together represent a single cohesive cross-repository version.
 #[derive(Debug)]
 pub struct VersionSpecifier {
 	patches: HashMap<u8, String>,
+	exact: bool,
 }
 
 impl VersionSpecifier {
@@ -35,17 +31,39 @@ impl VersionSpecifier {
 	pub fn latest() -> Self {
 		Self {
 			patches: HashMap::new(),
+			exact: false,
 		}
 	}
 
 	/// Create a VersionSpecifier that will read from, at latest, the specified
 	/// patches. Omitted repository IDs will read from the most recent patch available.
 	pub fn with_patches(patches: HashMap<u8, String>) -> Self {
-		Self { patches }
+		Self {
+			patches,
+			exact: false,
+		}
+	}
+
+	/// Create a VersionSpecifier that will read from, at latest, the specified
+	/// patches. Omitted repository IDs will be treated as unavailable.
+	pub fn exact(patches: HashMap<u8, String>) -> Self {
+		Self {
+			patches,
+			exact: true,
+		}
+	}
+
+	/// Get the patch names this specifier targets, keyed by repository ID.
+	pub fn patches(&self) -> &HashMap<u8, String> {
+		&self.patches
 	}
 }
 
 /// A snapshot into the data available in patch files as of a specified set of patches.
+///
+/// When used as a SqPack resource, the version reported for a repository is the
+/// game version its latest patch updates to (i.e. `2022.10.13.0000.0000`, as
+/// found in `.ver` files), rather than the name of the patch itself.
 #[derive(Debug)]
 pub struct Version {
 	specifier: VersionSpecifier,
@@ -75,6 +93,11 @@ impl Version {
 		})?;
 
 		let target_patch = self.specifier.patches.get(&repository_id);
+		if target_patch.is_none() && self.specifier.exact {
+			return Err(Error::NotFound(ErrorValue::Other(format!(
+				"repository {repository_id}"
+			))));
+		}
 
 		// We're operating at a patch-by-patch granularity here, with the (very safe)
 		// assumption that a game version is at minimum one patch.
@@ -95,7 +118,48 @@ impl Version {
 		Ok(iterator)
 	}
 
-	fn read_index(
+	pub(super) fn repository_ids(&self) -> impl Iterator<Item = u8> + '_ {
+		self.repositories.keys().copied()
+	}
+
+	/// Lookups for the patches at the specified indices within a repository,
+	/// regardless of the patches included in this version.
+	pub(super) fn range_lookups(
+		&self,
+		repository_id: u8,
+		range: Range<usize>,
+	) -> Result<impl Iterator<Item = Result<Arc<PatchLookup>>> + '_> {
+		let repository = self.repositories.get(&repository_id).ok_or_else(|| {
+			Error::NotFound(ErrorValue::Other(format!("repository {repository_id}")))
+		})?;
+
+		let iterator = repository.patches[range]
+			.iter()
+			.map(move |patch| self.cache.lookup(repository_id, repository, patch));
+
+		Ok(iterator)
+	}
+
+	/// Number of patches in the specified repository that are included in this
+	/// version. Included patches are always a prefix of the repository's patches.
+	pub(super) fn patch_count(&self, repository_id: u8) -> usize {
+		let repository = match self.repositories.get(&repository_id) {
+			Some(repository) => repository,
+			None => return 0,
+		};
+
+		match self.specifier.patches.get(&repository_id) {
+			Some(target) => repository
+				.patches
+				.iter()
+				.position(|patch| patch == target)
+				.map_or(0, |index| index + 1),
+			None if self.specifier.exact => 0,
+			None => repository.patches.len(),
+		}
+	}
+
+	pub(super) fn read_index(
 		&self,
 		repository: u8,
 		category: u8,
@@ -109,51 +173,57 @@ impl Version {
 			extension: SqPackFileExtension::Index(index_version),
 		};
 
-		let mut empty = true;
-		let mut cursor = Cursor::new(Vec::<u8>::new());
-
+		// Collect the patches that touch the index, newest first, until we reach a
+		// patch that truncates the file - anything prior to that is irrelevant.
+		let mut sources = Vec::new();
 		for maybe_lookup in self.lookups(repository)? {
-			// Grab the commands for the requested target, if any exist in this patch.
 			let lookup = maybe_lookup?;
-			let commands = match lookup.add_operations.get(&target_specifier) {
-				Some(commands) => commands,
-				None => continue,
-			};
+			let writes = lookup
+				.writes
+				.get(&target_specifier)
+				.map(Vec::as_slice)
+				.unwrap_or_default();
+			let truncation = truncation_start(writes);
+
+			if !writes.is_empty() || lookup.index_updates.contains_key(&target_specifier) {
+				sources.push((lookup, truncation.unwrap_or(0)));
+			}
 
-			// Read the commands for this patch.
-			let mut file = BufReader::new(fs::File::open(&lookup.path)?);
-			for command in commands {
-				empty = false;
-				cursor.set_position(command.target_offset());
-				let blocks = match command.operation() {
-					FileOperation::AddFile(blocks) => blocks,
-					_ => unreachable!(),
-				};
-
-				for block in blocks {
-					file.seek(SeekFrom::Start(block.offset()))?;
-					let mut reader = sqpack::BlockPayload::new(
-						&mut file,
-						block.compressed_size(),
-						block.decompressed_size(),
-					);
-					io::copy(&mut reader, &mut cursor)?;
+			if truncation.is_some() {
+				break;
+			}
+		}
+
+		// Replay the patches oldest-first, applying index updates on top of any file
+		// data written by the same patch.
+		let mut buffer = Vec::new();
+
+		for (lookup, start) in sources.iter().rev() {
+			if let Some(writes) = lookup.writes.get(&target_specifier) {
+				let mut file = BufReader::new(fs::File::open(&lookup.path)?);
+				for write in &writes[*start..] {
+					write_chunk(&mut file, write, &mut buffer)?;
 				}
 			}
 
-			// ASSUMPTION: The offset:0 (first) chunk for a file, even if split across
-			// multiple patches, will _always_ be the first chunk touching that file
-			// within the patch it is in, as any prior file operations would be negated
-			// by the truncation of the file caused by an offset:0 chunk.
+			// Index updates are keyed by the index1 entry hash, and as such only
+			// ever target .index files.
+			if let Some(updates) = lookup.index_updates.get(&target_specifier) {
+				if buffer.is_empty() {
+					return Err(Error::Invalid(
+						ErrorValue::Other(format!("zipatch target {target_specifier:?}")),
+						"index update found prior to index data".into(),
+					));
+				}
 
-			// If this patch started with offset:0, we can stop reading.
-			if !commands.is_empty() && commands[0].target_offset() == 0 {
-				break;
+				for update in updates {
+					apply_index_update(&mut buffer, update)?;
+				}
 			}
 		}
 
 		// If nothing was read, we mark this index as not found.
-		if empty {
+		if buffer.is_empty() {
 			// TODO: Improve the error value.
 			return Err(Error::NotFound(ErrorValue::Other(format!(
 				"zipatch target {target_specifier:?}"
@@ -161,26 +231,82 @@ impl Version {
 		}
 
 		// Done - reset the cursor's position and return it as a view of the index.
-		cursor.set_position(0);
-		Ok(cursor)
+		Ok(Cursor::new(buffer))
+	}
+
+	fn read_file(
+		&self,
+		target: SqPackSpecifier,
+		offset: u32,
+		size: Option<u32>,
+	) -> Result<FileReader> {
+		let range = TargetRange {
+			start: offset.into(),
+			end: size.map(|size| u64::from(offset) + u64::from(size)),
+		};
+
+		// Collect patches with writes touching the target range, newest first,
+		// until a patch truncates the target .dat file.
+		let mut sources = Vec::new();
+		for maybe_lookup in self.lookups(target.repository)? {
+			let lookup = maybe_lookup?;
+
+			let writes = match lookup.writes.get(&target) {
+				Some(writes) => writes,
+				None => continue,
+			};
+
+			let truncation = truncation_start(writes);
+			let start = truncation.unwrap_or(0);
+			let relevant = writes[start..]
+				.iter()
+				.any(|write| range.overlaps(write.target_offset, write.target_size));
+
+			if relevant {
+				sources.push((lookup, start));
+			}
+
+			if truncation.is_some() {
+				break;
+			}
+		}
+
+		let sources = sources
+			.iter()
+			.map(|(lookup, start)| (lookup.clone(), &lookup.writes[&target][*start..]))
+			.collect::<Vec<_>>();
+		let reader = FileReader::new(&sources, range)?;
+
+		if reader.is_empty() {
+			return Err(Error::NotFound(ErrorValue::Other(format!(
+				"zipatch target {:?}",
+				(target, offset)
+			))));
+		}
+
+		Ok(reader)
 	}
 }
 
 impl sqpack::Resource for Version {
 	fn version(&self, repository: u8) -> Result<String> {
-		self.specifier
-			.patches
-			.get(&repository)
-			.cloned()
-			.ok_or_else(|| {
-				Error::Invalid(
-					ErrorValue::Other(format!("repository {repository}")),
-					"unspecified repository version".to_string(),
-				)
-			})
+		let unavailable = || Error::NotFound(ErrorValue::Other(format!("repository {repository}")));
+
+		// Versions are reported as the game version the target patch updates to,
+		// matching the contents of .ver files in a game install.
+		let patch = match self.specifier.patches.get(&repository) {
+			Some(patch) => patch,
+			None if self.specifier.exact => return Err(unavailable()),
+			None => self
+				.repositories
+				.get(&repository)
+				.and_then(|repository| repository.patches.last())
+				.ok_or_else(unavailable)?,
+		};
+
+		Ok(patch_version(patch).to_string())
 	}
 
-	// ASSUMPTION: IndexUpdate chunks are unused, new indexes will always be distributed via FileOperation::AddFile.
 	type Index = Cursor<Vec<u8>>;
 	fn index(&self, repository: u8, category: u8, chunk: u8) -> Result<Self::Index> {
 		self.read_index(repository, category, chunk, 1)
@@ -193,116 +319,503 @@ impl sqpack::Resource for Version {
 
 	type File = FileReader;
 	fn file(&self, repository: u8, category: u8, location: sqpack::Location) -> Result<Self::File> {
-		let target = (
-			SqPackSpecifier {
-				repository,
-				category,
-				chunk: location.chunk(),
-				extension: SqPackFileExtension::Dat(location.data_file()),
-			},
-			location.offset(),
-		);
+		let target = SqPackSpecifier {
+			repository,
+			category,
+			chunk: location.chunk(),
+			extension: SqPackFileExtension::Dat(location.data_file()),
+		};
 
-		for maybe_lookup in self.lookups(repository)? {
-			let lookup = maybe_lookup?;
+		self.read_file(target, location.offset(), location.size())
+	}
+}
 
-			// Try to get the file from the add commands first.
-			// ASSUMPTION: Square seemingly never breaks new files up across multiple
-			// chunks - an entire file can be read by looking for the single add
-			// command starting at the precise offset we're looking for.
-			if let Some(command) = lookup.add_commands.get(&target) {
-				return read_add_command(&lookup, command);
-			};
+// Truncating writes negate any prior writes to the file. Returns the index of
+// the last such write, if any exist.
+fn truncation_start(writes: &[FileChunk]) -> Option<usize> {
+	writes.iter().rposition(|write| write.truncate)
+}
 
-			// Check if the file could be found in the file operations.
-			// ASSUMPTION: Target sqpack files read from a FileCommand-provided .dat
-			// file will not be split across a patch file boundary. While this is
-			// realistically possible, the chances of it occuring are vanishingly
-			// remote. If everything has blown up in your face because of this and you
-			// find this comment, bap me.
-			if let Some(commands) = lookup.add_operations.get(&target.0) {
-				return read_file_commands(&lookup, &location, commands);
-			};
+// Offsets within the SqPack header and index1 header used to patch index entries.
+const SQPACK_HEADER_SIZE_OFFSET: usize = 12;
+const INDEX_DATA_OFFSET: usize = 8;
+const INDEX_DATA_SIZE: usize = 12;
+const TRAILING_SECTION_OFFSETS: [usize; 3] = [84, 156, 228];
+const INDEX_ENTRY_SIZE: usize = 16;
+
+/// Location of the entry data section within an index1 file.
+struct IndexData {
+	header: usize,
+	offset: usize,
+	size: usize,
+}
+
+impl IndexData {
+	fn read(index: &[u8]) -> Option<Self> {
+		let header = read_u32(index, SQPACK_HEADER_SIZE_OFFSET)?;
+		let offset = read_u32(index, header.checked_add(INDEX_DATA_OFFSET)?)?;
+		let size = read_u32(index, header.checked_add(INDEX_DATA_SIZE)?)?;
+
+		// Ensure the data section sits within the index.
+		let end = offset.checked_add(size)?;
+		if index.len() < end {
+			return None;
 		}
 
-		Err(Error::NotFound(ErrorValue::Other(format!(
-			"zipatch target {:?}",
-			target
-		))))
+		Some(Self {
+			header,
+			offset,
+			size,
+		})
+	}
+
+	fn end(&self) -> usize {
+		self.offset + self.size
 	}
 }
 
-fn read_add_command(lookup: &PatchLookup, command: &AddCommand) -> Result<FileReader> {
-	let mut file = BufReader::new(fs::File::open(&lookup.path)?);
-	file.seek(SeekFrom::Start(command.source_offset()))?;
-	let out = file.take_seekable(command.data_size().into())?;
-	Ok(Either::Left(out))
+fn read_u32(index: &[u8], offset: usize) -> Option<usize> {
+	let bytes = index.get(offset..offset.checked_add(4)?)?;
+	usize::try_from(u32::from_le_bytes(bytes.try_into().unwrap())).ok()
 }
 
-fn read_file_commands(
-	lookup: &PatchLookup,
-	location: &sqpack::Location,
-	commands: &[FileOperationCommand],
-) -> Result<FileReader> {
-	let offset = location.offset();
+/// Read the entries of an index1 file, as a map of path hash to packed location.
+pub(super) fn index_entries(index: &[u8]) -> Result<BTreeMap<u64, u32>> {
+	let data = IndexData::read(index).ok_or_else(|| {
+		Error::Invalid(
+			ErrorValue::Other("zipatch index".into()),
+			"index data out of bounds".into(),
+		)
+	})?;
+
+	let entries = index[data.offset..data.end()]
+		.chunks_exact(INDEX_ENTRY_SIZE)
+		.map(|entry| {
+			(
+				u64::from_le_bytes(entry[..8].try_into().unwrap()),
+				u32::from_le_bytes(entry[8..12].try_into().unwrap()),
+			)
+		})
+		.collect();
+
+	Ok(entries)
+}
 
-	let outside_target = |offset: u64, size: u64| {
-		// If the size is available, filter out commands that sit beyond that size -
-		// otherwise, assume the file could be infintely long.
-		let before_end = location
-			.size()
-			.map(|size| offset < (location.offset() + size).into())
-			.unwrap_or(true);
+fn apply_index_update(index: &mut Vec<u8>, update: &IndexUpdate) -> Result<()> {
+	let invalid = |message: &str| {
+		Error::Invalid(
+			ErrorValue::Other(format!("index update {:016x}", update.hash)),
+			message.into(),
+		)
+	};
 
-		let after_start = (offset + size) > location.offset().into();
+	let data = IndexData::read(index).ok_or_else(|| invalid("index data out of bounds"))?;
 
-		after_start && before_end
+	// Entries are sorted by hash - find where the target entry is, or should be.
+	let hash = update.hash;
+	let entry_hash = |index: &[u8], position: usize| {
+		let offset = data.offset + position * INDEX_ENTRY_SIZE;
+		u64::from_le_bytes(index[offset..offset + 8].try_into().unwrap())
 	};
+	let count = data.size / INDEX_ENTRY_SIZE;
+	let (mut low, mut high) = (0, count);
+	while low < high {
+		let middle = (low + high) / 2;
+		match entry_hash(index, middle) < hash {
+			true => low = middle + 1,
+			false => high = middle,
+		}
+	}
+	let entry_offset = data.offset + low * INDEX_ENTRY_SIZE;
+	let exists = low < count && entry_hash(index, low) == hash;
+
+	// Whether the data section grows or shrinks by an entry, if its size changes.
+	let grow = match update.delete {
+		false => {
+			let file_id = update.data_file;
+			if file_id > 0b111 || update.block_offset > u32::MAX >> 4 {
+				return Err(invalid("entry location out of range"));
+			}
+
+			let location = update.block_offset << 4 | file_id << 1 | u32::from(update.is_synonym);
+			let mut entry = [0u8; INDEX_ENTRY_SIZE];
+			entry[..8].copy_from_slice(&hash.to_le_bytes());
+			entry[8..12].copy_from_slice(&location.to_le_bytes());
 
-	// Build an iterator over the commands. We're filtering any commands that sit
-	// outside the target range to minimise further processing.
-	let commands_iter = commands
-		.iter()
-		.filter(|command| outside_target(command.target_offset(), command.target_size()));
-
-	// Extract the metadata for each block in each command.
-	let block_iter = commands_iter.flat_map(|command| {
-		let blocks = match command.operation() {
-			FileOperation::AddFile(blocks) => blocks,
-			other => panic!("unexpected {other:?}"),
+			match exists {
+				true => {
+					index[entry_offset..entry_offset + INDEX_ENTRY_SIZE].copy_from_slice(&entry);
+					None
+				}
+				false => {
+					index.splice(entry_offset..entry_offset, entry);
+					Some(true)
+				}
+			}
+		}
+
+		true => match exists {
+			true => {
+				index.drain(entry_offset..entry_offset + INDEX_ENTRY_SIZE);
+				Some(false)
+			}
+			false => None,
+		},
+	};
+
+	let grow = match grow {
+		Some(grow) => grow,
+		None => return Ok(()),
+	};
+
+	// Resizing the index data shifts every section stored after it.
+	let adjust = |value: usize| {
+		let adjusted = match grow {
+			true => value.checked_add(INDEX_ENTRY_SIZE),
+			false => value.checked_sub(INDEX_ENTRY_SIZE),
 		};
+		adjusted
+			.and_then(|value| u32::try_from(value).ok())
+			.ok_or_else(|| invalid("index size out of range"))
+	};
 
-		blocks.iter().scan(0u64, |file_offset, block| {
-			let current_offset = *file_offset;
-			*file_offset += u64::from(block.decompressed_size());
-
-			Some(sqpack::BlockMetadata {
-				input_offset: block.offset().try_into().unwrap(),
-				input_size: block.compressed_size().try_into().unwrap(),
-				output_offset: (command.target_offset() + current_offset)
-					.try_into()
-					.unwrap(),
-				output_size: block.decompressed_size().try_into().unwrap(),
-			})
-		})
-	});
+	let write_u32 = |index: &mut Vec<u8>, offset: usize, value: u32| -> Result<()> {
+		index
+			.get_mut(offset..offset + 4)
+			.ok_or_else(|| invalid("index header out of bounds"))?
+			.copy_from_slice(&value.to_le_bytes());
+		Ok(())
+	};
+
+	write_u32(index, data.header + INDEX_DATA_SIZE, adjust(data.size)?)?;
+	for section in TRAILING_SECTION_OFFSETS {
+		let offset = read_u32(index, data.header + section)
+			.ok_or_else(|| invalid("index header out of bounds"))?;
+		if offset >= data.end() {
+			write_u32(index, data.header + section, adjust(offset)?)?;
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+pub(super) mod test {
+	use std::io::{self, Read, Seek, SeekFrom};
+
+	use tempfile::TempDir;
+
+	use crate::{
+		file::patch::{
+			Chunk, IndexUpdateCommand, IndexUpdateKind, SqPackChunk, SqPackFile, ZiPatchWriter,
+		},
+		zipatch::{DiffBuilder, PatchRepository, ZiPatch},
+	};
+
+	use super::*;
+
+	pub const CATEGORY: u8 = 0x0a;
+
+	pub type Writer = ZiPatchWriter<io::Cursor<Vec<u8>>>;
+
+	pub fn patch(write: impl FnOnce(&mut Writer) -> Result<()>) -> Vec<u8> {
+		let mut writer = ZiPatchWriter::new(io::Cursor::new(vec![])).unwrap();
+		write(&mut writer).unwrap();
+		writer.finish().unwrap().into_inner()
+	}
+
+	fn sqpack_file(file_id: u32) -> SqPackFile {
+		SqPackFile::new(CATEGORY.into(), 0, file_id)
+	}
+
+	pub fn index_update(
+		writer: &mut Writer,
+		kind: IndexUpdateKind,
+		hash: u64,
+		block_offset: u32,
+	) -> Result<()> {
+		writer.write_chunk(&Chunk::SqPack(SqPackChunk::IndexUpdate(
+			IndexUpdateCommand::new(kind, false, sqpack_file(0), hash, block_offset, 1),
+		)))
+	}
+
+	pub struct Chain {
+		_directory: TempDir,
+		pub zipatch: ZiPatch,
+	}
+
+	impl Chain {
+		pub fn new(patches: Vec<Vec<u8>>) -> Self {
+			let directory = tempfile::tempdir().unwrap();
+			for (index, patch) in patches.into_iter().enumerate() {
+				let path = directory.path().join(format!("D{index:04}.patch"));
+				fs::write(path, patch).unwrap();
+			}
+
+			let zipatch =
+				ZiPatch::new().with_repository(0, PatchRepository::at(directory.path()).unwrap());
+			Self {
+				_directory: directory,
+				zipatch,
+			}
+		}
+
+		fn version(&self) -> Version {
+			self.zipatch.version(VersionSpecifier::latest())
+		}
+	}
+
+	fn dat() -> SqPackSpecifier {
+		SqPackSpecifier {
+			repository: 0,
+			category: CATEGORY,
+			chunk: 0,
+			extension: SqPackFileExtension::Dat(0),
+		}
+	}
+
+	fn read(mut reader: FileReader) -> Vec<u8> {
+		let mut buffer = Vec::new();
+		reader.read_to_end(&mut buffer).unwrap();
+		buffer
+	}
+
+	#[test]
+	fn resource_version() {
+		let chain = Chain::new(vec![patch(|_| Ok(())), patch(|_| Ok(()))]);
+
+		// Reported versions omit the patch kind prefix, matching .ver files.
+		let version = chain.version();
+		assert_eq!(sqpack::Resource::version(&version, 0).unwrap(), "0001");
+
+		let version = chain
+			.zipatch
+			.version(VersionSpecifier::exact(HashMap::from([(
+				0,
+				"D0000".to_string(),
+			)])));
+		assert_eq!(sqpack::Resource::version(&version, 0).unwrap(), "0000");
+		assert!(matches!(
+			sqpack::Resource::version(&version, 1),
+			Err(Error::NotFound(_))
+		));
+	}
 
-	// ASSUMPTION: FileOperation commands will apply their data in sequential order.
+	#[test]
+	fn duplicate_add_commands() {
+		let chain = Chain::new(vec![patch(|writer| {
+			writer.add(sqpack_file(0), 256, &[1; 128], 0)?;
+			writer.add(sqpack_file(0), 256, &[2; 128], 0)
+		})]);
+
+		let file = chain.version().read_file(dat(), 256, None).unwrap();
+		assert_eq!(read(file), vec![2; 128]);
+	}
+
+	#[test]
+	fn file_split_across_patches() {
+		let path = "sqpack/ffxiv/0a0000.win32.dat0";
+		let chain = Chain::new(vec![
+			patch(|writer| {
+				writer.add_file(path, 0, 0, &[0; 64])?;
+				writer.add_file(path, 0, 64, &[1; 64])
+			}),
+			patch(|writer| writer.add_file(path, 0, 128, &[2; 64])),
+		]);
+
+		let file = chain.version().read_file(dat(), 64, Some(128)).unwrap();
+		let expected = [vec![1; 64], vec![2; 64]].concat();
+		assert_eq!(read(file), expected);
+	}
+
+	#[test]
+	fn truncation_hides_older_data() {
+		let path = "sqpack/ffxiv/0a0000.win32.dat0";
+		let chain = Chain::new(vec![
+			patch(|writer| writer.add_file(path, 0, 0, &[1; 128])),
+			patch(|writer| writer.add_file(path, 0, 0, &[2; 32])),
+		]);
+
+		let file = chain.version().read_file(dat(), 64, Some(64));
+		assert!(matches!(file, Err(Error::NotFound(_))));
+	}
+
+	#[test]
+	fn overlapping_add_commands() {
+		let chain = Chain::new(vec![
+			patch(|writer| {
+				writer.add(sqpack_file(0), 0, &[1; 512], 0)?;
+				writer.add(sqpack_file(0), 128, &[2; 128], 0)
+			}),
+			patch(|writer| writer.add(sqpack_file(0), 384, &[3; 128], 128)),
+		]);
+		let version = chain.version();
+
+		let file = version.read_file(dat(), 0, Some(640)).unwrap();
+		let expected = [[1; 128], [2; 128], [1; 128], [3; 128], [0; 128]].concat();
+		assert_eq!(read(file), expected);
+
+		// Reads of unknown size continue to the end of the written data.
+		let file = version.read_file(dat(), 256, None).unwrap();
+		assert_eq!(read(file), expected[256..]);
+	}
+
+	#[test]
+	fn unwritten_regions() {
+		let path = "sqpack/ffxiv/0a0000.win32.dat0";
+		let chain = Chain::new(vec![patch(|writer| {
+			writer.add_file(path, 0, 0, &[1; 64])?;
+			writer.add_file(path, 0, 128, &[2; 64])
+		})]);
+
+		let file = chain.version().read_file(dat(), 0, None).unwrap();
+		let expected = [vec![1; 64], vec![0; 64], vec![2; 64]].concat();
+		assert_eq!(read(file), expected);
+	}
 
-	// Do another pass, filtering out any remaining metadata (from AddFile blocks)
-	// that fall entirely outside the target range.
-	let metadata = block_iter
-		.filter(|meta| {
-			outside_target(
-				meta.output_offset.try_into().unwrap(),
-				meta.output_size.try_into().unwrap(),
+	#[test]
+	fn seek_composed_file() {
+		let chain = Chain::new(vec![
+			patch(|writer| writer.add(sqpack_file(0), 128, &[1; 256], 0)),
+			patch(|writer| writer.add(sqpack_file(0), 256, &[2; 128], 0)),
+		]);
+
+		let mut file = chain.version().read_file(dat(), 128, Some(256)).unwrap();
+		assert_eq!(file.seek(SeekFrom::End(-64)).unwrap(), 192);
+		let mut buffer = Vec::new();
+		file.read_to_end(&mut buffer).unwrap();
+		assert_eq!(buffer, [2; 64]);
+
+		file.seek(SeekFrom::Start(64)).unwrap();
+		let mut buffer = [0; 128];
+		file.read_exact(&mut buffer).unwrap();
+		assert_eq!(buffer[..64], [1; 64]);
+		assert_eq!(buffer[64..], [2; 64]);
+
+		assert!(file.seek(SeekFrom::Current(-512)).is_err());
+	}
+
+	#[test]
+	fn malformed_index() {
+		let mut bytes = index(&[(1, 0x10)]);
+		bytes[1032..1036].copy_from_slice(&u32::MAX.to_le_bytes());
+		assert!(index_entries(&bytes).is_err());
+
+		let update = IndexUpdate {
+			delete: false,
+			is_synonym: false,
+			data_file: 0,
+			hash: 2,
+			block_offset: 0,
+		};
+		assert!(apply_index_update(&mut bytes, &update).is_err());
+
+		bytes[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+		assert!(index_entries(&bytes).is_err());
+	}
+
+	#[test]
+	fn in_place_diff() {
+		let directory = tempfile::tempdir().unwrap();
+		let write = |game: &str, path: &str, data: &[u8]| {
+			let path = directory.path().join(game).join("sqpack/ffxiv").join(path);
+			fs::create_dir_all(path.parent().unwrap()).unwrap();
+			fs::write(path, data).unwrap();
+		};
+		let data = |length: usize, seed: u8| {
+			(0..length)
+				.map(|index| (index as u8).wrapping_mul(31).wrapping_add(seed))
+				.collect::<Vec<_>>()
+		};
+		let empty_blocks = |length: usize| {
+			let mut data = vec![0; length];
+			fill_empty(&mut data);
+			data
+		};
+		fn fill_empty(data: &mut [u8]) {
+			let header = [128, 0, 0, (data.len() / 128 - 1) as u32, 0].map(u32::to_le_bytes);
+			data.fill(0);
+			data[..20].copy_from_slice(&header.concat());
+		}
+
+		// The modified dat region is larger than a single add command.
+		let old_dat = [vec![1; 1024], vec![2; 1024], data(2_000_000, 1)].concat();
+		let mut new_dat = old_dat.clone();
+		new_dat[0] = 3;
+		new_dat[2048..2048 + 1_700_000].copy_from_slice(&data(1_700_000, 2));
+		fill_empty(&mut new_dat[1_900_032..1_900_032 + 1024]);
+		new_dat.extend(empty_blocks(512));
+
+		let old_index = [vec![4; 1024], vec![5; 1024], data(1000, 3)].concat();
+		let mut new_index = old_index.clone();
+		new_index[1024..2048].fill(6);
+		new_index[2500] ^= 0xFF;
+
+		fs::create_dir_all(directory.path().join("empty/sqpack")).unwrap();
+		write("old", "0a0000.win32.dat0", &old_dat);
+		write("old", "0a0000.win32.index", &old_index);
+		write("new", "0a0000.win32.dat0", &new_dat);
+		write("new", "0a0000.win32.index", &new_index);
+
+		let build = |old: &str, new: &str| {
+			DiffBuilder::new(
+				directory.path().join(old).join("sqpack"),
+				directory.path().join(new).join("sqpack"),
 			)
-		})
-		.collect::<Vec<_>>();
+			.build(io::Cursor::new(vec![]))
+			.unwrap()
+			.into_inner()
+		};
+		let chain = Chain::new(vec![build("empty", "old"), build("old", "new")]);
+		let version = chain.version();
+
+		let file = version.read_file(dat(), 0, None).unwrap();
+		assert_eq!(read(file), new_dat);
+		let file = version.read_file(dat(), 1_900_000, Some(128)).unwrap();
+		assert_eq!(read(file), new_dat[1_900_000..1_900_128]);
+
+		let index = version.read_index(0, CATEGORY, 0, 1).unwrap();
+		assert_eq!(index.into_inner(), new_index);
+	}
+
+	pub fn index(entries: &[(u64, u32)]) -> Vec<u8> {
+		let mut bytes = vec![0; 2048];
+		bytes[..8].copy_from_slice(b"SqPack\0\0");
+		bytes[12..16].copy_from_slice(&1024u32.to_le_bytes());
 
-	// Build the readers & complete
-	let file_reader = BufReader::new(fs::File::open(&lookup.path)?);
-	let block_stream = sqpack::BlockStream::new(file_reader, offset.try_into().unwrap(), metadata);
+		let data_size = (entries.len() * INDEX_ENTRY_SIZE) as u32;
+		bytes[1032..1036].copy_from_slice(&2048u32.to_le_bytes());
+		bytes[1036..1040].copy_from_slice(&data_size.to_le_bytes());
+		bytes[1108..1112].copy_from_slice(&(2048 + data_size).to_le_bytes());
 
-	Ok(Either::Right(block_stream))
+		for (hash, data) in entries {
+			bytes.extend_from_slice(&hash.to_le_bytes());
+			bytes.extend_from_slice(&data.to_le_bytes());
+			bytes.extend_from_slice(&[0; 4]);
+		}
+		bytes
+	}
+
+	#[test]
+	fn index_updates() {
+		let path = "sqpack/ffxiv/0a0000.win32.index";
+		let chain = Chain::new(vec![
+			patch(|writer| writer.add_file(path, 0, 0, &index(&[(1, 0x10), (3, 0x30)]))),
+			patch(|writer| {
+				index_update(writer, IndexUpdateKind::Add, 2, 2)?;
+				index_update(writer, IndexUpdateKind::Add, 3, 4)?;
+				index_update(writer, IndexUpdateKind::Delete, 1, 0)
+			}),
+		]);
+
+		let index_bytes = chain
+			.version()
+			.read_index(0, CATEGORY, 0, 1)
+			.unwrap()
+			.into_inner();
+		assert_eq!(
+			&index_bytes[..2048 + 32],
+			&index(&[(2, 0x20), (3, 0x40)])[..]
+		);
+	}
 }
@@ -1,9 +1,11 @@
 use std::{
-	collections::HashMap,
+	collections::{BTreeSet, HashMap},
+	ops::RangeBounds,
+	path::PathBuf,
 	sync::{Arc, RwLock},
 };
 
-use crate::error::Result;
+use crate::error::{Error, ErrorValue, Result};
 
 use super::{
 	lookup::PatchLookup,
@@ -24,10 +26,21 @@ impl ZiPatch {
 	pub fn new() -> Self {
 		Self {
 			repositories: HashMap::default(),
-			data: Arc::new(LookupCache::new()),
+			data: Arc::new(LookupCache::new(None)),
 		}
 	}
 
+	/// Persist patch lookup tables to the specified directory. Building a lookup
+	/// requires reading every chunk of a patch file - persisted lookups will be
+	/// reused on subsequent runs as long as the patch file is unchanged.
+	///
+	/// This should be configured before any versions are created; lookups built
+	/// prior to calling this method will be discarded.
+	pub fn with_cache_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+		self.data = Arc::new(LookupCache::new(Some(directory.into())));
+		self
+	}
+
 	/// Add a patch repository for the given SqPack repository ID.
 	pub fn with_repository(mut self, id: u8, repository: PatchRepository) -> Self {
 		self.add_repository(id, repository);
@@ -39,6 +52,58 @@ impl ZiPatch {
 		self.repositories.insert(id, Arc::new(repository));
 	}
 
+	/// List every game version available across the configured repositories, in
+	/// ascending order.
+	pub fn versions(&self) -> Vec<String> {
+		self.versions_in(..)
+	}
+
+	/// List the game versions within the specified range, in ascending order.
+	pub fn versions_in<'a>(&self, range: impl RangeBounds<&'a str>) -> Vec<String> {
+		self.repositories
+			.values()
+			.flat_map(|repository| repository.versions())
+			.filter(|version| range.contains(version))
+			.collect::<BTreeSet<_>>()
+			.into_iter()
+			.map(str::to_string)
+			.collect()
+	}
+
+	/// Resolve a game version string, i.e. `2022.10.13.0000.0000`, to the set of
+	/// patches that represent that version across the configured repositories.
+	/// The version must be provided by at least one repository. Repositories
+	/// without any patches at or before the version will be considered unavailable.
+	pub fn resolve_version(&self, version: &str) -> Result<VersionSpecifier> {
+		let known = self
+			.repositories
+			.values()
+			.any(|repository| repository.versions().any(|candidate| candidate == version));
+		if !known {
+			return Err(Error::NotFound(ErrorValue::Other(format!(
+				"game version {version}"
+			))));
+		}
+
+		let patches = self
+			.repositories
+			.iter()
+			.filter_map(|(id, repository)| {
+				repository
+					.patch_for_version(version)
+					.map(|patch| (*id, patch.to_string()))
+			})
+			.collect::<HashMap<_, _>>();
+
+		Ok(VersionSpecifier::exact(patches))
+	}
+
+	pub(super) fn repository(&self, id: u8) -> Result<&Arc<PatchRepository>> {
+		self.repositories
+			.get(&id)
+			.ok_or_else(|| Error::NotFound(ErrorValue::Other(format!("repository {id}"))))
+	}
+
 	/// Create a view into the patch data at the specified version. Repositories
 	/// configured with this instance will be snapshot at the point in time the
 	/// version is created.
@@ -57,12 +122,14 @@ impl Default for ZiPatch {
 #[derive(Debug)]
 pub struct LookupCache {
 	cache: RwLock<HashMap<(u8, String), Arc<PatchLookup>>>,
+	directory: Option<PathBuf>,
 }
 
 impl LookupCache {
-	pub fn new() -> Self {
+	pub fn new(directory: Option<PathBuf>) -> Self {
 		Self {
 			cache: Default::default(),
+			directory,
 		}
 	}
 
@@ -84,10 +151,17 @@ impl LookupCache {
 		};
 		drop(cache_read);
 
-		// Build a new lookup for this patch.
-		let lookup = Arc::new(PatchLookup::new(
-			&repository.base_directory.join(format!("{patch}.patch")),
-		)?);
+		// Build a new lookup for this patch, using the persisted copy if available.
+		let patch_path = repository.base_directory.join(format!("{patch}.patch"));
+		let lookup = Arc::new(match &self.directory {
+			Some(directory) => PatchLookup::with_cache(
+				&patch_path,
+				&directory
+					.join(format!("{repository_id}"))
+					.join(format!("{patch}.lookup")),
+			)?,
+			None => PatchLookup::new(&patch_path)?,
+		});
 
 		// Write the new lookup to the cache.
 		let mut cache_write = self.cache.write().unwrap();
@@ -95,3 +169,59 @@ impl LookupCache {
 		Ok(Arc::clone(lookup))
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn repository(patches: &[&str]) -> PatchRepository {
+		PatchRepository::new(
+			PathBuf::new(),
+			patches.iter().map(|patch| patch.to_string()).collect(),
+		)
+	}
+
+	fn zipatch() -> ZiPatch {
+		ZiPatch::new()
+			.with_repository(
+				0,
+				repository(&["H2017.06.06.0000.0001a", "D2017.07.11.0000.0001"]),
+			)
+			.with_repository(1, repository(&["D2017.07.11.0000.0002"]))
+	}
+
+	#[test]
+	fn versions() {
+		assert_eq!(
+			zipatch().versions(),
+			[
+				"2017.06.06.0000.0001",
+				"2017.07.11.0000.0001",
+				"2017.07.11.0000.0002"
+			]
+		);
+	}
+
+	#[test]
+	fn resolve_exact_version() {
+		let specifier = zipatch().resolve_version("2017.07.11.0000.0001").unwrap();
+		assert_eq!(
+			specifier.patches(),
+			&HashMap::from([(0, "D2017.07.11.0000.0001".to_string())])
+		);
+
+		let specifier = zipatch().resolve_version("2017.07.11.0000.0002").unwrap();
+		assert_eq!(specifier.patches().len(), 2);
+	}
+
+	#[test]
+	fn resolve_unknown_version() {
+		// Between, and after, known versions.
+		for version in ["2017.07.01.0000.0000", "2099.01.01.0000.0000"] {
+			assert!(matches!(
+				zipatch().resolve_version(version),
+				Err(Error::NotFound(_))
+			));
+		}
+	}
+}